@@ -0,0 +1,169 @@
+//! Streams packets and frames of a sensor to DDS. Shared by the
+//! `publisher` binary and `ouster publish`.
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use clap::Args;
+
+use ouster_lidar::{
+    DdsBridge, DdsConfig, DdsDurability, DdsPublisher, DdsReliability, HealthConfig, HealthMonitor,
+    HealthThresholds, MetricsRegistry, MetricsServer, Ros2Config, Ros2Publisher, Sensor,
+    SensorConfig,
+};
+
+/// DDS, health and metrics options of publishing.
+#[derive(Debug, Clone, Args)]
+pub struct PublishArgs {
+    /// DDS domain ID.
+    #[arg(long, default_value_t = 0)]
+    pub domain_id: u16,
+    /// Sensor ID put in keys of published samples.
+    #[arg(long, default_value_t = 0)]
+    pub sensor_id: u16,
+    /// Use reliable instead of best-effort delivery.
+    #[arg(long)]
+    pub reliable: bool,
+    /// Keep samples for late-joining subscribers.
+    #[arg(long)]
+    pub transient_local: bool,
+    /// Also publish ROS 2 point clouds, images and IMU messages
+    /// under `/ouster`.
+    #[arg(long)]
+    pub ros2: bool,
+    /// Number of samples kept per instance by subscribers and in total
+    /// by the publisher. Zero keeps up to 1024 samples.
+    #[arg(long, default_value_t = 16)]
+    pub history_depth: i32,
+    /// Alarm when more columns than this are missing in a frame.
+    #[arg(long, default_value_t = 0)]
+    pub max_missing_columns: usize,
+    /// Alarm when the mean sensor-to-host latency in milliseconds
+    /// exceeds this.
+    #[arg(long)]
+    pub max_latency_ms: Option<u64>,
+    /// Serve Prometheus metrics over HTTP on this address.
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+    /// Interval in seconds of polling time sync state and alerts from
+    /// the sensor for metrics.
+    #[arg(long, default_value_t = 10)]
+    pub metrics_interval: u64,
+}
+
+/// Connects to the sensor and publishes until Ctrl-C.
+pub fn run(sensor_config: &SensorConfig, args: &PublishArgs) -> Result<()> {
+    // IMU packets are only published as ROS 2 messages
+    let sensor_config = SensorConfig {
+        imu: sensor_config.imu || args.ros2,
+        ..sensor_config.clone()
+    };
+    let mut sensor = Sensor::connect(&sensor_config)?;
+
+    let mut dds_config = DdsConfig {
+        domain_id: args.domain_id,
+        sensor_id: args.sensor_id,
+        ..Default::default()
+    };
+    if args.reliable {
+        dds_config.qos.reliability = DdsReliability::Reliable;
+    }
+    if args.transient_local {
+        dds_config.qos.durability = DdsDurability::TransientLocal;
+    }
+    dds_config.qos.history_depth = (args.history_depth > 0).then_some(args.history_depth);
+    let mut bridge = DdsBridge::new(DdsPublisher::new(&dds_config)?, sensor.config().clone())?;
+    if args.ros2 {
+        let ros2_config = Ros2Config {
+            domain_id: args.domain_id,
+            ..Default::default()
+        };
+        bridge.set_ros2_publisher(Ros2Publisher::with_participant(
+            bridge.publisher().participant().clone(),
+            ros2_config,
+        )?);
+    }
+
+    let health_config = HealthConfig {
+        thresholds: HealthThresholds {
+            max_missing_columns: Some(args.max_missing_columns),
+            max_out_of_order: Some(0),
+            max_latency: args.max_latency_ms.map(Duration::from_millis),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut monitor = HealthMonitor::new(sensor.config(), health_config)?;
+
+    let sensor_name = args.sensor_id.to_string();
+    let registry = MetricsRegistry::new();
+    let _server = args
+        .metrics_addr
+        .map(|addr| MetricsServer::bind(addr, registry.clone()))
+        .transpose()?;
+    let metrics_interval = Duration::from_secs(args.metrics_interval);
+    let mut last_poll: Option<Instant> = None;
+
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let running = running.clone();
+        ctrlc::set_handler(move || running.store(false, Ordering::SeqCst))?;
+    }
+    println!(
+        "Publishing packets from {} on domain {}. Press Ctrl-C to quit.",
+        sensor_config.lidar_addr, args.domain_id
+    );
+
+    let mut num_packets = 0usize;
+    let mut num_frames = 0usize;
+    let mut num_bad_packets = 0usize;
+
+    while running.load(Ordering::SeqCst) {
+        if args.metrics_addr.is_some()
+            && last_poll.is_none_or(|instant| instant.elapsed() >= metrics_interval)
+        {
+            if let Err(error) = registry.poll_sensor(&sensor_name, sensor.client_mut()) {
+                eprintln!("failed to poll sensor metrics: {:#}", error);
+            }
+            last_poll = Some(Instant::now());
+        }
+        for packet in sensor.recv_imu_packets()? {
+            bridge.push_imu_packet(&packet)?;
+        }
+        let packet = sensor.recv_packet()?;
+        // Windows also close on timeouts, so rates drop when the
+        // stream stops
+        let alarms = match &packet {
+            Some(packet) => monitor.push_packet(packet),
+            None => monitor.poll(),
+        };
+        for alarm in alarms {
+            eprintln!("health alarm: {}", alarm);
+        }
+        registry.update_stream(&sensor_name, &monitor);
+        if let Some(packet) = packet {
+            match bridge.push_packet(&packet) {
+                Ok(count) => num_frames += count,
+                Err(error) => {
+                    eprintln!("dropped packet: {:#}", error);
+                    num_bad_packets += 1;
+                }
+            }
+            num_packets += 1;
+        }
+    }
+    num_frames += bridge.finish()?.iter().count();
+
+    println!(
+        "Published {} packets and {} frames, {} bad packets.",
+        num_packets, num_frames, num_bad_packets
+    );
+    Ok(())
+}
@@ -1,125 +1,269 @@
-use std::time::{Duration};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    net::{SocketAddr, UdpSocket},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
-use log::{debug, trace};
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
 use mio::{Events, Poll, PollOpt, Ready, Token};
 use mio_extras::channel;
-use rustdds::{DomainParticipant, QosPolicyBuilder, StatusEvented, TopicDescription, TopicKind};
-use rustdds::policy::{Durability, History, Reliability};
-use ouster_lidar::{Column, PacketMetaData};
 
-const READER_READY: Token = Token(1);
-const READER_STATUS_READY: Token = Token(2);
+use ouster_lidar::{
+    save_points, Config, DdsConfig, DdsReliability, DdsSubscriber, DdsSubscriptions, Frame,
+    FrameConverter, PointCloudFormat, SensorId,
+};
+
 const STOP_PROGRAM: Token = Token(0);
+const PACKETS_READY: Token = Token(1);
+const FRAMES_READY: Token = Token(2);
+
+/// Where frames come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Source {
+    /// Reconstruct frames from packets.
+    Packets,
+    /// Receive frames assembled by the publisher.
+    Frames,
+}
+
+/// Receives Ouster lidar data from DDS, and optionally writes frames
+/// to point cloud files and forwards packets over UDP.
+#[derive(Debug, Parser)]
+struct Args {
+    /// DDS domain ID.
+    #[arg(long, default_value_t = 0)]
+    domain_id: u16,
+    /// Use reliable instead of best-effort delivery.
+    #[arg(long)]
+    reliable: bool,
+    /// Where frames come from.
+    #[arg(long, value_enum, default_value_t = Source::Packets)]
+    source: Source,
+    /// Sensor config JSON used to convert packets. Defaults to the OS-1 config.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Directory where every frame is written to.
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+    /// Format of written frames, `pcd`, `ply` or `las`.
+    #[arg(long, default_value = "pcd")]
+    format: PointCloudFormat,
+    /// Forward raw packets over UDP to this address.
+    #[arg(long)]
+    forward: Option<SocketAddr>,
+    /// Seconds between statistics reports. Zero disables them.
+    #[arg(long, default_value_t = 1)]
+    stats_interval: u64,
+}
 
+/// Counters of received data.
+#[derive(Debug, Clone, Default)]
+struct Stats {
+    packets: usize,
+    frames: usize,
+    dropped_frames: usize,
+    missing_columns: usize,
+    bad_packets: usize,
+}
 
-fn main() {
-    let topic_name = String::from("OusterLidar");
-    let type_desc = "OusterLidarMessage".to_string();
-    let domain_id = 0;
+impl Stats {
+    fn add(&mut self, other: &Stats) {
+        self.packets += other.packets;
+        self.frames += other.frames;
+        self.dropped_frames += other.dropped_frames;
+        self.missing_columns += other.missing_columns;
+        self.bad_packets += other.bad_packets;
+    }
 
-    let domain_participant: DomainParticipant = DomainParticipant::new(domain_id)
-        .unwrap_or_else(|e| panic!("DomainParticipant construction failed: {:?}", e));
+    fn report(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        println!(
+            "{:.1} packets/s, {:.1} frames/s, {} dropped frames, {} missing columns, {} bad packets",
+            self.packets as f64 / secs,
+            self.frames as f64 / secs,
+            self.dropped_frames,
+            self.missing_columns,
+            self.bad_packets
+        );
+    }
+}
 
-    let qos_b = QosPolicyBuilder::new()
-        .reliability(Reliability::BestEffort)
-        .durability(Durability::Volatile)
-        .history(History::KeepAll);
+struct FrameSink {
+    config: Config,
+    output_dir: Option<PathBuf>,
+    format: PointCloudFormat,
+    frame_counts: HashMap<SensorId, usize>,
+}
 
-    let qos = qos_b.build();
+impl FrameSink {
+    fn push(&mut self, sensor_id: SensorId, frame: &Frame, stats: &mut Stats) -> Result<()> {
+        let completeness = &frame.completeness;
+        stats.frames += 1;
+        stats.dropped_frames += frame.skipped_frame_ids.len();
+        stats.missing_columns += completeness
+            .expected_columns
+            .saturating_sub(completeness.valid_columns()) as usize;
 
-    let loop_delay = Duration::from_millis(200);
+        let count = self.frame_counts.entry(sensor_id).or_default();
+        if let Some(output_dir) = &self.output_dir {
+            let path = output_dir.join(format!(
+                "sensor{}_frame{:06}.{}",
+                sensor_id,
+                count,
+                self.format.extension()
+            ));
+            save_points(path, &frame.points, self.format)?;
+        }
+        *count += 1;
+        Ok(())
+    }
+}
 
-    let topic = domain_participant
-        .create_topic(
-            topic_name,
-            type_desc,
-            &qos,
-            TopicKind::WithKey,
-        )
-        .unwrap_or_else(|e| panic!("create_topic failed: {:?}", e));
+fn main() -> Result<()> {
+    let args = Args::parse();
+    // Packets are read to convert or forward them
+    let subscriptions = DdsSubscriptions {
+        packets: args.source == Source::Packets || args.forward.is_some(),
+        metadata: false,
+        frames: args.source == Source::Frames,
+    };
+    let mut dds_config = DdsConfig {
+        domain_id: args.domain_id,
+        subscriptions,
+        ..Default::default()
+    };
+    if args.reliable {
+        dds_config.qos.reliability = DdsReliability::Reliable;
+    }
+    let mut subscriber = DdsSubscriber::new(&dds_config)?;
 
-    println!(
-        "Topic name is {}. Type is {}.",
-        topic.name(),
-        topic.get_type().name()
-    );
+    let config = match &args.config {
+        Some(path) => Config::from_path(path)?,
+        None => Config::os_1_config(),
+    };
+    if let Some(output_dir) = &args.output_dir {
+        std::fs::create_dir_all(output_dir)?;
+    }
+    let forward_socket = match args.forward {
+        Some(_) => Some(UdpSocket::bind("0.0.0.0:0")?),
+        None => None,
+    };
+    let mut converters: HashMap<SensorId, FrameConverter> = HashMap::new();
+    let mut sink = FrameSink {
+        config,
+        output_dir: args.output_dir.clone(),
+        format: args.format,
+        frame_counts: HashMap::new(),
+    };
 
     // Set Ctrl-C handler
     let (stop_sender, stop_receiver) = channel::channel();
     ctrlc::set_handler(move || {
         stop_sender.send(()).unwrap_or(());
         // ignore errors, as we are quitting anyway
-    })
-        .expect("Error setting Ctrl-C handler");
+    })?;
     println!("Press Ctrl-C to quit.");
 
-    let poll = Poll::new().unwrap();
+    let poll = Poll::new()?;
     let mut events = Events::with_capacity(4);
-
-
     poll.register(
         &stop_receiver,
         STOP_PROGRAM,
         Ready::readable(),
         PollOpt::edge(),
-    ).unwrap();
-
-
-    let mut reader = {
-        let subscriber = domain_participant.create_subscriber(&qos).unwrap();
-        let mut reader = subscriber
-            .create_datareader_cdr::<PacketMetaData>(&topic, Some(qos))
-            .unwrap();
-        poll
-            .register(&reader, READER_READY, Ready::readable(), PollOpt::edge())
-            .unwrap();
-        poll
-            .register(
-                reader.as_status_evented(),
-                READER_STATUS_READY,
-                Ready::readable(),
-                PollOpt::edge(),
-            )
-            .unwrap();
-        debug!("Created DataReader");
-        reader
-    };
+    )?;
+    if subscriptions.packets {
+        subscriber.register_packets(&poll, PACKETS_READY)?;
+    }
+    if subscriptions.frames {
+        subscriber.register_frames(&poll, FRAMES_READY)?;
+    }
+
+    let stats_interval = Duration::from_secs(args.stats_interval);
+    let mut stats = Stats::default();
+    let mut total = Stats::default();
+    let mut last_report = Instant::now();
 
     loop {
-        poll.poll(&mut events, Some(loop_delay)).unwrap();
+        poll.poll(&mut events, Some(Duration::from_millis(200)))?;
         for event in &events {
             match event.token() {
-                STOP_PROGRAM => {
-                    if stop_receiver.try_recv().is_ok() {
-                        println!("Done.");
-                        return;
+                STOP_PROGRAM if stop_receiver.try_recv().is_ok() => {
+                    for (sensor_id, converter) in converters.drain() {
+                        if let Some(frame) = converter.finish() {
+                            sink.push(sensor_id, &frame, &mut stats)?;
+                        }
                     }
+                    total.add(&stats);
+                    println!(
+                        "Received {} packets and {} frames, {} dropped frames, {} missing columns, {} bad packets.",
+                        total.packets,
+                        total.frames,
+                        total.dropped_frames,
+                        total.missing_columns,
+                        total.bad_packets
+                    );
+                    return Ok(());
                 }
-
-                READER_READY => loop {
-                    trace!("DataReader triggered");
-                    match reader.take_next_sample() {
-                        Ok(Some(sample)) => match sample.into_value() {
-                            Ok(sample) => println!(
-                                "{} {:?} \n\n\n",
-                                topic.name(),
-                                sample
-                            ),
-                            Err(key) => println!("Disposed key {:?}", key),
-                        },
-                        Ok(None) => break, // no more data
-                        Err(e) => println!("DataReader error {:?}", e),
-                    } // match
-                },
-                READER_STATUS_READY => {
-                    while let Some(status) = reader.try_recv_status() {
-                        println!("DataReader status: {:?}", status);
+                PACKETS_READY => {
+                    for message in subscriber.take_packets()? {
+                        stats.packets += 1;
+                        if let (Some(socket), Some(addr)) = (&forward_socket, args.forward) {
+                            socket.send_to(message.packet.as_bytes(), addr)?;
+                        }
+                        if args.source != Source::Packets {
+                            continue;
+                        }
+                        let converter = match converters.entry(message.sensor_id) {
+                            Entry::Occupied(entry) => entry.into_mut(),
+                            Entry::Vacant(entry) => {
+                                entry.insert(FrameConverter::from_config(sink.config.clone())?)
+                            }
+                        };
+                        match converter.push_packet(message.packet) {
+                            Ok(frames) => {
+                                for frame in frames {
+                                    sink.push(message.sensor_id, &frame, &mut stats)?;
+                                }
+                            }
+                            // A sensor that rewinds, e.g. on reboot, only
+                            // loses its frame in progress
+                            Err(error) => {
+                                eprintln!(
+                                    "dropped packet of sensor {}: {:#}",
+                                    message.sensor_id, error
+                                );
+                                stats.bad_packets += 1;
+                                converter.reset();
+                            }
+                        }
                     }
                 }
-                other_token => {
-                    println!("Polled event is {:?}. WTF?", other_token);
+                FRAMES_READY => {
+                    for message in subscriber.take_frame_messages()? {
+                        match message.to_frame() {
+                            Ok(frame) => sink.push(message.sensor_id, &frame, &mut stats)?,
+                            Err(error) => {
+                                eprintln!(
+                                    "dropped frame of sensor {}: {:#}",
+                                    message.sensor_id, error
+                                );
+                                stats.bad_packets += 1;
+                            }
+                        }
+                    }
                 }
+                _ => {}
             }
         }
+
+        if !stats_interval.is_zero() && last_report.elapsed() >= stats_interval {
+            stats.report(last_report.elapsed());
+            total.add(&stats);
+            stats = Stats::default();
+            last_report = Instant::now();
+        }
     }
-}
\ No newline at end of file
+}
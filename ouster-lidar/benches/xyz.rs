@@ -0,0 +1,83 @@
+//! Compares converting the packets of a frame to a `Vec<Point>`, the
+//! conversion path of frames, with writing them to a `PointBuffer`, and
+//! with the bare lookup table that both use for positions.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use ouster_lidar::{
+    config::Config, consts::PIXELS_PER_COLUMN, packet::Packet, pcd_converter::PointCloudConverter,
+    point_buffer::PointBuffer,
+};
+
+/// Packets of a frame with ranges up to 100 m and some missing
+/// returns.
+fn frame_packets() -> Vec<Packet> {
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 0);
+    for (index, column) in packets
+        .iter_mut()
+        .flat_map(|packet| packet.columns.iter_mut())
+        .enumerate()
+    {
+        for (laser_id, pixel) in column.pixels.iter_mut().enumerate() {
+            pixel.raw_distance = ((index * PIXELS_PER_COLUMN + laser_id) as u32 * 7919) % 100_000;
+        }
+    }
+    packets
+}
+
+fn convert_frame(c: &mut Criterion) {
+    let mut config = Config::os_1_config();
+    config.lidar_origin_to_beam_origin_mm(12.163);
+    let converter = PointCloudConverter::from_config(config).unwrap();
+    let lut = converter.xyz_lut();
+    let packets = frame_packets();
+    let columns: Vec<_> = packets
+        .iter()
+        .flat_map(|packet| packet.columns.iter())
+        .map(|column| {
+            let ranges = column.pixels.map(|pixel| pixel.distance_millimeter());
+            (column.measurement_id, ranges)
+        })
+        .collect();
+    let mut xyz = vec![[[0.0; PIXELS_PER_COLUMN]; 3]; columns.len()];
+    let mut buffer = PointBuffer::with_capacity(columns.len() * PIXELS_PER_COLUMN);
+
+    let mut group = c.benchmark_group("frame_xyz");
+    group.throughput(Throughput::Elements(
+        (columns.len() * PIXELS_PER_COLUMN) as u64,
+    ));
+    group.bench_function("points", |b| {
+        b.iter(|| {
+            let points: Vec<_> = packets
+                .iter()
+                .map(|packet| converter.convert(black_box(packet)).unwrap())
+                .collect();
+            black_box(points);
+        })
+    });
+    group.bench_function("point_buffer", |b| {
+        b.iter(|| {
+            buffer.clear();
+            for packet in packets.iter() {
+                converter
+                    .convert_into(black_box(packet), &mut buffer)
+                    .unwrap();
+            }
+            black_box(&buffer);
+        })
+    });
+    group.bench_function("lut", |b| {
+        b.iter(|| {
+            for ((measurement_id, ranges), xyz) in columns.iter().zip(xyz.iter_mut()) {
+                lut.column_xyz(*measurement_id, black_box(ranges), xyz);
+            }
+            black_box(&xyz);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, convert_frame);
+criterion_main!(benches);
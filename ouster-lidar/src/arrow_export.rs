@@ -0,0 +1,172 @@
+//! Conversion of frames to Apache Arrow record batches and Parquet
+//! files, enabled by the `parquet` feature.
+//!
+//! [ParquetFrameWriter] writes one file per frame in Hive-style
+//! `frame=<index>` directories, so DataFusion, pyarrow and pandas read
+//! the output directory as a single dataset partitioned by frame.
+
+use super::{config::Config, frame_converter::Frame, pcd_converter::Point};
+use crate::common::*;
+use arrow_array::{ArrayRef, Float32Array, RecordBatch, UInt16Array, UInt32Array, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::{
+    arrow::ArrowWriter,
+    basic::Compression,
+    file::{metadata::KeyValue, properties::WriterProperties},
+};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+/// Key of the sensor config JSON in Parquet key-value metadata.
+pub const PARQUET_CONFIG_KEY: &str = "ouster.config";
+
+/// Returns the schema of [frame_to_record_batch] output.
+///
+/// | column         | type   | unit                      |
+/// |----------------|--------|---------------------------|
+/// | `frame_id`     | uint16 |                           |
+/// | `x`, `y`, `z`  | float  | meters                    |
+/// | `range`        | uint32 | millimeters               |
+/// | `reflectivity` | uint16 |                           |
+/// | `signal`       | uint16 | photons                   |
+/// | `noise`        | uint16 | photons                   |
+/// | `laser_id`     | uint16 |                           |
+/// | `column`       | uint16 | measurement ID            |
+/// | `timestamp`    | uint64 | nanoseconds, sensor clock |
+pub fn frame_schema() -> SchemaRef {
+    let unit = |unit: &str| HashMap::from([("unit".to_string(), unit.to_string())]);
+    Arc::new(Schema::new(vec![
+        Field::new("frame_id", DataType::UInt16, false),
+        Field::new("x", DataType::Float32, false).with_metadata(unit("m")),
+        Field::new("y", DataType::Float32, false).with_metadata(unit("m")),
+        Field::new("z", DataType::Float32, false).with_metadata(unit("m")),
+        Field::new("range", DataType::UInt32, false).with_metadata(unit("mm")),
+        Field::new("reflectivity", DataType::UInt16, false),
+        Field::new("signal", DataType::UInt16, false),
+        Field::new("noise", DataType::UInt16, false),
+        Field::new("laser_id", DataType::UInt16, false),
+        Field::new("column", DataType::UInt16, false),
+        Field::new("timestamp", DataType::UInt64, false).with_metadata(unit("ns")),
+    ]))
+}
+
+/// Converts the points of a frame to a record batch of
+/// [frame_schema].
+pub fn frame_to_record_batch(frame: &Frame) -> Result<RecordBatch> {
+    let points = &frame.points;
+    let coordinate = |axis: usize| -> ArrayRef {
+        Arc::new(Float32Array::from_iter_values(
+            points
+                .iter()
+                .map(|point| point.point[axis].as_meters() as f32),
+        ))
+    };
+    let attribute = |value: fn(&Point) -> u16| -> ArrayRef {
+        Arc::new(UInt16Array::from_iter_values(points.iter().map(value)))
+    };
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt16Array::from_value(frame.frame_id, points.len())),
+        coordinate(0),
+        coordinate(1),
+        coordinate(2),
+        Arc::new(UInt32Array::from_iter_values(
+            points
+                .iter()
+                .map(|point| point.distance.as_millimeters().round() as u32),
+        )),
+        attribute(|point| point.reflectivity),
+        attribute(|point| point.signal_photons),
+        attribute(|point| point.noise_photons),
+        attribute(|point| point.laser_id as u16),
+        attribute(|point| point.measurement_id),
+        Arc::new(UInt64Array::from_iter_values(
+            points.iter().map(|point| point.timestamp.as_nanos() as u64),
+        )),
+    ];
+    Ok(RecordBatch::try_new(frame_schema(), columns)?)
+}
+
+impl Frame {
+    /// Converts the points to a record batch. See
+    /// [frame_to_record_batch].
+    pub fn to_record_batch(&self) -> Result<RecordBatch> {
+        frame_to_record_batch(self)
+    }
+}
+
+/// Writes frames to Parquet files partitioned by frame.
+///
+/// The sensor config and other added metadata are stored in the
+/// key-value metadata of every file.
+#[derive(Debug)]
+pub struct ParquetFrameWriter {
+    dir: PathBuf,
+    metadata: Vec<KeyValue>,
+    num_frames: usize,
+}
+
+impl ParquetFrameWriter {
+    /// Creates the output directory and a writer that records the
+    /// config under [PARQUET_CONFIG_KEY].
+    pub fn create<P: AsRef<Path>>(dir: P, config: &Config) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        let metadata = vec![
+            KeyValue::new(
+                PARQUET_CONFIG_KEY.to_string(),
+                serde_json::to_string(config)?,
+            ),
+            KeyValue::new(
+                "ouster.lidar_mode".to_string(),
+                config.lidar_mode.to_string(),
+            ),
+        ];
+        Ok(Self {
+            dir,
+            metadata,
+            num_frames: 0,
+        })
+    }
+
+    /// Adds a key-value pair to the metadata of files written from now
+    /// on, such as the sensor serial number.
+    pub fn add_metadata(&mut self, key: &str, value: &str) {
+        self.metadata
+            .push(KeyValue::new(key.to_string(), value.to_string()));
+    }
+
+    /// Returns the number of frames written.
+    pub fn num_frames(&self) -> usize {
+        self.num_frames
+    }
+
+    /// Writes a frame to `frame=<index>/part-0.parquet` in the output
+    /// directory and returns the file path.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<PathBuf> {
+        let partition = self.dir.join(format!("frame={:06}", self.num_frames));
+        std::fs::create_dir_all(&partition)?;
+        let path = partition.join("part-0.parquet");
+
+        let mut metadata = self.metadata.clone();
+        metadata.extend([
+            KeyValue::new("ouster.frame_id".to_string(), frame.frame_id.to_string()),
+            KeyValue::new(
+                "ouster.start_time_ns".to_string(),
+                frame.start_time().as_nanos().to_string(),
+            ),
+        ]);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_key_value_metadata(Some(metadata))
+            .build();
+
+        let batch = frame.to_record_batch()?;
+        let file = File::create(&path)?;
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties))?;
+        writer.write(&batch)?;
+        writer.close()?;
+
+        self.num_frames += 1;
+        Ok(path)
+    }
+}
@@ -0,0 +1,383 @@
+//! rosbag2 compatible MCAP recording of packets and ROS 2 messages.
+//!
+//! Messages are stored CDR encoded with `ros2msg` schemas, so the
+//! files can be opened by Foxglove Studio and `ros2 bag`. The sensor
+//! [Config] is embedded as an MCAP metadata record, which lets
+//! [BagReader] convert recorded packets to frames without the sensor.
+
+use super::{
+    config::Config,
+    frame_converter::{Frame, FrameConverter},
+    packet::{ImuPacket, Packet},
+    parallel::PACKETS_PER_BATCH,
+    ros2::{from_cdr_bytes, to_cdr_bytes, Imu, PacketMsg, PointCloud2},
+};
+use crate::common::*;
+use mcap::{
+    read::{LinearReader, Summary},
+    records::{MessageHeader, Metadata, Record},
+    MessageStream, WriteOptions,
+};
+use memmap2::Mmap;
+use std::{collections::BTreeMap, io::BufWriter};
+
+/// Name of the metadata record that stores the sensor config.
+pub const BAG_METADATA_NAME: &str = "ouster";
+
+const PACKET_MSG_SCHEMA: &str = "uint8[] buf\n";
+
+const HEADER_SCHEMA: &str = "\
+================================================================================
+MSG: std_msgs/Header
+builtin_interfaces/Time stamp
+string frame_id
+================================================================================
+MSG: builtin_interfaces/Time
+int32 sec
+uint32 nanosec
+";
+
+const POINT_CLOUD2_SCHEMA: &str = "\
+std_msgs/Header header
+uint32 height
+uint32 width
+PointField[] fields
+bool is_bigendian
+uint32 point_step
+uint32 row_step
+uint8[] data
+bool is_dense
+================================================================================
+MSG: sensor_msgs/PointField
+uint8 INT8=1
+uint8 UINT8=2
+uint8 INT16=3
+uint8 UINT16=4
+uint8 INT32=5
+uint8 UINT32=6
+uint8 FLOAT32=7
+uint8 FLOAT64=8
+string name
+uint32 offset
+uint8 datatype
+uint32 count
+";
+
+const IMU_SCHEMA: &str = "\
+std_msgs/Header header
+geometry_msgs/Quaternion orientation
+float64[9] orientation_covariance
+geometry_msgs/Vector3 angular_velocity
+float64[9] angular_velocity_covariance
+geometry_msgs/Vector3 linear_acceleration
+float64[9] linear_acceleration_covariance
+================================================================================
+MSG: geometry_msgs/Quaternion
+float64 x 0
+float64 y 0
+float64 z 0
+float64 w 1
+================================================================================
+MSG: geometry_msgs/Vector3
+float64 x
+float64 y
+float64 z
+";
+
+/// Topic names of recorded messages.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BagTopics {
+    pub lidar_packets: String,
+    pub imu_packets: String,
+    pub points: String,
+    pub imu: String,
+}
+
+impl Default for BagTopics {
+    fn default() -> Self {
+        Self {
+            lidar_packets: "/ouster/lidar_packets".into(),
+            imu_packets: "/ouster/imu_packets".into(),
+            points: "/ouster/points".into(),
+            imu: "/ouster/imu".into(),
+        }
+    }
+}
+
+/// A message read from a recording.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BagMessage {
+    LidarPacket(Box<Packet>),
+    ImuPacket(ImuPacket),
+    Points(PointCloud2),
+    Imu(Box<Imu>),
+}
+
+/// Writes packets and ROS 2 messages to an MCAP file.
+///
+/// Log times of messages are taken from packet and header timestamps.
+pub struct BagWriter<W: Write + Seek> {
+    writer: mcap::Writer<W>,
+    lidar_packets_channel: u16,
+    imu_packets_channel: u16,
+    points_channel: u16,
+    imu_channel: u16,
+    sequence: u32,
+}
+
+impl BagWriter<BufWriter<File>> {
+    /// Creates the MCAP file at path with default topics.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::create(path.as_ref())?;
+        Self::new(BufWriter::new(file), &BagTopics::default())
+    }
+}
+
+impl<W: Write + Seek> BagWriter<W> {
+    /// Starts writing a recording with given topics.
+    pub fn new(writer: W, topics: &BagTopics) -> Result<Self> {
+        let mut writer = WriteOptions::new().profile("ros2").create(writer)?;
+        let mut add_channel = |topic: &str, name: &str, schema: String| -> Result<u16> {
+            let schema_id = writer.add_schema(name, "ros2msg", schema.as_bytes())?;
+            let channel_id = writer.add_channel(schema_id, topic, "cdr", &BTreeMap::new())?;
+            Ok(channel_id)
+        };
+
+        let packet_msg = "ouster_sensor_msgs/msg/PacketMsg";
+        Ok(Self {
+            lidar_packets_channel: add_channel(
+                &topics.lidar_packets,
+                packet_msg,
+                PACKET_MSG_SCHEMA.into(),
+            )?,
+            imu_packets_channel: add_channel(
+                &topics.imu_packets,
+                packet_msg,
+                PACKET_MSG_SCHEMA.into(),
+            )?,
+            points_channel: add_channel(
+                &topics.points,
+                "sensor_msgs/msg/PointCloud2",
+                format!("{}{}", POINT_CLOUD2_SCHEMA, HEADER_SCHEMA),
+            )?,
+            imu_channel: add_channel(
+                &topics.imu,
+                "sensor_msgs/msg/Imu",
+                format!("{}{}", IMU_SCHEMA, HEADER_SCHEMA),
+            )?,
+            writer,
+            sequence: 0,
+        })
+    }
+
+    /// Embeds the sensor config as a metadata record.
+    pub fn write_config(&mut self, config: &Config) -> Result<()> {
+        let metadata = Metadata {
+            name: BAG_METADATA_NAME.into(),
+            metadata: BTreeMap::from([("config".to_string(), serde_json::to_string(config)?)]),
+        };
+        self.writer.write_metadata(&metadata)?;
+        Ok(())
+    }
+
+    /// Writes a lidar packet as `PacketMsg`.
+    ///
+    /// Like rosbag2, messages are logged at `log_time`, the Unix time
+    /// in nanoseconds when the host received them, so that players
+    /// order all topics on the same clock.
+    pub fn write_packet(&mut self, packet: &Packet, log_time: u64) -> Result<()> {
+        self.write(
+            self.lidar_packets_channel,
+            log_time,
+            &PacketMsg::from(packet),
+        )
+    }
+
+    /// Writes an IMU packet as `PacketMsg` logged at `log_time`.
+    pub fn write_imu_packet(&mut self, packet: &ImuPacket, log_time: u64) -> Result<()> {
+        self.write(self.imu_packets_channel, log_time, &PacketMsg::from(packet))
+    }
+
+    /// Writes a point cloud logged at `log_time`.
+    pub fn write_points(&mut self, cloud: &PointCloud2, log_time: u64) -> Result<()> {
+        self.write(self.points_channel, log_time, cloud)
+    }
+
+    /// Writes an IMU message logged at `log_time`.
+    pub fn write_imu(&mut self, imu: &Imu, log_time: u64) -> Result<()> {
+        self.write(self.imu_channel, log_time, imu)
+    }
+
+    /// Writes the summary and returns the inner writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.finish()?;
+        Ok(self.writer.into_inner())
+    }
+
+    fn write<T: Serialize>(&mut self, channel_id: u16, log_time: u64, message: &T) -> Result<()> {
+        let header = MessageHeader {
+            channel_id,
+            sequence: self.sequence,
+            log_time,
+            publish_time: log_time,
+        };
+        self.writer
+            .write_to_known_channel(&header, &to_cdr_bytes(message)?)?;
+        self.sequence = self.sequence.wrapping_add(1);
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> Debug for BagWriter<W> {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("BagWriter")
+            .field("sequence", &self.sequence)
+            .finish()
+    }
+}
+
+/// Bytes of a recording, either mapped from a file or in memory.
+#[derive(Debug)]
+enum BagData {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl std::ops::Deref for BagData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Mapped(mmap) => mmap,
+            Self::Owned(data) => data,
+        }
+    }
+}
+
+/// Reads recordings written by [BagWriter].
+#[derive(Debug)]
+pub struct BagReader {
+    data: BagData,
+    topics: BagTopics,
+}
+
+impl BagReader {
+    /// Maps the MCAP file at path into memory with default topics.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path.as_ref())?;
+        // SAFETY: The file is only read. Recordings are not expected to
+        // be modified while they are read.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self {
+            data: BagData::Mapped(mmap),
+            topics: BagTopics::default(),
+        })
+    }
+
+    /// Reads a recording in memory with given topics.
+    pub fn from_bytes(data: Vec<u8>, topics: BagTopics) -> Self {
+        Self {
+            data: BagData::Owned(data),
+            topics,
+        }
+    }
+
+    /// Returns the embedded sensor config if present. It is looked up
+    /// through the metadata index of the summary, and the file is only
+    /// scanned if the summary is missing, e.g. when recording was
+    /// interrupted.
+    pub fn config(&self) -> Result<Option<Config>> {
+        let metadata = match Summary::read(&self.data)? {
+            Some(summary) => summary
+                .metadata_indexes
+                .iter()
+                .filter(|index| index.name == BAG_METADATA_NAME)
+                .map(|index| mcap::read::metadata(&self.data, index))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => LinearReader::new(&self.data)?
+                .filter_map(|record| match record {
+                    Ok(Record::Metadata(metadata)) if metadata.name == BAG_METADATA_NAME => {
+                        Some(Ok(metadata))
+                    }
+                    Ok(_) => None,
+                    Err(error) => Some(Err(error)),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        metadata
+            .iter()
+            .find_map(|metadata| metadata.metadata.get("config"))
+            .map(|config| Config::from_json_str(config))
+            .transpose()
+    }
+
+    /// Iterates over messages of known topics in file order along
+    /// with their log times in nanoseconds.
+    pub fn messages(&self) -> Result<impl Iterator<Item = Result<(u64, BagMessage)>> + '_> {
+        let stream = MessageStream::new(&self.data)?;
+        let iter = stream.filter_map(move |message| {
+            let message = match message {
+                Ok(message) => message,
+                Err(error) => return Some(Err(error.into())),
+            };
+            let topic = message.channel.topic.as_str();
+            let data = &message.data;
+            let decoded = if topic == self.topics.lidar_packets {
+                from_cdr_bytes::<PacketMsg>(data)
+                    .and_then(|msg| msg.to_packet())
+                    .map(|packet| BagMessage::LidarPacket(Box::new(packet)))
+            } else if topic == self.topics.imu_packets {
+                from_cdr_bytes::<PacketMsg>(data)
+                    .and_then(|msg| msg.to_imu_packet())
+                    .map(BagMessage::ImuPacket)
+            } else if topic == self.topics.points {
+                from_cdr_bytes(data).map(BagMessage::Points)
+            } else if topic == self.topics.imu {
+                from_cdr_bytes(data).map(|imu| BagMessage::Imu(Box::new(imu)))
+            } else {
+                return None;
+            };
+            Some(decoded.map(|decoded| (message.log_time, decoded)))
+        });
+        Ok(iter)
+    }
+
+    /// Iterates over recorded lidar packets.
+    pub fn packets(&self) -> Result<impl Iterator<Item = Result<Packet>> + '_> {
+        let iter = self.messages()?.filter_map(|message| match message {
+            Ok((_, BagMessage::LidarPacket(packet))) => Some(Ok(*packet)),
+            Ok(_) => None,
+            Err(error) => Some(Err(error)),
+        });
+        Ok(iter)
+    }
+
+    /// Feeds recorded lidar packets to the converter and returns all
+    /// frames, including the last incomplete one.
+    pub fn convert(&self, mut converter: FrameConverter) -> Result<Vec<Frame>> {
+        let mut frames = vec![];
+        let mut packets = self.packets()?;
+        loop {
+            let batch = packets
+                .by_ref()
+                .take(PACKETS_PER_BATCH)
+                .collect::<Result<Vec<_>>>()?;
+            if batch.is_empty() {
+                break;
+            }
+            frames.extend(converter.push_packets(&batch)?);
+        }
+        frames.extend(converter.finish());
+        Ok(frames)
+    }
+
+    /// Converts recorded lidar packets to frames using the embedded
+    /// sensor config.
+    pub fn frames(&self) -> Result<Vec<Frame>> {
+        let config = self
+            .config()?
+            .ok_or_else(|| format_err!("the recording has no sensor config"))?;
+        self.convert(FrameConverter::from_config(config)?)
+    }
+}
@@ -0,0 +1,427 @@
+//! Reading and writing UDP datagrams in pcap capture files.
+//!
+//! The reader understands Ethernet, Linux cooked and raw IPv4 link
+//! types in micro- and nanosecond resolution files, and reassembles
+//! fragmented IPv4 datagrams, which is common for lidar packets
+//! larger than the MTU. It does not depend on libpcap.
+
+use super::packet::{ImuPacket, Packet};
+use crate::common::*;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufWriter, ErrorKind},
+    net::SocketAddrV4,
+};
+
+const MAGIC_MICROS: u32 = 0xa1b2c3d4;
+const MAGIC_NANOS: u32 = 0xa1b23c4d;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IP_PROTOCOL_UDP: u8 = 17;
+const MAX_UDP_PAYLOAD_SIZE: usize = 65507;
+/// Largest IPv4 datagram, the limit of reassembled fragments.
+const MAX_IP_DATAGRAM_SIZE: usize = 65535;
+/// Largest record read regardless of the snapshot length in the header.
+const MAX_RECORD_SIZE: usize = 256 * 1024;
+/// Capture time after which incomplete fragmented datagrams are dropped.
+const FRAGMENT_TIMEOUT_NS: u64 = 1_000_000_000;
+/// Maximum number of datagrams reassembled at once.
+const MAX_PENDING_DATAGRAMS: usize = 256;
+
+/// A UDP datagram read from or written to a capture.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UdpDatagram {
+    /// Capture time as Unix timestamp in nanoseconds.
+    pub timestamp: u64,
+    pub source: SocketAddrV4,
+    pub destination: SocketAddrV4,
+    pub payload: Vec<u8>,
+}
+
+/// Ouster packets decoded from datagram payloads.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CapturedPacket {
+    Lidar(Box<Packet>),
+    Imu(ImuPacket),
+}
+
+impl UdpDatagram {
+    /// Decodes the payload as a lidar or IMU packet by its size.
+    pub fn to_packet(&self) -> Option<CapturedPacket> {
+        if let Ok(packet) = Packet::from_slice(&self.payload) {
+            Some(CapturedPacket::Lidar(Box::new(*packet)))
+        } else if let Ok(packet) = ImuPacket::from_slice(&self.payload) {
+            Some(CapturedPacket::Imu(*packet))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Fragments {
+    /// Capture time of the first fragment.
+    timestamp: u64,
+    /// Fragment bodies by offset. Duplicates of an offset are ignored.
+    parts: BTreeMap<usize, Vec<u8>>,
+    total_len: Option<usize>,
+}
+
+impl Fragments {
+    fn new(timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            parts: BTreeMap::new(),
+            total_len: None,
+        }
+    }
+
+    /// Adds a fragment. It returns false if the fragment contradicts the
+    /// ones received before.
+    fn insert(&mut self, offset: usize, body: &[u8], last: bool) -> bool {
+        let end = offset + body.len();
+        if end > MAX_IP_DATAGRAM_SIZE {
+            return false;
+        }
+        if last {
+            if self.total_len.is_some_and(|total_len| total_len != end) {
+                return false;
+            }
+            self.total_len = Some(end);
+        }
+        if let Some(total_len) = self.total_len {
+            if end > total_len
+                || self
+                    .parts
+                    .iter()
+                    .any(|(offset, part)| offset + part.len() > total_len)
+            {
+                return false;
+            }
+        }
+        self.parts.entry(offset).or_insert_with(|| body.to_vec());
+        true
+    }
+
+    /// Returns the datagram if fragments cover all of it.
+    fn assemble(&self) -> Option<Vec<u8>> {
+        let total_len = self.total_len?;
+        let mut covered = 0;
+        for (offset, part) in &self.parts {
+            if *offset > covered {
+                return None;
+            }
+            covered = covered.max(offset + part.len());
+        }
+        if covered < total_len {
+            return None;
+        }
+
+        let mut udp = vec![0u8; total_len];
+        for (offset, part) in &self.parts {
+            udp[*offset..(offset + part.len())].copy_from_slice(part);
+        }
+        Some(udp)
+    }
+}
+
+/// Reads UDP datagrams from a pcap file.
+#[derive(Debug)]
+pub struct PcapReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    nanos: bool,
+    link_type: u32,
+    max_record_len: usize,
+    fragments: HashMap<([u8; 4], [u8; 4], u16), Fragments>,
+}
+
+impl PcapReader<BufReader<File>> {
+    /// Opens the pcap file at path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path.as_ref())?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Reads the global header.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut header = [0u8; 24];
+        reader.read_exact(&mut header)?;
+        let magic_le = u32::from_le_bytes(header[0..4].try_into().unwrap());
+        let magic_be = u32::from_be_bytes(header[0..4].try_into().unwrap());
+        let (big_endian, nanos) = match (magic_le, magic_be) {
+            (MAGIC_MICROS, _) => (false, false),
+            (MAGIC_NANOS, _) => (false, true),
+            (_, MAGIC_MICROS) => (true, false),
+            (_, MAGIC_NANOS) => (true, true),
+            _ => bail!("not a pcap file, magic number {:#010x}", magic_le),
+        };
+
+        let mut this = Self {
+            reader,
+            big_endian,
+            nanos,
+            link_type: 0,
+            max_record_len: MAX_RECORD_SIZE,
+            fragments: HashMap::new(),
+        };
+        let snaplen = this.read_u32(&header[16..20]) as usize;
+        if snaplen > 0 {
+            this.max_record_len = snaplen.min(MAX_RECORD_SIZE);
+        }
+        let link_type = this.read_u32(&header[20..24]);
+        ensure!(
+            matches!(
+                link_type,
+                LINKTYPE_ETHERNET | LINKTYPE_RAW | LINKTYPE_LINUX_SLL
+            ),
+            "unsupported pcap link type {}",
+            link_type
+        );
+        this.link_type = link_type;
+        Ok(this)
+    }
+
+    /// Returns the next UDP datagram, or `None` at the end of file.
+    /// Records of other protocols are skipped.
+    pub fn next_datagram(&mut self) -> Result<Option<UdpDatagram>> {
+        loop {
+            let mut header = [0u8; 16];
+            match self.reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(error) => return Err(error.into()),
+            }
+            let secs = self.read_u32(&header[0..4]) as u64;
+            let fraction = self.read_u32(&header[4..8]) as u64;
+            let captured_len = self.read_u32(&header[8..12]) as usize;
+            let original_len = self.read_u32(&header[12..16]) as usize;
+            let timestamp = secs * 1_000_000_000
+                + if self.nanos {
+                    fraction
+                } else {
+                    fraction * 1000
+                };
+
+            ensure!(
+                captured_len <= self.max_record_len,
+                "pcap record of {} bytes exceeds the limit of {} bytes",
+                captured_len,
+                self.max_record_len
+            );
+            let mut data = vec![0u8; captured_len];
+            self.reader.read_exact(&mut data)?;
+            if captured_len < original_len {
+                // Truncated by the snapshot length
+                continue;
+            }
+            if let Some(datagram) = self.parse_frame(timestamp, &data) {
+                return Ok(Some(datagram));
+            }
+        }
+    }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = bytes.try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    fn parse_frame(&mut self, timestamp: u64, data: &[u8]) -> Option<UdpDatagram> {
+        let ip = match self.link_type {
+            LINKTYPE_ETHERNET => {
+                let mut offset = 12;
+                let mut ethertype =
+                    u16::from_be_bytes(data.get(offset..(offset + 2))?.try_into().ok()?);
+                while ethertype == ETHERTYPE_VLAN {
+                    offset += 4;
+                    ethertype =
+                        u16::from_be_bytes(data.get(offset..(offset + 2))?.try_into().ok()?);
+                }
+                (ethertype == ETHERTYPE_IPV4).then_some(data.get((offset + 2)..)?)?
+            }
+            LINKTYPE_LINUX_SLL => {
+                let protocol = u16::from_be_bytes(data.get(14..16)?.try_into().ok()?);
+                (protocol == ETHERTYPE_IPV4).then_some(data.get(16..)?)?
+            }
+            _ => data,
+        };
+        self.parse_ipv4(timestamp, ip)
+    }
+
+    fn parse_ipv4(&mut self, timestamp: u64, ip: &[u8]) -> Option<UdpDatagram> {
+        if ip.len() < 20 || ip[0] >> 4 != 4 || ip[9] != IP_PROTOCOL_UDP {
+            return None;
+        }
+        let header_len = (ip[0] & 0x0f) as usize * 4;
+        let total_len = (u16::from_be_bytes([ip[2], ip[3]]) as usize).min(ip.len());
+        let id = u16::from_be_bytes([ip[4], ip[5]]);
+        let flags_offset = u16::from_be_bytes([ip[6], ip[7]]);
+        let more_fragments = flags_offset & 0x2000 != 0;
+        let fragment_offset = (flags_offset & 0x1fff) as usize * 8;
+        let source: [u8; 4] = ip[12..16].try_into().ok()?;
+        let destination: [u8; 4] = ip[16..20].try_into().ok()?;
+        let body = ip.get(header_len..total_len)?;
+
+        let udp = if more_fragments || fragment_offset > 0 {
+            self.evict_fragments(timestamp);
+            let key = (source, destination, id);
+            let fragments = self
+                .fragments
+                .entry(key)
+                .or_insert_with(|| Fragments::new(timestamp));
+            if !fragments.insert(fragment_offset, body, !more_fragments) {
+                self.fragments.remove(&key);
+                return None;
+            }
+            let udp = fragments.assemble()?;
+            self.fragments.remove(&key);
+            udp
+        } else {
+            body.to_vec()
+        };
+
+        if udp.len() < 8 {
+            return None;
+        }
+        let source_port = u16::from_be_bytes([udp[0], udp[1]]);
+        let destination_port = u16::from_be_bytes([udp[2], udp[3]]);
+        let udp_len = (u16::from_be_bytes([udp[4], udp[5]]) as usize).clamp(8, udp.len());
+
+        Some(UdpDatagram {
+            timestamp,
+            source: SocketAddrV4::new(source.into(), source_port),
+            destination: SocketAddrV4::new(destination.into(), destination_port),
+            payload: udp[8..udp_len].to_vec(),
+        })
+    }
+
+    /// Drops incomplete datagrams that timed out, and the oldest ones
+    /// if too many are pending.
+    fn evict_fragments(&mut self, timestamp: u64) {
+        self.fragments.retain(|_, fragments| {
+            timestamp.saturating_sub(fragments.timestamp) < FRAGMENT_TIMEOUT_NS
+        });
+        while self.fragments.len() >= MAX_PENDING_DATAGRAMS {
+            let oldest = self
+                .fragments
+                .iter()
+                .min_by_key(|(_, fragments)| fragments.timestamp)
+                .map(|(key, _)| *key);
+            match oldest {
+                Some(key) => self.fragments.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = Result<UdpDatagram>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_datagram().transpose()
+    }
+}
+
+/// Writes UDP datagrams to a nanosecond resolution pcap file over
+/// Ethernet. Datagrams are written unfragmented.
+#[derive(Debug)]
+pub struct PcapWriter<W: Write> {
+    writer: W,
+    ip_id: u16,
+}
+
+impl PcapWriter<BufWriter<File>> {
+    /// Creates the pcap file at path.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::create(path.as_ref())?;
+        Self::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Writes the global header.
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(&MAGIC_NANOS.to_le_bytes())?;
+        writer.write_all(&2u16.to_le_bytes())?;
+        writer.write_all(&4u16.to_le_bytes())?;
+        writer.write_all(&0i32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&65535u32.to_le_bytes())?;
+        writer.write_all(&LINKTYPE_ETHERNET.to_le_bytes())?;
+        Ok(Self { writer, ip_id: 0 })
+    }
+
+    /// Writes a datagram.
+    pub fn write_datagram(&mut self, datagram: &UdpDatagram) -> Result<()> {
+        let payload = &datagram.payload;
+        ensure!(
+            payload.len() <= MAX_UDP_PAYLOAD_SIZE,
+            "UDP payload of {} bytes is too large",
+            payload.len()
+        );
+        let udp_len = 8 + payload.len();
+        let ip_len = 20 + udp_len;
+        let frame_len = 14 + ip_len;
+
+        let mut frame = Vec::with_capacity(frame_len);
+        // Ethernet
+        frame.extend_from_slice(&[0xff; 6]);
+        frame.extend_from_slice(&[0x00; 6]);
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        // IPv4
+        let mut ip_header = [0u8; 20];
+        ip_header[0] = 0x45;
+        ip_header[2..4].copy_from_slice(&(ip_len as u16).to_be_bytes());
+        ip_header[4..6].copy_from_slice(&self.ip_id.to_be_bytes());
+        ip_header[6] = 0x40;
+        ip_header[8] = 64;
+        ip_header[9] = IP_PROTOCOL_UDP;
+        ip_header[12..16].copy_from_slice(&datagram.source.ip().octets());
+        ip_header[16..20].copy_from_slice(&datagram.destination.ip().octets());
+        let checksum = ip_checksum(&ip_header);
+        ip_header[10..12].copy_from_slice(&checksum.to_be_bytes());
+        frame.extend_from_slice(&ip_header);
+        // UDP without checksum
+        frame.extend_from_slice(&datagram.source.port().to_be_bytes());
+        frame.extend_from_slice(&datagram.destination.port().to_be_bytes());
+        frame.extend_from_slice(&(udp_len as u16).to_be_bytes());
+        frame.extend_from_slice(&[0; 2]);
+        frame.extend_from_slice(payload);
+        self.ip_id = self.ip_id.wrapping_add(1);
+
+        let secs = datagram.timestamp / 1_000_000_000;
+        let nanos = datagram.timestamp % 1_000_000_000;
+        self.writer.write_all(&(secs as u32).to_le_bytes())?;
+        self.writer.write_all(&(nanos as u32).to_le_bytes())?;
+        self.writer.write_all(&(frame_len as u32).to_le_bytes())?;
+        self.writer.write_all(&(frame_len as u32).to_le_bytes())?;
+        self.writer.write_all(&frame)?;
+        Ok(())
+    }
+
+    /// Flushes and returns the inner writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn ip_checksum(header: &[u8]) -> u16 {
+    let sum: u32 = header
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]) as u32)
+        .sum();
+    let sum = (sum & 0xffff) + (sum >> 16);
+    let sum = (sum & 0xffff) + (sum >> 16);
+    !(sum as u16)
+}
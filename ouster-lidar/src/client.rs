@@ -1,6 +1,7 @@
 //! Tools to work with TCP API on Ouster sensors.
 
 use super::{
+    config::AzimuthWindow,
     consts::PIXELS_PER_COLUMN,
     enums::{LidarMode, MultipurposeIoMode, NmeaBaudRate, OnOffMode, Polarity, TimestampMode},
 };
@@ -40,6 +41,8 @@ pub struct BeamIntrinsics {
     pub beam_altitude_angles: [R64; PIXELS_PER_COLUMN],
     #[serde(with = "BigArray")]
     pub beam_azimuth_angles: [R64; PIXELS_PER_COLUMN],
+    #[serde(default)]
+    pub lidar_origin_to_beam_origin_mm: R64,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -57,6 +60,9 @@ pub struct TimeInfo {
     pub timestamp: TimestampInfo,
     pub sync_pulse_in: SyncPulseInInfo,
     pub multipurpose_io: MultiPurposeIo,
+    /// PTP state, reported by firmware 2.0 and later.
+    #[serde(default)]
+    pub ptp: Option<PtpInfo>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -86,6 +92,41 @@ pub struct NmeaInfo {
     pub locked: bool,
 }
 
+/// The `ptp` section of `get_time_info`, as far as it is used.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PtpInfo {
+    pub profile: String,
+    pub port_data_set: PtpPortDataSet,
+    pub current_data_set: PtpCurrentDataSet,
+    pub parent_data_set: PtpParentDataSet,
+}
+
+impl PtpInfo {
+    /// Returns if the sensor follows a PTP master.
+    pub fn locked(&self) -> bool {
+        self.port_data_set.port_state == "SLAVE"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PtpPortDataSet {
+    pub port_state: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PtpCurrentDataSet {
+    /// Offset from the master clock in nanoseconds.
+    pub offset_from_master: R64,
+    /// Mean path delay to the master in nanoseconds.
+    pub mean_path_delay: R64,
+    pub steps_removed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PtpParentDataSet {
+    pub grandmaster_identity: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SyncPulseOutInfo {
     pub frequency_hz: u64,
@@ -138,6 +179,30 @@ pub struct NmeaDecodingInfo {
     pub date_decoded_count: u64,
 }
 
+/// Alerts reported by `get_alerts`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Alerts {
+    /// Alerts that are currently active.
+    pub active: Vec<Alert>,
+    /// Activation and deactivation events in order.
+    pub log: Vec<Alert>,
+    /// Cursor of the next event to be logged.
+    pub next_cursor: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: String,
+    pub category: String,
+    pub level: String,
+    pub msg: String,
+    #[serde(default)]
+    pub msg_verbose: String,
+    pub active: bool,
+    pub cursor: u64,
+    pub realtime: String,
+}
+
 #[derive(Debug)]
 pub struct CommandClient {
     reader: Lines<BufReader<TcpStream>>,
@@ -209,7 +274,17 @@ impl CommandClient {
         Ok(config)
     }
 
-    pub fn reinitialize(mut self) -> Result<()> {
+    pub fn get_alerts(&mut self) -> Result<Alerts> {
+        self.writer.write_all(b"get_alerts\n")?;
+        let line = self
+            .reader
+            .next()
+            .ok_or_else(|| format_err!("Unexpected end of stream"))??;
+        let alerts = serde_json::from_str(&line)?;
+        Ok(alerts)
+    }
+
+    pub fn reinitialize(&mut self) -> Result<()> {
         self.writer.write_all(b"reinitialize\n")?;
         let line = self
             .reader
@@ -249,6 +324,11 @@ impl CommandClient {
         Ok(())
     }
 
+    pub fn set_azimuth_window(&mut self, window: AzimuthWindow) -> Result<()> {
+        self.set_config_param("azimuth_window", window)?;
+        Ok(())
+    }
+
     pub fn set_timestamp_mode(&mut self, mode: TimestampMode) -> Result<()> {
         self.set_config_param("timestamp_mode", mode)?;
         Ok(())
@@ -264,7 +344,9 @@ impl CommandClient {
         Ok(())
     }
 
-    fn set_config_param<T: Display>(&mut self, param: &str, arg: T) -> Result<()> {
+    /// Sets a configuration parameter. It takes effect after
+    /// [reinitialize](CommandClient::reinitialize).
+    pub fn set_config_param<T: Display>(&mut self, param: &str, arg: T) -> Result<()> {
         let command = format!("set_config_param {} {}\n", param, arg);
         self.writer.write_all(command.as_bytes())?;
         let line = self
@@ -0,0 +1,250 @@
+//! Clustering of point clouds.
+//!
+//! [EuclideanClustering] works on unorganized point sets using
+//! a [KdTree], while [RangeImageClustering] grows clusters on
+//! neighboring beams and columns of the organized scan.
+
+use super::{
+    consts::PIXELS_PER_COLUMN, organized::OrganizedIndex, pcd_converter::Point, spatial::KdTree,
+};
+use crate::{common::*, utils::squared_distance};
+use std::collections::VecDeque;
+
+/// An axis-aligned bounding box in meters.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AxisAlignedBox {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+/// A bounding box rotated around the Z axis.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OrientedBox {
+    /// Box center in meters.
+    pub center: [f64; 3],
+    /// Half of the box size along its local axes in meters.
+    pub half_extents: [f64; 3],
+    /// Rotation around the Z axis in radians.
+    pub yaw: f64,
+}
+
+/// A group of points found by clustering.
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    /// Indices of member points.
+    pub indices: Vec<usize>,
+    pub aabb: AxisAlignedBox,
+    /// Box aligned with the principal axis of members in XY plane.
+    pub obb: OrientedBox,
+}
+
+/// The result of clustering.
+#[derive(Debug, Clone)]
+pub struct Clustering {
+    /// Per-point cluster IDs in the same order of input points.
+    /// `None` marks unclustered points.
+    pub labels: Vec<Option<usize>>,
+    /// Clusters indexed by cluster ID.
+    pub clusters: Vec<Cluster>,
+}
+
+impl Clustering {
+    fn from_members(points: &[Point], members: Vec<Vec<usize>>) -> Self {
+        let mut labels = vec![None; points.len()];
+        let clusters = members
+            .into_iter()
+            .enumerate()
+            .map(|(cluster_id, indices)| {
+                indices
+                    .iter()
+                    .for_each(|&index| labels[index] = Some(cluster_id));
+                let positions: Vec<_> = indices
+                    .iter()
+                    .map(|&index| points[index].point.map(|v| v.as_meters()))
+                    .collect();
+                Cluster {
+                    aabb: axis_aligned_box(&positions),
+                    obb: oriented_box(&positions),
+                    indices,
+                }
+            })
+            .collect();
+
+        Self { labels, clusters }
+    }
+}
+
+/// Classic Euclidean cluster extraction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EuclideanClustering {
+    /// Maximum distance in meters between neighboring members.
+    pub tolerance: f64,
+    pub min_cluster_size: usize,
+    pub max_cluster_size: usize,
+}
+
+impl EuclideanClustering {
+    /// Clusters the points. Points with zero distance are ignored.
+    pub fn cluster(&self, points: &[Point]) -> Clustering {
+        let tree = KdTree::new(points);
+        let mut visited: Vec<bool> = points
+            .iter()
+            .map(|point| point.distance.as_meters() <= 0.0)
+            .collect();
+        let mut members = vec![];
+        let mut neighbors = vec![];
+
+        for seed in 0..points.len() {
+            if visited[seed] {
+                continue;
+            }
+            visited[seed] = true;
+
+            let mut cluster = vec![seed];
+            let mut cursor = 0;
+            while cursor < cluster.len() {
+                neighbors.clear();
+                tree.radius_search_into(
+                    tree.position(cluster[cursor]),
+                    self.tolerance,
+                    &mut neighbors,
+                );
+                for &neighbor in neighbors.iter() {
+                    if !visited[neighbor] {
+                        visited[neighbor] = true;
+                        cluster.push(neighbor);
+                    }
+                }
+                cursor += 1;
+            }
+
+            if (self.min_cluster_size..=self.max_cluster_size).contains(&cluster.len()) {
+                cluster.sort_unstable();
+                members.push(cluster);
+            }
+        }
+
+        Clustering::from_members(points, members)
+    }
+}
+
+/// Breadth-first clustering on the range image.
+///
+/// Two pixels are connected if they are adjacent beams in the same
+/// column, or adjacent columns on the same beam, and their points
+/// are within the distance tolerance. Columns wrap around.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RangeImageClustering {
+    /// Maximum distance in meters between connected points.
+    pub tolerance: f64,
+    pub min_cluster_size: usize,
+    pub max_cluster_size: usize,
+}
+
+impl RangeImageClustering {
+    /// Clusters points of a frame with `columns_per_revolution` columns.
+    /// Points with zero distance are ignored.
+    pub fn cluster(&self, points: &[Point], columns_per_revolution: u16) -> Clustering {
+        let width = columns_per_revolution as usize;
+        let index = OrganizedIndex::new(points, width, PIXELS_PER_COLUMN);
+        let positions: Vec<[f64; 3]> = points
+            .iter()
+            .map(|point| point.point.map(|v| v.as_meters()))
+            .collect();
+        let mut visited: Vec<bool> = points
+            .iter()
+            .map(|point| point.distance.as_meters() <= 0.0)
+            .collect();
+        let tolerance_sq = self.tolerance * self.tolerance;
+        let mut members = vec![];
+        let mut queue = VecDeque::new();
+
+        for row in 0..index.height() {
+            for col in 0..width {
+                let seed = match index.get(row, col) {
+                    Some(seed) if !visited[seed] => seed,
+                    _ => continue,
+                };
+                visited[seed] = true;
+                queue.push_back((row, col, seed));
+                let mut cluster = vec![];
+
+                while let Some((row, col, current)) = queue.pop_front() {
+                    cluster.push(current);
+                    let neighbors = [
+                        (row.wrapping_sub(1), col),
+                        (row + 1, col),
+                        (row, (col + width - 1) % width),
+                        (row, (col + 1) % width),
+                    ];
+                    for (n_row, n_col) in neighbors {
+                        let neighbor = match index.get(n_row, n_col) {
+                            Some(neighbor) if !visited[neighbor] => neighbor,
+                            _ => continue,
+                        };
+                        if squared_distance(positions[current], positions[neighbor]) <= tolerance_sq
+                        {
+                            visited[neighbor] = true;
+                            queue.push_back((n_row, n_col, neighbor));
+                        }
+                    }
+                }
+
+                if (self.min_cluster_size..=self.max_cluster_size).contains(&cluster.len()) {
+                    cluster.sort_unstable();
+                    members.push(cluster);
+                }
+            }
+        }
+
+        Clustering::from_members(points, members)
+    }
+}
+
+fn axis_aligned_box(positions: &[[f64; 3]]) -> AxisAlignedBox {
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for position in positions {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(position[axis]);
+            max[axis] = max[axis].max(position[axis]);
+        }
+    }
+    AxisAlignedBox { min, max }
+}
+
+fn oriented_box(positions: &[[f64; 3]]) -> OrientedBox {
+    let count = positions.len() as f64;
+    let mean_x = positions.iter().map(|p| p[0]).sum::<f64>() / count;
+    let mean_y = positions.iter().map(|p| p[1]).sum::<f64>() / count;
+    let (mut cxx, mut cxy, mut cyy) = (0.0, 0.0, 0.0);
+    for position in positions {
+        let (dx, dy) = (position[0] - mean_x, position[1] - mean_y);
+        cxx += dx * dx;
+        cxy += dx * dy;
+        cyy += dy * dy;
+    }
+
+    // Principal axis of the 2x2 covariance
+    let yaw = 0.5 * (2.0 * cxy).atan2(cxx - cyy);
+    let (sin, cos) = yaw.sin_cos();
+    let local: Vec<[f64; 3]> = positions
+        .iter()
+        .map(|p| {
+            let (dx, dy) = (p[0] - mean_x, p[1] - mean_y);
+            [cos * dx + sin * dy, -sin * dx + cos * dy, p[2]]
+        })
+        .collect();
+    let AxisAlignedBox { min, max } = axis_aligned_box(&local);
+    let local_center = [0, 1, 2].map(|axis| (min[axis] + max[axis]) / 2.0);
+
+    OrientedBox {
+        center: [
+            mean_x + cos * local_center[0] - sin * local_center[1],
+            mean_y + sin * local_center[0] + cos * local_center[1],
+            local_center[2],
+        ],
+        half_extents: [0, 1, 2].map(|axis| (max[axis] - min[axis]) / 2.0),
+        yaw,
+    }
+}
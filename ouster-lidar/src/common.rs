@@ -1,20 +1,17 @@
 pub use anyhow::{bail, ensure, format_err, Error, Result};
 pub use chrono::NaiveDateTime;
 pub use derivative::Derivative;
-pub use itertools::{chain, izip, Itertools as _};
+pub use itertools::{izip, Itertools as _};
 pub use measurements::{Angle, Length};
 pub use noisy_float::types::R64;
-pub use num_traits::{Float, Num};
+pub use num_traits::Float;
 pub use serde::{Deserialize, Deserializer, Serialize, Serializer};
 pub use std::{
-    borrow::{Borrow, Cow},
     cmp::Ordering,
     fmt::{self, Debug, Display, Formatter},
     fs::File,
     io::{prelude::*, BufReader, LineWriter, Lines},
-    iter,
-    marker::PhantomData,
-    mem::{self, MaybeUninit},
+    iter, mem,
     net::{Ipv4Addr, TcpStream, ToSocketAddrs},
     ops::Range,
     path::Path,
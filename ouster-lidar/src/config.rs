@@ -19,6 +19,13 @@ pub struct Config {
     #[serde(with = "BigArray", rename = "beam_azimuth_angles")]
     pub beam_azimuth_angle_corrections: [R64; PIXELS_PER_COLUMN],
     pub lidar_mode: LidarMode,
+    /// Azimuth range where the sensor reports valid columns.
+    #[serde(default)]
+    pub azimuth_window: AzimuthWindow,
+    /// Distance from the lidar origin to the beam origins in
+    /// millimeters.
+    #[serde(default)]
+    pub lidar_origin_to_beam_origin_mm: R64,
 }
 
 impl Config {
@@ -29,11 +36,11 @@ impl Config {
         lidar_mode: LidarMode,
     ) -> Config {
         Config {
-            beam_altitude_angles: unsafe { mem::transmute(beam_altitude_angles) },
-            beam_azimuth_angle_corrections: unsafe {
-                mem::transmute(beam_azimuth_angle_corrections)
-            },
+            beam_altitude_angles,
+            beam_azimuth_angle_corrections,
             lidar_mode,
+            azimuth_window: AzimuthWindow::default(),
+            lidar_origin_to_beam_origin_mm: R64::new(0.0),
         }
     }
 
@@ -61,13 +68,12 @@ impl Config {
         &mut self,
         beam_azimuth_angle_corrections: [f64; PIXELS_PER_COLUMN],
     ) {
-        self.beam_azimuth_angle_corrections =
-            unsafe { mem::transmute(beam_azimuth_angle_corrections) };
+        self.beam_azimuth_angle_corrections = beam_azimuth_angle_corrections.map(R64::new);
     }
 
     /// Sets `beam_altitude_angles` field.
     pub fn beam_altitude_angles(&mut self, beam_altitude_angles: [f64; PIXELS_PER_COLUMN]) {
-        self.beam_altitude_angles = unsafe { mem::transmute(beam_altitude_angles) };
+        self.beam_altitude_angles = beam_altitude_angles.map(R64::new);
     }
 
     /// Sets `lidar_mode` field.
@@ -75,6 +81,16 @@ impl Config {
         self.lidar_mode = lidar_mode;
     }
 
+    /// Sets `azimuth_window` field.
+    pub fn azimuth_window(&mut self, azimuth_window: AzimuthWindow) {
+        self.azimuth_window = azimuth_window;
+    }
+
+    /// Sets `lidar_origin_to_beam_origin_mm` field.
+    pub fn lidar_origin_to_beam_origin_mm(&mut self, millimeters: f64) {
+        self.lidar_origin_to_beam_origin_mm = R64::new(millimeters);
+    }
+
     /// Create default configuration for Ouster OS-1.
     pub fn os_1_config() -> Self {
         // From firmware 1.12.0
@@ -82,11 +98,114 @@ impl Config {
         let beam_azimuth_angle_corrections = OS_1_BEAM_AZIMUTH_DEGREE_CORRECTIONS;
 
         Self {
-            beam_altitude_angles: unsafe { mem::transmute(beam_altitude_angles) },
-            beam_azimuth_angle_corrections: unsafe {
-                mem::transmute(beam_azimuth_angle_corrections)
-            },
+            beam_altitude_angles: beam_altitude_angles.map(R64::new),
+            beam_azimuth_angle_corrections: beam_azimuth_angle_corrections.map(R64::new),
             lidar_mode: LidarMode::Mode1024x10,
+            azimuth_window: AzimuthWindow::default(),
+            lidar_origin_to_beam_origin_mm: R64::new(0.0),
+        }
+    }
+}
+
+/// Full circle in millidegrees.
+const MILLIDEGREES_PER_REV: u64 = 360_000;
+
+/// The azimuth range in millidegrees where the sensor fires, as in the
+/// `azimuth_window` sensor parameter. The window wraps around zero if
+/// `start` is greater than `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "[u32; 2]", into = "[u32; 2]")]
+pub struct AzimuthWindow {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl AzimuthWindow {
+    /// Creates a window from start and end angles in millidegrees.
+    pub fn new(start: u32, end: u32) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the window covering the full circle.
+    pub fn full() -> Self {
+        Self::new(0, MILLIDEGREES_PER_REV as u32)
+    }
+
+    /// Returns if the window wraps around zero.
+    pub fn wraps(&self) -> bool {
+        self.start > self.end
+    }
+
+    /// Returns the first and last in-window measurement IDs of a
+    /// revolution, in angle order from `start` to `end`.
+    pub fn measurement_id_bounds(&self, columns_per_revolution: u16) -> (u16, u16) {
+        let columns = columns_per_revolution as u64;
+        let to_column = |angle: u32| (angle as u64).min(MILLIDEGREES_PER_REV) * columns;
+        let first = to_column(self.start).div_ceil(MILLIDEGREES_PER_REV);
+        let last = to_column(self.end) / MILLIDEGREES_PER_REV;
+        let last_column = columns.saturating_sub(1);
+        (first.min(last_column) as u16, last.min(last_column) as u16)
+    }
+
+    /// Returns if a column is in the window.
+    pub fn contains(&self, measurement_id: u16, columns_per_revolution: u16) -> bool {
+        let (first, last) = self.measurement_id_bounds(columns_per_revolution);
+        if self.wraps() {
+            measurement_id >= first || measurement_id <= last
+        } else {
+            (first..=last).contains(&measurement_id)
+        }
+    }
+
+    /// Returns the largest in-window measurement ID, which is the last
+    /// column of a frame to arrive.
+    pub fn last_measurement_id(&self, columns_per_revolution: u16) -> u16 {
+        let (_, last) = self.measurement_id_bounds(columns_per_revolution);
+        if self.wraps() {
+            columns_per_revolution.saturating_sub(1)
+        } else {
+            last
+        }
+    }
+
+    /// Returns the number of in-window columns of a revolution.
+    pub fn num_columns(&self, columns_per_revolution: u16) -> u16 {
+        let (first, last) = self.measurement_id_bounds(columns_per_revolution);
+        if self.wraps() {
+            (columns_per_revolution - first) + last + 1
+        } else {
+            (last + 1).saturating_sub(first)
         }
     }
 }
+
+impl Default for AzimuthWindow {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+impl Display for AzimuthWindow {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "[{},{}]", self.start, self.end)
+    }
+}
+
+impl From<[u32; 2]> for AzimuthWindow {
+    fn from([start, end]: [u32; 2]) -> Self {
+        Self::new(start, end)
+    }
+}
+
+impl From<AzimuthWindow> for [u32; 2] {
+    fn from(window: AzimuthWindow) -> Self {
+        [window.start, window.end]
+    }
+}
+
+impl From<[u64; 2]> for AzimuthWindow {
+    fn from([start, end]: [u64; 2]) -> Self {
+        let clamp = |angle: u64| angle.min(MILLIDEGREES_PER_REV) as u32;
+        Self::new(clamp(start), clamp(end))
+    }
+}
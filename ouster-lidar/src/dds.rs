@@ -0,0 +1,808 @@
+//! DDS bridge that publishes and subscribes sensor data with rustdds.
+//!
+//! Each kind of data goes to its own topic with its own type,
+//! so that subscribers read exactly what publishers write.
+//!
+//! # Keys
+//!
+//! All topics are keyed, and every key starts with the [SensorId]
+//! set in [DdsConfig], so several sensors can share a topic.
+//!
+//! - [PacketMessage] and [PacketMetaData] samples are keyed by
+//!   [ColumnKey], i.e., sensor ID, frame ID and measurement ID.
+//!   For packets the key of the first column is used.
+//! - [FrameMessage] samples are keyed by [FrameKey], i.e., sensor
+//!   ID and frame ID.
+//!
+//! Each key identifies a DDS instance. Readers apply the `KeepLast`
+//! history depth per instance, so a received sample is never replaced
+//! by a different column or frame. rustdds writers apply the depth
+//! across all instances instead, which bounds the writer cache but
+//! means a reliable reader that falls more than `depth` samples behind
+//! can miss some. A `KeepAll` history is bounded by
+//! [DDS_MAX_SAMPLES].
+
+use super::{
+    config::Config,
+    frame_converter::{Frame, FrameCompleteness, FrameConverter},
+    packet::{Column, ImuPacket, Packet, PacketMetaData},
+    pcd_converter::Point,
+    rig::SensorId,
+    ros2::Ros2Publisher,
+};
+use crate::common::*;
+use cdr_encoding_size::CdrEncodingSize;
+use mio::{Evented, Poll, PollOpt, Ready, Token};
+use rustdds::{
+    policy::{Durability, History, Reliability, ResourceLimits},
+    with_key::{DataReader, DataWriter},
+    CDRDeserializerAdapter, CDRSerializerAdapter, DomainParticipant, Key, Keyed, QosPolicies,
+    QosPolicyBuilder, Topic, TopicKind,
+};
+use serde::de::DeserializeOwned;
+
+/// Maximum number of samples kept in a DDS cache of a topic.
+pub const DDS_MAX_SAMPLES: i32 = 1024;
+
+/// Reliability kind of DDS topics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DdsReliability {
+    BestEffort,
+    Reliable,
+}
+
+/// Durability kind of DDS topics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DdsDurability {
+    Volatile,
+    TransientLocal,
+}
+
+/// A serializable subset of DDS QoS policies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DdsQos {
+    pub reliability: DdsReliability,
+    pub durability: DdsDurability,
+    /// Number of samples kept per instance by readers and in total by
+    /// writers. `None` keeps up to [DDS_MAX_SAMPLES] samples.
+    pub history_depth: Option<i32>,
+}
+
+impl Default for DdsQos {
+    fn default() -> Self {
+        Self {
+            reliability: DdsReliability::BestEffort,
+            durability: DdsDurability::Volatile,
+            history_depth: Some(16),
+        }
+    }
+}
+
+impl DdsQos {
+    /// Builds rustdds QoS policies.
+    pub fn to_policies(&self) -> QosPolicies {
+        let reliability = match self.reliability {
+            DdsReliability::BestEffort => Reliability::BestEffort,
+            DdsReliability::Reliable => Reliability::Reliable {
+                max_blocking_time: rustdds::Duration::from_secs(1),
+            },
+        };
+        let durability = match self.durability {
+            DdsDurability::Volatile => Durability::Volatile,
+            DdsDurability::TransientLocal => Durability::TransientLocal,
+        };
+        let history = match self.history_depth {
+            Some(depth) => History::KeepLast { depth },
+            None => History::KeepAll,
+        };
+
+        QosPolicyBuilder::new()
+            .reliability(reliability)
+            .durability(durability)
+            .history(history)
+            .resource_limits(ResourceLimits {
+                max_samples: DDS_MAX_SAMPLES,
+                max_instances: DDS_MAX_SAMPLES,
+                max_samples_per_instance: DDS_MAX_SAMPLES,
+            })
+            .build()
+    }
+}
+
+/// Names of DDS topics used by the bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DdsTopics {
+    /// Topic of raw [Packet]s.
+    pub packets: String,
+    /// Topic of per-column [PacketMetaData].
+    pub metadata: String,
+    /// Topic of complete frames in [FrameMessage]s.
+    pub frames: String,
+}
+
+impl Default for DdsTopics {
+    fn default() -> Self {
+        Self {
+            packets: "OusterLidarPackets".into(),
+            metadata: "OusterLidarMetadata".into(),
+            frames: "OusterLidarFrames".into(),
+        }
+    }
+}
+
+/// Topics a [DdsSubscriber] reads. Samples of other topics are not
+/// received at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DdsSubscriptions {
+    pub packets: bool,
+    pub metadata: bool,
+    pub frames: bool,
+}
+
+impl Default for DdsSubscriptions {
+    fn default() -> Self {
+        Self {
+            packets: true,
+            metadata: true,
+            frames: true,
+        }
+    }
+}
+
+/// Configuration of [DdsPublisher] and [DdsSubscriber].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub struct DdsConfig {
+    pub domain_id: u16,
+    /// The sensor ID put in keys of published samples.
+    pub sensor_id: SensorId,
+    pub qos: DdsQos,
+    pub topics: DdsTopics,
+    /// Topics read by subscribers.
+    #[serde(default)]
+    pub subscriptions: DdsSubscriptions,
+}
+
+/// The key of column-level samples.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    CdrEncodingSize,
+)]
+pub struct ColumnKey {
+    pub sensor_id: SensorId,
+    pub frame_id: u16,
+    pub measurement_id: u16,
+}
+
+impl Key for ColumnKey {}
+
+impl ColumnKey {
+    /// Creates the key of a column produced by a sensor.
+    pub fn new(sensor_id: SensorId, column: &Column) -> Self {
+        Self {
+            sensor_id,
+            frame_id: column.frame_id,
+            measurement_id: column.measurement_id,
+        }
+    }
+}
+
+/// The key of frame samples.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    CdrEncodingSize,
+)]
+pub struct FrameKey {
+    pub sensor_id: SensorId,
+    pub frame_id: u16,
+}
+
+impl Key for FrameKey {}
+
+/// A [Packet] tagged with the sensor that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PacketMessage {
+    pub sensor_id: SensorId,
+    pub packet: Packet,
+}
+
+impl Keyed for PacketMessage {
+    type K = ColumnKey;
+
+    fn key(&self) -> Self::K {
+        ColumnKey::new(self.sensor_id, &self.packet.columns[0])
+    }
+}
+
+impl Keyed for PacketMetaData {
+    type K = ColumnKey;
+
+    fn key(&self) -> Self::K {
+        ColumnKey {
+            sensor_id: self.sensor_id,
+            frame_id: self.frame_id,
+            measurement_id: self.measurement_id,
+        }
+    }
+}
+
+/// A point cloud frame in struct-of-arrays layout for DDS.
+///
+/// Per-point attributes are stored in parallel arrays. Positions and
+/// angles are single-precision, and point timestamps are recovered
+/// from the column timestamps on conversion back to [Frame].
+///
+/// A full 1024x10 frame is about 500 KB and relies on DDS
+/// fragmentation. Receivers may need larger UDP socket buffers
+/// (e.g. `net.core.rmem_max` on Linux) to reassemble it reliably.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct FrameMessage {
+    pub sensor_id: SensorId,
+    pub frame_id: u16,
+    /// The `start..end` range of dropped frame IDs before this frame.
+    pub skipped_frame_ids: [u16; 2],
+    /// Measurement IDs of received columns.
+    pub column_ids: Vec<u16>,
+    /// Unix timestamps in nanoseconds of received columns.
+    pub column_timestamps: Vec<u64>,
+    /// The number of columns per revolution of the lidar mode.
+    pub columns_per_revolution: u16,
+    /// The number of columns of a complete frame in the azimuth window.
+    pub expected_columns: u16,
+    /// The number of columns marked invalid.
+    pub invalid_columns: u16,
+    /// The number of columns filled with zero-range points.
+    pub filled_columns: u16,
+    /// Whether the stream ended before the last column of the frame.
+    pub truncated: bool,
+    /// Point positions in meters.
+    pub x: Vec<f32>,
+    pub y: Vec<f32>,
+    pub z: Vec<f32>,
+    /// Point distances in meters.
+    pub distance: Vec<f32>,
+    /// Azimuth angles in radians.
+    pub azimuth: Vec<f32>,
+    pub reflectivity: Vec<u16>,
+    pub signal_photons: Vec<u16>,
+    pub noise_photons: Vec<u16>,
+    pub laser_ids: Vec<u16>,
+    pub measurement_ids: Vec<u16>,
+}
+
+impl FrameMessage {
+    /// Creates the message from a frame produced by a sensor.
+    pub fn new(sensor_id: SensorId, frame: &Frame) -> Self {
+        let mut message = Self::from(frame);
+        message.sensor_id = sensor_id;
+        message
+    }
+
+    /// Returns the number of points.
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    /// Returns true if the frame has no points.
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
+    /// Converts the message to a [Frame].
+    pub fn to_frame(&self) -> Result<Frame> {
+        let len = self.len();
+        ensure!(
+            [
+                self.y.len(),
+                self.z.len(),
+                self.distance.len(),
+                self.azimuth.len(),
+                self.reflectivity.len(),
+                self.signal_photons.len(),
+                self.noise_photons.len(),
+                self.laser_ids.len(),
+                self.measurement_ids.len(),
+            ]
+            .iter()
+            .all(|&other| other == len),
+            "point attribute arrays of frame {} have different lengths",
+            self.frame_id
+        );
+        ensure!(
+            self.column_ids.len() == self.column_timestamps.len(),
+            "column arrays of frame {} have different lengths",
+            self.frame_id
+        );
+        ensure!(
+            self.column_ids
+                .iter()
+                .all(|&column_id| column_id < self.columns_per_revolution),
+            "column IDs of frame {} exceed {} columns per revolution",
+            self.frame_id,
+            self.columns_per_revolution
+        );
+
+        let timestamps: Vec<(u16, u64)> = self
+            .column_ids
+            .iter()
+            .copied()
+            .zip(self.column_timestamps.iter().copied())
+            .collect();
+        let mut column_timestamps = vec![0; self.columns_per_revolution as usize];
+        for &(column_id, timestamp) in timestamps.iter() {
+            column_timestamps[column_id as usize] = timestamp;
+        }
+        let points = (0..len)
+            .map(|index| {
+                let measurement_id = self.measurement_ids[index];
+                let timestamp = column_timestamps
+                    .get(measurement_id as usize)
+                    .copied()
+                    .unwrap_or(0);
+                Point {
+                    timestamp: Duration::from_nanos(timestamp),
+                    azimuth_angle: Angle::from_radians(self.azimuth[index] as f64),
+                    distance: Length::from_meters(self.distance[index] as f64),
+                    reflectivity: self.reflectivity[index],
+                    signal_photons: self.signal_photons[index],
+                    noise_photons: self.noise_photons[index],
+                    laser_id: self.laser_ids[index] as u32,
+                    measurement_id,
+                    point: [self.x[index], self.y[index], self.z[index]]
+                        .map(|v| Length::from_meters(v as f64)),
+                }
+            })
+            .collect();
+
+        let mut completeness =
+            FrameCompleteness::new(self.columns_per_revolution, self.expected_columns);
+        completeness.invalid_columns = self.invalid_columns;
+        completeness.filled_columns = self.filled_columns;
+        completeness.truncated = self.truncated;
+        for &column_id in &self.column_ids {
+            completeness.columns.insert(column_id);
+        }
+
+        Ok(Frame {
+            frame_id: self.frame_id,
+            skipped_frame_ids: self.skipped_frame_ids[0]..self.skipped_frame_ids[1],
+            timestamps,
+            points,
+            completeness,
+        })
+    }
+}
+
+impl From<&Frame> for FrameMessage {
+    fn from(frame: &Frame) -> Self {
+        let len = frame.points.len();
+        let mut message = FrameMessage {
+            sensor_id: 0,
+            frame_id: frame.frame_id,
+            skipped_frame_ids: [frame.skipped_frame_ids.start, frame.skipped_frame_ids.end],
+            column_ids: frame.timestamps.iter().map(|(mid, _)| *mid).collect(),
+            column_timestamps: frame.timestamps.iter().map(|(_, ts)| *ts).collect(),
+            columns_per_revolution: frame.completeness.columns.len(),
+            expected_columns: frame.completeness.expected_columns,
+            invalid_columns: frame.completeness.invalid_columns,
+            filled_columns: frame.completeness.filled_columns,
+            truncated: frame.completeness.truncated,
+            x: Vec::with_capacity(len),
+            y: Vec::with_capacity(len),
+            z: Vec::with_capacity(len),
+            distance: Vec::with_capacity(len),
+            azimuth: Vec::with_capacity(len),
+            reflectivity: Vec::with_capacity(len),
+            signal_photons: Vec::with_capacity(len),
+            noise_photons: Vec::with_capacity(len),
+            laser_ids: Vec::with_capacity(len),
+            measurement_ids: Vec::with_capacity(len),
+        };
+
+        for point in frame.points.iter() {
+            let [x, y, z] = point.point.map(|v| v.as_meters() as f32);
+            message.x.push(x);
+            message.y.push(y);
+            message.z.push(z);
+            message.distance.push(point.distance.as_meters() as f32);
+            message
+                .azimuth
+                .push(point.azimuth_angle.as_radians() as f32);
+            message.reflectivity.push(point.reflectivity);
+            message.signal_photons.push(point.signal_photons);
+            message.noise_photons.push(point.noise_photons);
+            message.laser_ids.push(point.laser_id as u16);
+            message.measurement_ids.push(point.measurement_id);
+        }
+
+        message
+    }
+}
+
+impl Keyed for FrameMessage {
+    type K = FrameKey;
+
+    fn key(&self) -> Self::K {
+        FrameKey {
+            sensor_id: self.sensor_id,
+            frame_id: self.frame_id,
+        }
+    }
+}
+
+type Writer<D> = DataWriter<D, CDRSerializerAdapter<D>>;
+type Reader<D> = DataReader<D, CDRDeserializerAdapter<D>>;
+
+fn create_topic(
+    participant: &DomainParticipant,
+    name: &str,
+    type_name: &str,
+    qos: &QosPolicies,
+) -> Result<Topic> {
+    let topic = participant.create_topic(
+        name.to_string(),
+        type_name.to_string(),
+        qos,
+        TopicKind::WithKey,
+    )?;
+    Ok(topic)
+}
+
+/// Publishes sensor data to DDS topics.
+pub struct DdsPublisher {
+    participant: DomainParticipant,
+    sensor_id: SensorId,
+    packet_writer: Writer<PacketMessage>,
+    metadata_writer: Writer<PacketMetaData>,
+    frame_writer: Writer<FrameMessage>,
+}
+
+impl DdsPublisher {
+    /// Joins the configured domain and creates writers.
+    pub fn new(config: &DdsConfig) -> Result<Self> {
+        let participant = DomainParticipant::new(config.domain_id)?;
+        Self::with_participant(participant, config)
+    }
+
+    /// Creates writers on an existing domain participant.
+    /// The `domain_id` in config is ignored.
+    pub fn with_participant(participant: DomainParticipant, config: &DdsConfig) -> Result<Self> {
+        let qos = config.qos.to_policies();
+        let publisher = participant.create_publisher(&qos)?;
+
+        let packet_topic = create_topic(
+            &participant,
+            &config.topics.packets,
+            "ouster_lidar::PacketMessage",
+            &qos,
+        )?;
+        let metadata_topic = create_topic(
+            &participant,
+            &config.topics.metadata,
+            "ouster_lidar::PacketMetaData",
+            &qos,
+        )?;
+        let frame_topic = create_topic(
+            &participant,
+            &config.topics.frames,
+            "ouster_lidar::FrameMessage",
+            &qos,
+        )?;
+
+        Ok(Self {
+            sensor_id: config.sensor_id,
+            packet_writer: publisher.create_datawriter_cdr(&packet_topic, Some(qos.clone()))?,
+            metadata_writer: publisher.create_datawriter_cdr(&metadata_topic, Some(qos.clone()))?,
+            frame_writer: publisher.create_datawriter_cdr(&frame_topic, Some(qos))?,
+            participant,
+        })
+    }
+
+    /// Returns the domain participant.
+    pub fn participant(&self) -> &DomainParticipant {
+        &self.participant
+    }
+
+    /// Returns the sensor ID put in keys of published samples.
+    pub fn sensor_id(&self) -> SensorId {
+        self.sensor_id
+    }
+
+    /// Publishes a packet and the metadata of its columns.
+    pub fn publish_packet(&self, packet: &Packet) -> Result<()> {
+        let message = PacketMessage {
+            sensor_id: self.sensor_id,
+            packet: *packet,
+        };
+        self.packet_writer.write(message, None)?;
+        for column in packet.columns.iter() {
+            self.metadata_writer
+                .write(PacketMetaData::new(self.sensor_id, column), None)?;
+        }
+        Ok(())
+    }
+
+    /// Publishes a frame.
+    pub fn publish_frame(&self, frame: &Frame) -> Result<()> {
+        self.frame_writer
+            .write(FrameMessage::new(self.sensor_id, frame), None)?;
+        Ok(())
+    }
+}
+
+impl Debug for DdsPublisher {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("DdsPublisher")
+            .field("domain_id", &self.participant.domain_id())
+            .field("sensor_id", &self.sensor_id)
+            .finish()
+    }
+}
+
+/// Publishes packets along with the frames assembled from them.
+///
+/// It runs a [FrameConverter] on pushed packets, so subscribers
+/// can read complete frames without rebuilding them. Frames and
+/// IMU packets are also published as ROS 2 messages if a
+/// [Ros2Publisher] is set.
+#[derive(Debug)]
+pub struct DdsBridge {
+    publisher: DdsPublisher,
+    ros2: Option<Ros2Publisher>,
+    converter: FrameConverter,
+}
+
+impl DdsBridge {
+    /// Creates the bridge from the sensor config.
+    pub fn new(publisher: DdsPublisher, config: Config) -> Result<Self> {
+        Ok(Self {
+            publisher,
+            ros2: None,
+            converter: FrameConverter::from_config(config)?,
+        })
+    }
+
+    /// Returns the underlying publisher.
+    pub fn publisher(&self) -> &DdsPublisher {
+        &self.publisher
+    }
+
+    /// Sets the publisher of ROS 2 messages.
+    pub fn set_ros2_publisher(&mut self, ros2: Ros2Publisher) {
+        self.ros2 = Some(ros2);
+    }
+
+    /// Returns the publisher of ROS 2 messages if set.
+    pub fn ros2_publisher(&self) -> Option<&Ros2Publisher> {
+        self.ros2.as_ref()
+    }
+
+    /// Returns the frame converter, e.g., to set up filters.
+    pub fn converter_mut(&mut self) -> &mut FrameConverter {
+        &mut self.converter
+    }
+
+    /// Publishes the packet and the frames it completes.
+    /// Returns the number of published frames. If the converter
+    /// rejects the packet, e.g. after the sensor rewinds, the frame in
+    /// progress is discarded before the error is returned, so the
+    /// bridge can go on with the next packet.
+    pub fn push_packet(&mut self, packet: &Packet) -> Result<usize> {
+        self.publisher.publish_packet(packet)?;
+        let frames = match self.converter.push_packet(packet) {
+            Ok(frames) => frames,
+            Err(error) => {
+                self.converter.reset();
+                return Err(error);
+            }
+        };
+        for frame in frames.iter() {
+            self.publish_frame(frame)?;
+        }
+        Ok(frames.len())
+    }
+
+    /// Publishes an IMU packet as a ROS 2 message if a
+    /// [Ros2Publisher] is set.
+    pub fn push_imu_packet(&self, packet: &ImuPacket) -> Result<()> {
+        if let Some(ros2) = &self.ros2 {
+            ros2.publish_imu(packet)?;
+        }
+        Ok(())
+    }
+
+    /// Publishes the last partial frame if any.
+    pub fn finish(self) -> Result<Option<Frame>> {
+        let Self {
+            publisher,
+            ros2,
+            converter,
+        } = self;
+        let columns_per_revolution = converter.columns_per_revolution();
+        let frame_opt = converter.finish();
+        if let Some(frame) = &frame_opt {
+            publisher.publish_frame(frame)?;
+            if let Some(ros2) = &ros2 {
+                ros2.publish_frame(frame, columns_per_revolution)?;
+            }
+        }
+        Ok(frame_opt)
+    }
+
+    fn publish_frame(&self, frame: &Frame) -> Result<()> {
+        self.publisher.publish_frame(frame)?;
+        if let Some(ros2) = &self.ros2 {
+            ros2.publish_frame(frame, self.converter.columns_per_revolution())?;
+        }
+        Ok(())
+    }
+}
+
+/// Subscribes sensor data from DDS topics.
+///
+/// The readers implement [Evented] and can be registered on a
+/// [Poll] to wait for incoming samples.
+pub struct DdsSubscriber {
+    participant: DomainParticipant,
+    packet_reader: Option<Reader<PacketMessage>>,
+    metadata_reader: Option<Reader<PacketMetaData>>,
+    frame_reader: Option<Reader<FrameMessage>>,
+}
+
+impl DdsSubscriber {
+    /// Joins the configured domain and creates readers of the
+    /// subscribed topics.
+    pub fn new(config: &DdsConfig) -> Result<Self> {
+        let participant = DomainParticipant::new(config.domain_id)?;
+        Self::with_participant(participant, config)
+    }
+
+    /// Creates readers on an existing domain participant.
+    /// The `domain_id` in config is ignored.
+    pub fn with_participant(participant: DomainParticipant, config: &DdsConfig) -> Result<Self> {
+        let qos = config.qos.to_policies();
+        let subscriber = participant.create_subscriber(&qos)?;
+        let subscriptions = config.subscriptions;
+
+        let packet_reader = if subscriptions.packets {
+            let topic = create_topic(
+                &participant,
+                &config.topics.packets,
+                "ouster_lidar::PacketMessage",
+                &qos,
+            )?;
+            Some(subscriber.create_datareader_cdr(&topic, None)?)
+        } else {
+            None
+        };
+        let metadata_reader = if subscriptions.metadata {
+            let topic = create_topic(
+                &participant,
+                &config.topics.metadata,
+                "ouster_lidar::PacketMetaData",
+                &qos,
+            )?;
+            Some(subscriber.create_datareader_cdr(&topic, None)?)
+        } else {
+            None
+        };
+        let frame_reader = if subscriptions.frames {
+            let topic = create_topic(
+                &participant,
+                &config.topics.frames,
+                "ouster_lidar::FrameMessage",
+                &qos,
+            )?;
+            Some(subscriber.create_datareader_cdr(&topic, None)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            packet_reader,
+            metadata_reader,
+            frame_reader,
+            participant,
+        })
+    }
+
+    /// Returns the domain participant.
+    pub fn participant(&self) -> &DomainParticipant {
+        &self.participant
+    }
+
+    /// Registers the packet reader on the poll. It fails if packets
+    /// are not subscribed.
+    pub fn register_packets(&self, poll: &Poll, token: Token) -> Result<()> {
+        register(self.packet_reader.as_ref(), "packets", poll, token)
+    }
+
+    /// Registers the metadata reader on the poll. It fails if
+    /// metadata is not subscribed.
+    pub fn register_metadata(&self, poll: &Poll, token: Token) -> Result<()> {
+        register(self.metadata_reader.as_ref(), "metadata", poll, token)
+    }
+
+    /// Registers the frame reader on the poll. It fails if frames are
+    /// not subscribed.
+    pub fn register_frames(&self, poll: &Poll, token: Token) -> Result<()> {
+        register(self.frame_reader.as_ref(), "frames", poll, token)
+    }
+
+    /// Takes all received packets. Empty if packets are not subscribed.
+    pub fn take_packets(&mut self) -> Result<Vec<PacketMessage>> {
+        take_all(self.packet_reader.as_mut())
+    }
+
+    /// Takes all received column metadata. Empty if metadata is not
+    /// subscribed.
+    pub fn take_metadata(&mut self) -> Result<Vec<PacketMetaData>> {
+        take_all(self.metadata_reader.as_mut())
+    }
+
+    /// Takes all received frame messages. Empty if frames are not
+    /// subscribed.
+    pub fn take_frame_messages(&mut self) -> Result<Vec<FrameMessage>> {
+        take_all(self.frame_reader.as_mut())
+    }
+
+    /// Takes all received frames.
+    pub fn take_frames(&mut self) -> Result<Vec<Frame>> {
+        self.take_frame_messages()?
+            .iter()
+            .map(FrameMessage::to_frame)
+            .collect()
+    }
+}
+
+impl Debug for DdsSubscriber {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("DdsSubscriber")
+            .field("domain_id", &self.participant.domain_id())
+            .finish()
+    }
+}
+
+fn register<E: Evented>(evented: Option<&E>, name: &str, poll: &Poll, token: Token) -> Result<()> {
+    let evented = evented.ok_or_else(|| format_err!("{} are not subscribed", name))?;
+    poll.register(evented, token, Ready::readable(), PollOpt::edge())?;
+    Ok(())
+}
+
+fn take_all<D>(reader: Option<&mut Reader<D>>) -> Result<Vec<D>>
+where
+    D: Keyed + DeserializeOwned + 'static,
+    D::K: Key,
+{
+    let reader = match reader {
+        Some(reader) => reader,
+        None => return Ok(vec![]),
+    };
+    let mut samples = vec![];
+    while let Some(sample) = reader.take_next_sample()? {
+        // Disposed instances carry only keys
+        if let Ok(value) = sample.into_value() {
+            samples.push(value);
+        }
+    }
+    Ok(samples)
+}
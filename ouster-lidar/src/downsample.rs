@@ -0,0 +1,115 @@
+//! Point cloud decimation methods.
+//!
+//! All methods keep the attributes of a representative input
+//! point, such as reflectivity, laser ID and timestamp.
+
+use super::{frame_converter::Frame, pcd_converter::Point};
+use crate::{common::*, utils::squared_distance};
+use rand::{rngs::StdRng, seq::index::sample, SeedableRng};
+use std::collections::HashMap;
+
+/// Selects which point represents a voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VoxelRepresentative {
+    /// The point nearest to the centroid with its position moved to the centroid.
+    Centroid,
+    /// The point nearest to the centroid as is.
+    NearestToCentroid,
+}
+
+/// Voxel grid downsampling that outputs one point per occupied voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct VoxelGrid {
+    /// Voxel size along X, Y and Z axes in meters.
+    pub voxel_size: [f64; 3],
+    pub representative: VoxelRepresentative,
+}
+
+impl VoxelGrid {
+    /// Creates a grid of cubic voxels.
+    pub fn new(voxel_size: f64, representative: VoxelRepresentative) -> Self {
+        Self {
+            voxel_size: [voxel_size; 3],
+            representative,
+        }
+    }
+
+    /// Returns the integer voxel coordinates of a position in meters.
+    pub fn voxel_of(&self, position: [f64; 3]) -> [i64; 3] {
+        [0, 1, 2].map(|idx| (position[idx] / self.voxel_size[idx]).floor() as i64)
+    }
+
+    /// Downsamples the points. The output is ordered by
+    /// the first appearance of each voxel in the input.
+    pub fn downsample(&self, points: &[Point]) -> Vec<Point> {
+        let mut voxel_indices: HashMap<[i64; 3], usize> = HashMap::new();
+        let mut voxels: Vec<(Vec<usize>, [f64; 3])> = vec![];
+
+        for (index, point) in points.iter().enumerate() {
+            let position = point.point.map(|v| v.as_meters());
+            let voxel_index = *voxel_indices
+                .entry(self.voxel_of(position))
+                .or_insert_with(|| {
+                    voxels.push((vec![], [0.0; 3]));
+                    voxels.len() - 1
+                });
+            let (members, sum) = &mut voxels[voxel_index];
+            members.push(index);
+            izip!(sum.iter_mut(), position.iter()).for_each(|(sum, value)| *sum += value);
+        }
+
+        voxels
+            .into_iter()
+            .map(|(members, sum)| {
+                let centroid = sum.map(|value| value / members.len() as f64);
+                let nearest = members
+                    .iter()
+                    .map(|&index| &points[index])
+                    .min_by_key(|point| {
+                        let position = point.point.map(|v| v.as_meters());
+                        R64::new(squared_distance(position, centroid))
+                    })
+                    .unwrap();
+
+                let mut output = nearest.clone();
+                if self.representative == VoxelRepresentative::Centroid {
+                    output.point = centroid.map(Length::from_meters);
+                }
+                output
+            })
+            .collect()
+    }
+
+    /// Downsamples the points of a frame.
+    pub fn downsample_frame(&self, frame: &Frame) -> Frame {
+        Frame {
+            frame_id: frame.frame_id,
+            skipped_frame_ids: frame.skipped_frame_ids.clone(),
+            timestamps: frame.timestamps.clone(),
+            points: self.downsample(&frame.points),
+            completeness: frame.completeness.clone(),
+        }
+    }
+}
+
+/// Keeps every `step`-th point starting from the first one.
+pub fn uniform_decimate(points: &[Point], step: usize) -> Vec<Point> {
+    points.iter().step_by(step.max(1)).cloned().collect()
+}
+
+/// Keeps `count` randomly chosen points in their original order.
+///
+/// The choice is reproducible for the same `seed`.
+pub fn random_decimate(points: &[Point], count: usize, seed: u64) -> Vec<Point> {
+    if count >= points.len() {
+        return points.to_vec();
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut indices = sample(&mut rng, points.len(), count).into_vec();
+    indices.sort_unstable();
+    indices
+        .into_iter()
+        .map(|index| points[index].clone())
+        .collect()
+}
@@ -3,29 +3,88 @@
 use crate::common::*;
 
 /// The mode includes number of vertical scans in one revolution and rotation frequency (Hz).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Modes this crate does not know are kept as [Unknown](LidarMode::Unknown)
+/// so that sensor metadata still parses. Their dimensions are read from
+/// the `<columns>x<frequency>` name if possible.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum LidarMode {
-    #[serde(rename = "512x10")]
     Mode512x10,
-    #[serde(rename = "512x20")]
     Mode512x20,
-    #[serde(rename = "1024x10")]
     Mode1024x10,
-    #[serde(rename = "1024x20")]
     Mode1024x20,
-    #[serde(rename = "2048x10")]
     Mode2048x10,
+    Mode4096x5,
+    Unknown(String),
 }
 
 impl LidarMode {
-    pub fn columns_per_revolution(&self) -> u16 {
+    /// Returns the modes known to this crate.
+    pub fn known_modes() -> [LidarMode; 6] {
+        use LidarMode::*;
+        [
+            Mode512x10,
+            Mode512x20,
+            Mode1024x10,
+            Mode1024x20,
+            Mode2048x10,
+            Mode4096x5,
+        ]
+    }
+
+    /// Returns the number of columns in a revolution, or `None` for
+    /// unknown modes without a `<columns>x<frequency>` name.
+    pub fn columns_per_revolution(&self) -> Option<u16> {
+        use LidarMode::*;
+        match self {
+            Mode512x10 | Mode512x20 => Some(512),
+            Mode1024x10 | Mode1024x20 => Some(1024),
+            Mode2048x10 => Some(2048),
+            Mode4096x5 => Some(4096),
+            Unknown(name) => parse_mode_name(name).map(|(columns, _)| columns),
+        }
+    }
+
+    /// Returns the rotation frequency in Hz, or `None` for unknown
+    /// modes without a `<columns>x<frequency>` name.
+    pub fn rotation_frequency(&self) -> Option<u16> {
         use LidarMode::*;
         match self {
-            Mode512x10 | Mode512x20 => 512,
-            Mode1024x10 | Mode1024x20 => 1024,
-            Mode2048x10 => 2048,
+            Mode4096x5 => Some(5),
+            Mode512x10 | Mode1024x10 | Mode2048x10 => Some(10),
+            Mode512x20 | Mode1024x20 => Some(20),
+            Unknown(name) => parse_mode_name(name).map(|(_, hz)| hz),
         }
     }
+
+    /// Returns the duration of one revolution if the rotation
+    /// frequency is known.
+    pub fn frame_period(&self) -> Option<Duration> {
+        let hz = self.rotation_frequency()?;
+        Some(Duration::from_secs(1) / hz as u32)
+    }
+
+    /// Returns the time between consecutive columns if the mode is
+    /// known.
+    pub fn column_time(&self) -> Option<Duration> {
+        let columns = self.columns_per_revolution()?;
+        Some(self.frame_period()? / columns as u32)
+    }
+
+    /// Returns the number of columns in a revolution, or an error for
+    /// modes of unknown dimensions.
+    pub(crate) fn checked_columns_per_revolution(&self) -> Result<u16> {
+        self.columns_per_revolution()
+            .ok_or_else(|| format_err!("lidar mode '{}' has unknown dimensions", self))
+    }
+}
+
+/// Parses a `<columns>x<frequency>` mode name.
+fn parse_mode_name(name: &str) -> Option<(u16, u16)> {
+    let (columns, hz) = name.split_once('x')?;
+    let columns = columns.parse().ok().filter(|&columns| columns > 0)?;
+    let hz = hz.parse().ok().filter(|&hz| hz > 0)?;
+    Some((columns, hz))
 }
 
 impl Display for LidarMode {
@@ -37,11 +96,61 @@ impl Display for LidarMode {
             Mode1024x10 => "1024x10",
             Mode1024x20 => "1024x20",
             Mode2048x10 => "2048x10",
+            Mode4096x5 => "4096x5",
+            Unknown(name) => name,
         };
         write!(formatter, "{}", text)
     }
 }
 
+impl FromStr for LidarMode {
+    type Err = Error;
+
+    /// Parses a mode name. Unknown modes are accepted if they are
+    /// named `<columns>x<frequency>`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mode = Self::from_name(text);
+        if let LidarMode::Unknown(_) = mode {
+            ensure!(
+                parse_mode_name(text).is_some(),
+                "unsupported lidar mode '{}'",
+                text
+            );
+        }
+        Ok(mode)
+    }
+}
+
+impl LidarMode {
+    /// Maps a name to a mode, falling back to
+    /// [Unknown](LidarMode::Unknown).
+    fn from_name(name: &str) -> Self {
+        Self::known_modes()
+            .into_iter()
+            .find(|mode| mode.to_string() == name)
+            .unwrap_or_else(|| LidarMode::Unknown(name.to_string()))
+    }
+}
+
+impl Serialize for LidarMode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for LidarMode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self::from_name(&name))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MultipurposeIoMode {
     #[serde(rename = "OUTPUT_FROM_INTERNAL_OSC")]
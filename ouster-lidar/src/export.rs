@@ -0,0 +1,223 @@
+//! Writers of point cloud files.
+//!
+//! Points are stored with the fields below in PCD and PLY files.
+//!
+//! | field        | type    | content                 |
+//! |--------------|---------|-------------------------|
+//! | x, y, z      | float32 | position in meters      |
+//! | intensity    | float32 | signal photons          |
+//! | reflectivity | uint16  | reflectivity            |
+//! | ring         | uint16  | laser ID                |
+//! | ambient      | uint16  | noise photons           |
+//! | range        | uint32  | distance in millimeters |
+//!
+//! LAS files use point data format 1 in millimeter resolution, with
+//! signal photons as intensity, the laser ID as user data and the
+//! point timestamp in seconds as GPS time.
+
+use super::{frame_converter::Frame, pcd_converter::Point};
+use crate::common::*;
+use std::io::BufWriter;
+
+/// Point cloud file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PointCloudFormat {
+    /// Binary PCD v0.7.
+    Pcd,
+    /// Binary little-endian PLY.
+    Ply,
+    /// LAS 1.2.
+    Las,
+}
+
+impl PointCloudFormat {
+    /// Returns the file extension without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Pcd => "pcd",
+            Self::Ply => "ply",
+            Self::Las => "las",
+        }
+    }
+
+    /// Guesses the format from the extension of a path.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .ok_or_else(|| format_err!("{} has no extension", path.as_ref().display()))?;
+        extension.parse()
+    }
+
+    /// Writes points in this format.
+    pub fn write<W: Write>(&self, writer: W, points: &[Point]) -> Result<()> {
+        match self {
+            Self::Pcd => write_pcd(writer, points),
+            Self::Ply => write_ply(writer, points),
+            Self::Las => write_las(writer, points),
+        }
+    }
+}
+
+impl FromStr for PointCloudFormat {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let format = match text.to_lowercase().as_str() {
+            "pcd" => Self::Pcd,
+            "ply" => Self::Ply,
+            "las" => Self::Las,
+            _ => bail!("unsupported point cloud format '{}'", text),
+        };
+        Ok(format)
+    }
+}
+
+impl Display for PointCloudFormat {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.extension())
+    }
+}
+
+/// Writes points as a binary PCD file.
+pub fn write_pcd<W: Write>(mut writer: W, points: &[Point]) -> Result<()> {
+    write!(
+        writer,
+        "# .PCD v0.7 - Point Cloud Data file format\n\
+         VERSION 0.7\n\
+         FIELDS x y z intensity reflectivity ring ambient range\n\
+         SIZE 4 4 4 4 2 2 2 4\n\
+         TYPE F F F F U U U U\n\
+         COUNT 1 1 1 1 1 1 1 1\n\
+         WIDTH {len}\n\
+         HEIGHT 1\n\
+         VIEWPOINT 0 0 0 1 0 0 0\n\
+         POINTS {len}\n\
+         DATA binary\n",
+        len = points.len()
+    )?;
+    write_points(writer, points)
+}
+
+/// Writes points as a binary little-endian PLY file.
+pub fn write_ply<W: Write>(mut writer: W, points: &[Point]) -> Result<()> {
+    write!(
+        writer,
+        "ply\n\
+         format binary_little_endian 1.0\n\
+         element vertex {}\n\
+         property float x\n\
+         property float y\n\
+         property float z\n\
+         property float intensity\n\
+         property ushort reflectivity\n\
+         property ushort ring\n\
+         property ushort ambient\n\
+         property uint range\n\
+         end_header\n",
+        points.len()
+    )?;
+    write_points(writer, points)
+}
+
+/// Writes points as a LAS 1.2 file with point data format 1.
+pub fn write_las<W: Write>(mut writer: W, points: &[Point]) -> Result<()> {
+    const HEADER_SIZE: u16 = 227;
+    const POINT_RECORD_LENGTH: u16 = 28;
+    const SCALE: f64 = 0.001;
+
+    let num_points = u32::try_from(points.len())?;
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for point in points.iter() {
+        for axis in 0..3 {
+            let value = point.point[axis].as_meters();
+            min[axis] = min[axis].min(value);
+            max[axis] = max[axis].max(value);
+        }
+    }
+    if points.is_empty() {
+        min = [0.0; 3];
+        max = [0.0; 3];
+    }
+
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend_from_slice(b"LASF");
+    // File source ID, global encoding and project GUID
+    header.extend_from_slice(&[0; 20]);
+    header.extend_from_slice(&[1, 2]);
+    let mut identifier = [0u8; 32];
+    identifier[..6].copy_from_slice(b"ouster");
+    header.extend_from_slice(&identifier);
+    let mut software = [0u8; 32];
+    software[..12].copy_from_slice(b"ouster-lidar");
+    header.extend_from_slice(&software);
+    // Creation day and year
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&HEADER_SIZE.to_le_bytes());
+    header.extend_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes());
+    header.push(1);
+    header.extend_from_slice(&POINT_RECORD_LENGTH.to_le_bytes());
+    header.extend_from_slice(&num_points.to_le_bytes());
+    for count in [num_points, 0, 0, 0, 0] {
+        header.extend_from_slice(&count.to_le_bytes());
+    }
+    for value in [SCALE; 3].iter().chain([0.0; 3].iter()) {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    for axis in 0..3 {
+        header.extend_from_slice(&max[axis].to_le_bytes());
+        header.extend_from_slice(&min[axis].to_le_bytes());
+    }
+    debug_assert_eq!(header.len(), HEADER_SIZE as usize);
+    writer.write_all(&header)?;
+
+    for point in points.iter() {
+        for axis in point.point.iter() {
+            let value = (axis.as_meters() / SCALE).round() as i32;
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&point.signal_photons.to_le_bytes())?;
+        // Single return, unclassified, zero scan angle
+        writer.write_all(&[0b0000_1001, 0, 0])?;
+        writer.write_all(&[point.laser_id as u8])?;
+        writer.write_all(&0u16.to_le_bytes())?;
+        writer.write_all(&point.timestamp.as_secs_f64().to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Saves points to path in the given format.
+pub fn save_points<P: AsRef<Path>>(
+    path: P,
+    points: &[Point],
+    format: PointCloudFormat,
+) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path.as_ref())?);
+    format.write(&mut writer, points)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Saves points of a frame to path in the format guessed from its
+/// extension.
+pub fn save_frame<P: AsRef<Path>>(path: P, frame: &Frame) -> Result<()> {
+    let format = PointCloudFormat::from_path(path.as_ref())?;
+    save_points(path, &frame.points, format)
+}
+
+fn write_points<W: Write>(mut writer: W, points: &[Point]) -> Result<()> {
+    for point in points.iter() {
+        for axis in point.point.iter() {
+            writer.write_all(&(axis.as_meters() as f32).to_le_bytes())?;
+        }
+        writer.write_all(&(point.signal_photons as f32).to_le_bytes())?;
+        writer.write_all(&point.reflectivity.to_le_bytes())?;
+        writer.write_all(&(point.laser_id as u16).to_le_bytes())?;
+        writer.write_all(&point.noise_photons.to_le_bytes())?;
+        writer.write_all(&(point.distance.as_millimeters().round() as u32).to_le_bytes())?;
+    }
+    Ok(())
+}
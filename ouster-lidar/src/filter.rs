@@ -0,0 +1,188 @@
+//! Composable filters on point clouds.
+//!
+//! A [FilterPipeline] can be applied to the points of a [Frame],
+//! or installed on [PointCloudConverter](super::pcd_converter::PointCloudConverter)
+//! so that rejected points are never pushed to the output.
+
+use super::{frame_converter::Frame, pcd_converter::Point};
+use crate::common::*;
+use std::f64::consts::PI;
+
+/// A box in 3D space, optionally rotated around the Z axis.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    /// Box center in meters.
+    pub center: [f64; 3],
+    /// Half of the box size along each axis in meters.
+    pub half_extents: [f64; 3],
+    /// Rotation around the Z axis in radians. Zero means axis-aligned.
+    #[serde(default)]
+    pub yaw: f64,
+}
+
+impl BoundingBox {
+    /// Creates an axis-aligned box from its corners.
+    pub fn from_min_max(min: [f64; 3], max: [f64; 3]) -> Self {
+        Self {
+            center: [0, 1, 2].map(|idx| (min[idx] + max[idx]) / 2.0),
+            half_extents: [0, 1, 2].map(|idx| (max[idx] - min[idx]).abs() / 2.0),
+            yaw: 0.0,
+        }
+    }
+
+    /// Checks if the point in meters is inside the box.
+    pub fn contains(&self, point: [f64; 3]) -> bool {
+        let [dx, dy, dz] = [0, 1, 2].map(|idx| point[idx] - self.center[idx]);
+        let (sin, cos) = self.yaw.sin_cos();
+        let local = [cos * dx + sin * dy, -sin * dx + cos * dy, dz];
+        izip!(local.iter(), self.half_extents.iter()).all(|(value, half)| value.abs() <= *half)
+    }
+}
+
+/// The values of a point that filters check, so that points can be
+/// filtered before a [Point] is built.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawPoint {
+    /// Position in meters.
+    pub xyz: [f64; 3],
+    /// Distance in meters.
+    pub distance: f64,
+    pub reflectivity: u16,
+    pub signal_photons: u16,
+}
+
+impl From<&Point> for RawPoint {
+    fn from(point: &Point) -> Self {
+        Self {
+            xyz: point.point.map(|value| value.as_meters()),
+            distance: point.distance.as_meters(),
+            reflectivity: point.reflectivity,
+            signal_photons: point.signal_photons,
+        }
+    }
+}
+
+/// A single criterion to keep or reject a [Point].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PointFilter {
+    /// Keeps points with distance within `[min, max]` meters.
+    Range { min: f64, max: f64 },
+    /// Keeps points with reflectivity not less than `min`.
+    Reflectivity { min: u16 },
+    /// Keeps points with signal photons not less than `min`.
+    Signal { min: u16 },
+    /// Removes points with zero distance, which are pixels without return.
+    ZeroReturn,
+    /// Keeps points inside the box.
+    RegionOfInterest(BoundingBox),
+    /// Removes points inside the box, such as returns from the vehicle body.
+    CropBox(BoundingBox),
+    /// Keeps points within a sector in degrees.
+    ///
+    /// The azimuth is measured counter-clockwise from X axis in `[0, 360)`.
+    /// The range wraps around zero if `azimuth[0] > azimuth[1]`.
+    /// The elevation is measured from XY plane in `[-90, 90]`.
+    Sector {
+        azimuth: [f64; 2],
+        elevation: [f64; 2],
+    },
+}
+
+impl PointFilter {
+    /// Checks if the point passes the filter.
+    pub fn keep(&self, point: &Point) -> bool {
+        self.keep_raw(&RawPoint::from(point))
+    }
+
+    /// Checks if the point values pass the filter.
+    pub fn keep_raw(&self, point: &RawPoint) -> bool {
+        match self {
+            Self::Range { min, max } => point.distance >= *min && point.distance <= *max,
+            Self::Reflectivity { min } => point.reflectivity >= *min,
+            Self::Signal { min } => point.signal_photons >= *min,
+            Self::ZeroReturn => point.distance > 0.0,
+            Self::RegionOfInterest(bbox) => bbox.contains(point.xyz),
+            Self::CropBox(bbox) => !bbox.contains(point.xyz),
+            Self::Sector { azimuth, elevation } => {
+                let [x, y, z] = point.xyz;
+                let point_azimuth = {
+                    let degrees = y.atan2(x) * 180.0 / PI;
+                    if degrees < 0.0 {
+                        degrees + 360.0
+                    } else {
+                        degrees
+                    }
+                };
+                let point_elevation = z.atan2(x.hypot(y)) * 180.0 / PI;
+
+                let [start, end] = *azimuth;
+                let azimuth_ok = if start <= end {
+                    point_azimuth >= start && point_azimuth <= end
+                } else {
+                    point_azimuth >= start || point_azimuth <= end
+                };
+                let elevation_ok =
+                    point_elevation >= elevation[0] && point_elevation <= elevation[1];
+                azimuth_ok && elevation_ok
+            }
+        }
+    }
+}
+
+/// An ordered list of [PointFilter]s. A point is kept only if
+/// it passes all filters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilterPipeline {
+    filters: Vec<PointFilter>,
+}
+
+impl FilterPipeline {
+    /// Creates an empty pipeline that keeps all points.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a filter and returns the pipeline.
+    pub fn with(mut self, filter: PointFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Appends a filter.
+    pub fn push(&mut self, filter: PointFilter) {
+        self.filters.push(filter);
+    }
+
+    /// Returns the list of filters.
+    pub fn filters(&self) -> &[PointFilter] {
+        &self.filters
+    }
+
+    /// Returns true if there is no filter.
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Checks if the point passes all filters.
+    pub fn keep(&self, point: &Point) -> bool {
+        self.keep_raw(&RawPoint::from(point))
+    }
+
+    /// Checks if the point values pass all filters.
+    pub fn keep_raw(&self, point: &RawPoint) -> bool {
+        self.filters.iter().all(|filter| filter.keep_raw(point))
+    }
+
+    /// Removes rejected points in place.
+    pub fn apply(&self, points: &mut Vec<Point>) {
+        if !self.is_empty() {
+            points.retain(|point| self.keep(point));
+        }
+    }
+
+    /// Removes rejected points from the frame.
+    pub fn apply_frame(&self, frame: &mut Frame) {
+        self.apply(&mut frame.points);
+    }
+}
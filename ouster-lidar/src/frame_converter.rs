@@ -1,9 +1,12 @@
 //! Provides a set of tools convert raw packets from Ouster sensors.
 
 use super::{
-    config::Config,
-    consts::COLUMNS_PER_PACKET,
+    config::{AzimuthWindow, Config},
+    consts::{COLUMNS_PER_PACKET, ENCODER_TICKS_PER_REV},
+    enums::LidarMode,
+    filter::FilterPipeline,
     packet::{Column, Packet},
+    parallel,
     pcd_converter::{Point, PointCloudConverter},
 };
 use crate::common::*;
@@ -20,6 +23,221 @@ pub struct Frame {
     pub timestamps: Vec<(u16, u64)>,
     /// Point cloud data.
     pub points: Vec<Point>,
+    /// Which columns of the frame arrived.
+    pub completeness: FrameCompleteness,
+}
+
+impl Frame {
+    /// Returns the earliest column timestamp, or zero if the frame has
+    /// no columns.
+    pub fn start_time(&self) -> Duration {
+        self.timestamps
+            .iter()
+            .map(|(_, timestamp)| *timestamp)
+            .min()
+            .map(Duration::from_nanos)
+            .unwrap_or_default()
+    }
+
+    /// Returns the latest column timestamp, or zero if the frame has
+    /// no columns.
+    pub fn end_time(&self) -> Duration {
+        self.timestamps
+            .iter()
+            .map(|(_, timestamp)| *timestamp)
+            .max()
+            .map(Duration::from_nanos)
+            .unwrap_or_default()
+    }
+
+    /// Returns the duration from the frame start to the end.
+    pub fn duration(&self) -> Duration {
+        self.end_time().saturating_sub(self.start_time())
+    }
+
+    /// Returns the time of each point relative to the frame start, in
+    /// the same order as [points](Frame::points).
+    pub fn point_time_offsets(&self) -> impl Iterator<Item = Duration> + '_ {
+        let start_time = self.start_time();
+        self.points
+            .iter()
+            .map(move |point| point.timestamp.saturating_sub(start_time))
+    }
+}
+
+/// A set of measurement IDs, stored as one bit per column.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ColumnBitmap {
+    len: u16,
+    words: Vec<u64>,
+}
+
+impl ColumnBitmap {
+    /// Creates an empty bitmap for measurement IDs below `len`.
+    pub fn new(len: u16) -> Self {
+        Self {
+            len,
+            words: vec![0; (len as usize).div_ceil(64)],
+        }
+    }
+
+    /// Returns the number of measurement IDs the bitmap can hold.
+    pub fn len(&self) -> u16 {
+        self.len
+    }
+
+    /// Returns true if the bitmap holds no measurement IDs.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Marks a column present. IDs out of range are ignored.
+    pub fn insert(&mut self, measurement_id: u16) {
+        if measurement_id < self.len {
+            let index = measurement_id as usize;
+            self.words[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    /// Returns if a column is present.
+    pub fn contains(&self, measurement_id: u16) -> bool {
+        let index = measurement_id as usize;
+        measurement_id < self.len && self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Returns the number of present columns.
+    pub fn count(&self) -> u16 {
+        self.words.iter().map(|word| word.count_ones() as u16).sum()
+    }
+
+    /// Iterates over present measurement IDs in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..self.len).filter(|&measurement_id| self.contains(measurement_id))
+    }
+}
+
+/// The reason a frame is not complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PartialReason {
+    /// The stream ended before the last column of the frame.
+    EndOfStream,
+    /// Columns of the frame were never received.
+    MissingColumns,
+    /// Columns of the frame were marked invalid by the sensor.
+    InvalidColumns,
+}
+
+impl Display for PartialReason {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Self::EndOfStream => "stream ended before the last column",
+            Self::MissingColumns => "missing columns",
+            Self::InvalidColumns => "invalid columns",
+        };
+        formatter.write_str(text)
+    }
+}
+
+/// Column presence and counts of a frame within the azimuth window.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameCompleteness {
+    /// Valid columns received.
+    pub columns: ColumnBitmap,
+    /// The number of columns of a complete frame.
+    pub expected_columns: u16,
+    /// The number of columns marked invalid.
+    pub invalid_columns: u16,
+    /// The number of columns filled with zero-range points.
+    pub filled_columns: u16,
+    /// Whether [FrameConverter::finish] flushed the frame before its
+    /// last column arrived.
+    pub truncated: bool,
+}
+
+impl FrameCompleteness {
+    /// Creates an empty record for a frame of `expected_columns`.
+    pub fn new(columns_per_revolution: u16, expected_columns: u16) -> Self {
+        Self {
+            columns: ColumnBitmap::new(columns_per_revolution),
+            expected_columns,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the number of valid columns.
+    pub fn valid_columns(&self) -> u16 {
+        self.columns.count()
+    }
+
+    /// Returns the number of columns neither valid nor invalid.
+    pub fn missing_columns(&self) -> u16 {
+        self.expected_columns
+            .saturating_sub(self.valid_columns() + self.invalid_columns)
+    }
+
+    /// Returns the fraction of expected columns that are valid.
+    pub fn ratio(&self) -> f64 {
+        match self.expected_columns {
+            0 => 1.0,
+            expected => (self.valid_columns() as f64 / expected as f64).min(1.0),
+        }
+    }
+
+    /// Returns true if all expected columns are valid.
+    pub fn is_complete(&self) -> bool {
+        self.partial_reason().is_none()
+    }
+
+    /// Returns why the frame is partial, or `None` if it is complete.
+    pub fn partial_reason(&self) -> Option<PartialReason> {
+        if self.truncated {
+            Some(PartialReason::EndOfStream)
+        } else if self.missing_columns() > 0 {
+            Some(PartialReason::MissingColumns)
+        } else if self.invalid_columns > 0 {
+            Some(PartialReason::InvalidColumns)
+        } else {
+            None
+        }
+    }
+}
+
+/// What [FrameConverter] does with frames that miss columns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PartialFramePolicy {
+    /// Emit partial frames as they are.
+    #[default]
+    Emit,
+    /// Drop partial frames.
+    Drop,
+    /// Emit partial frames with zero-range points in place of the
+    /// columns that are missing or invalid.
+    ZeroFill,
+}
+
+impl FromStr for PartialFramePolicy {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let policy = match text.to_lowercase().as_str() {
+            "emit" => Self::Emit,
+            "drop" => Self::Drop,
+            "zero-fill" => Self::ZeroFill,
+            _ => bail!("unsupported partial frame policy '{}'", text),
+        };
+        Ok(policy)
+    }
+}
+
+impl Display for PartialFramePolicy {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Self::Emit => "emit",
+            Self::Drop => "drop",
+            Self::ZeroFill => "zero-fill",
+        };
+        formatter.write_str(text)
+    }
 }
 
 /// It reads [columns](Column) of sensor data, and
@@ -30,19 +248,44 @@ pub struct Frame {
 /// The columns must be pushed in the same order
 /// of LIDAR output. It keeps track of skipped
 /// columns and dropped frames.
+///
+/// Valid columns with a zero timestamp are treated as missing their
+/// timestamp. It is interpolated from the encoder ticks and the
+/// rotation rate of the lidar mode, relative to the last column with a
+/// timestamp.
+///
+/// A frame is emitted as soon as the last column in the
+/// [azimuth window](AzimuthWindow) of the config arrives, or otherwise
+/// when a column of the next frame arrives.
+///
+/// Frames that miss columns are handled by the
+/// [partial frame policy](PartialFramePolicy). Frames with less than
+/// the minimum completeness are dropped under any policy.
 #[derive(Debug)]
 pub struct FrameConverter {
     pcd_converter: PointCloudConverter,
+    azimuth_window: AzimuthWindow,
+    partial_frame_policy: PartialFramePolicy,
+    min_completeness: f64,
+    dropped_partial_frames: usize,
+    /// Timestamp and encoder ticks of the last column with a timestamp.
+    last_timed_column: Option<(u64, u32)>,
     state: Option<FrameConverterState>,
 }
 
 impl FrameConverter {
-    /// Creates converter from config.
-    pub fn from_config(config: Config) -> Self {
-        Self {
-            pcd_converter: PointCloudConverter::from_config(config),
+    /// Creates converter from config. It fails if the lidar mode has
+    /// unknown dimensions.
+    pub fn from_config(config: Config) -> Result<Self> {
+        Ok(Self {
+            azimuth_window: config.azimuth_window,
+            pcd_converter: PointCloudConverter::from_config(config)?,
+            partial_frame_policy: PartialFramePolicy::default(),
+            min_completeness: 0.0,
+            dropped_partial_frames: 0,
+            last_timed_column: None,
             state: None,
-        }
+        })
     }
 
     /// Returns the resolution in `(width, height)` pair.
@@ -56,19 +299,116 @@ impl FrameConverter {
         self.pcd_converter.columns_per_revolution()
     }
 
+    /// Returns the lidar mode of the config.
+    pub fn lidar_mode(&self) -> &LidarMode {
+        self.pcd_converter.lidar_mode()
+    }
+
+    /// Returns the azimuth window.
+    pub fn azimuth_window(&self) -> AzimuthWindow {
+        self.azimuth_window
+    }
+
+    /// Sets the azimuth window, e.g. after changing it on the sensor.
+    pub fn set_azimuth_window(&mut self, azimuth_window: AzimuthWindow) {
+        self.azimuth_window = azimuth_window;
+    }
+
+    /// Returns the number of columns of a complete frame in the
+    /// azimuth window.
+    pub fn columns_per_frame(&self) -> u16 {
+        self.azimuth_window
+            .num_columns(self.columns_per_revolution())
+    }
+
+    /// Returns the policy for frames that miss columns.
+    pub fn partial_frame_policy(&self) -> PartialFramePolicy {
+        self.partial_frame_policy
+    }
+
+    /// Sets the policy for frames that miss columns.
+    pub fn set_partial_frame_policy(&mut self, policy: PartialFramePolicy) {
+        self.partial_frame_policy = policy;
+    }
+
+    /// Returns the minimum fraction of valid columns of emitted frames.
+    pub fn min_completeness(&self) -> f64 {
+        self.min_completeness
+    }
+
+    /// Sets the minimum fraction of valid columns, from 0 to 1, of
+    /// emitted frames.
+    pub fn set_min_completeness(&mut self, ratio: f64) {
+        self.min_completeness = ratio.clamp(0.0, 1.0);
+    }
+
+    /// Returns the number of partial frames dropped so far.
+    pub fn dropped_partial_frames(&self) -> usize {
+        self.dropped_partial_frames
+    }
+
+    /// Returns if the column is the last one of a frame in the azimuth
+    /// window.
+    fn is_last_column(&self, measurement_id: u16) -> bool {
+        let last = self
+            .azimuth_window
+            .last_measurement_id(self.columns_per_revolution());
+        measurement_id >= last
+    }
+
+    /// Sets the filter applied to points before they are
+    /// gathered into frames.
+    pub fn set_filter(&mut self, filter: FilterPipeline) {
+        self.pcd_converter.set_filter(filter);
+    }
+
     /// Pushes new [Column] to converter.
     pub fn push_column(&mut self, column: &Column) -> Result<Vec<Frame>> {
+        let points = self.pcd_converter.column_to_points(column)?;
+        self.push_column_points(column, points)
+    }
+
+    /// Pushes a column along with its points computed by the point
+    /// cloud converter.
+    fn push_column_points(
+        &mut self,
+        column: &Column,
+        mut points: Vec<Point>,
+    ) -> Result<Vec<Frame>> {
+        let interpolated;
+        let column = match self.interpolate_timestamp(column) {
+            Some(timestamp) => {
+                interpolated = Column {
+                    timestamp,
+                    ..*column
+                };
+                for point in points.iter_mut() {
+                    point.timestamp = Duration::from_nanos(timestamp);
+                }
+                &interpolated
+            }
+            None => column,
+        };
+
         let curr_fid = column.frame_id;
         let curr_mid = column.measurement_id;
         let curr_ts = column.timestamp;
-        let curr_points = self.pcd_converter.column_to_points(column)?;
+        let curr_points = points;
 
-        // If received column is not valid, update last_{fid,mid} only
+        // If received column is not valid, update last_{fid,mid} and
+        // count it if it is in the window
         if !column.valid() {
-            let (frame_opt, new_state) = match self.state.take() {
+            let in_window = self
+                .azimuth_window
+                .contains(curr_mid, self.columns_per_revolution());
+            let (frame_opt, mut new_state) = match self.state.take() {
                 Some(mut state) => {
                     let frame_opt = match state.last_fid.cmp(&curr_fid) {
-                        Ordering::Less => state.frame.take(),
+                        Ordering::Less => {
+                            state.emitted = false;
+                            state.pending_invalid_columns = 0;
+                            state.frame.take()
+                        }
                         Ordering::Equal => None,
                         Ordering::Greater => {
                             bail!(
@@ -85,17 +425,40 @@ impl FrameConverter {
                     let new_state = FrameConverterState {
                         last_fid: curr_fid,
                         last_mid: curr_mid,
+                        last_frame_id: None,
                         frame: None,
+                        emitted: false,
+                        pending_invalid_columns: 0,
                     };
                     (None, new_state)
                 }
             };
 
+            if in_window {
+                match &mut new_state.frame {
+                    Some(frame) => frame.completeness.invalid_columns += 1,
+                    None => new_state.pending_invalid_columns += 1,
+                }
+            }
+
+            // Produce the frame if the last column of the window is not
+            // valid as well
+            let last_frame_opt = if self.is_last_column(curr_mid) && new_state.frame.is_some() {
+                new_state.emitted = true;
+                new_state.frame.take()
+            } else {
+                None
+            };
+
             self.state = Some(new_state);
-            return Ok(frame_opt.into_iter().collect());
+            return Ok(frame_opt
+                .into_iter()
+                .chain(last_frame_opt)
+                .filter_map(|frame| self.apply_partial_frame_policy(frame))
+                .collect());
         }
 
-        let (new_state, output_frames) = match self.state.take() {
+        let (new_state, output_frames): (_, Vec<Frame>) = match self.state.take() {
             Some(mut state) => {
                 match state.last_fid.cmp(&curr_fid) {
                     Ordering::Less => {
@@ -103,34 +466,34 @@ impl FrameConverter {
                         // Pop out saved frame and conditionally save or output second frame
 
                         let first_frame_opt = state.frame.take();
-                        let second_frame = Frame {
-                            frame_id: curr_fid,
-                            skipped_frame_ids: (state.last_fid + 1)..curr_fid,
-                            timestamps: {
-                                let mut timestamps = Vec::with_capacity(COLUMNS_PER_PACKET);
-                                timestamps.push((curr_mid, curr_ts));
-                                timestamps
-                            },
-                            points: curr_points,
-                        };
+                        let second_frame = self.new_frame(
+                            curr_fid,
+                            state.skipped_frame_ids(curr_fid),
+                            (curr_mid, curr_ts),
+                            curr_points,
+                            0,
+                        );
                         let mut new_state = FrameConverterState {
                             last_mid: curr_mid,
                             last_fid: curr_fid,
+                            last_frame_id: Some(curr_fid),
                             frame: None,
+                            emitted: false,
+                            pending_invalid_columns: 0,
                         };
 
-                        // Produce frame if measurement ID is exactly the latest ID of frame
-                        let (second_frame_opt, new_state) =
-                            if curr_mid + 1 == self.pcd_converter.columns_per_revolution() {
-                                (Some(second_frame), new_state)
-                            } else {
-                                new_state.frame = Some(second_frame);
-                                (None, new_state)
-                            };
+                        // Produce frame if measurement ID is the last one in the window
+                        let (second_frame_opt, new_state) = if self.is_last_column(curr_mid) {
+                            new_state.emitted = true;
+                            (Some(second_frame), new_state)
+                        } else {
+                            new_state.frame = Some(second_frame);
+                            (None, new_state)
+                        };
 
                         let output_frames = first_frame_opt
                             .into_iter()
-                            .chain(second_frame_opt.into_iter())
+                            .chain(second_frame_opt)
                             .collect();
 
                         (new_state, output_frames)
@@ -147,24 +510,41 @@ impl FrameConverter {
                         let mut new_state = FrameConverterState {
                             last_mid: curr_mid,
                             last_fid: curr_fid,
+                            last_frame_id: Some(curr_fid),
                             frame: None,
+                            emitted: state.emitted,
+                            pending_invalid_columns: 0,
                         };
-                        let frame = {
-                            let mut frame = state.frame.take().unwrap_or_else(|| {
-                                unreachable!("Please report bug to upstream");
-                            });
-                            frame.timestamps.push((curr_mid, curr_ts));
-                            frame.points.extend(curr_points);
-                            frame
+                        let frame = match state.frame.take() {
+                            Some(mut frame) => {
+                                frame.timestamps.push((curr_mid, curr_ts));
+                                frame.points.extend(curr_points);
+                                frame.completeness.columns.insert(curr_mid);
+                                frame
+                            }
+                            // Drop columns past the azimuth window after
+                            // the frame is emitted
+                            None if state.emitted => {
+                                self.state = Some(new_state);
+                                return Ok(vec![]);
+                            }
+                            // The frame starts with invalid columns
+                            None => self.new_frame(
+                                curr_fid,
+                                state.skipped_frame_ids(curr_fid),
+                                (curr_mid, curr_ts),
+                                curr_points,
+                                state.pending_invalid_columns,
+                            ),
                         };
 
-                        let (frame_opt, new_state) =
-                            if curr_mid + 1 == self.pcd_converter.columns_per_revolution() {
-                                (Some(frame), new_state)
-                            } else {
-                                new_state.frame = Some(frame);
-                                (None, new_state)
-                            };
+                        let (frame_opt, new_state) = if self.is_last_column(curr_mid) {
+                            new_state.emitted = true;
+                            (Some(frame), new_state)
+                        } else {
+                            new_state.frame = Some(frame);
+                            (None, new_state)
+                        };
 
                         let output_frames = frame_opt.into_iter().collect();
                         (new_state, output_frames)
@@ -178,23 +558,24 @@ impl FrameConverter {
                 }
             }
             None => {
-                let frame = Frame {
-                    frame_id: curr_fid,
-                    skipped_frame_ids: curr_fid..curr_fid,
-                    timestamps: {
-                        let mut timestamps = Vec::with_capacity(COLUMNS_PER_PACKET);
-                        timestamps.push((curr_mid, curr_ts));
-                        timestamps
-                    },
-                    points: curr_points,
-                };
+                let frame = self.new_frame(
+                    curr_fid,
+                    curr_fid..curr_fid,
+                    (curr_mid, curr_ts),
+                    curr_points,
+                    0,
+                );
                 let mut new_state = FrameConverterState {
                     last_mid: curr_mid,
                     last_fid: curr_fid,
+                    last_frame_id: Some(curr_fid),
                     frame: None,
+                    emitted: false,
+                    pending_invalid_columns: 0,
                 };
 
-                let frame_opt = if curr_mid + 1 == self.pcd_converter.columns_per_revolution() {
+                let frame_opt = if self.is_last_column(curr_mid) {
+                    new_state.emitted = true;
                     Some(frame)
                 } else {
                     new_state.frame = Some(frame);
@@ -206,7 +587,109 @@ impl FrameConverter {
         };
 
         self.state = Some(new_state);
-        Ok(output_frames)
+        Ok(output_frames
+            .into_iter()
+            .filter_map(|frame| self.apply_partial_frame_policy(frame))
+            .collect())
+    }
+
+    /// Starts a frame from its first valid column.
+    fn new_frame(
+        &self,
+        frame_id: u16,
+        skipped_frame_ids: Range<u16>,
+        (measurement_id, timestamp): (u16, u64),
+        points: Vec<Point>,
+        invalid_columns: u16,
+    ) -> Frame {
+        let mut completeness =
+            FrameCompleteness::new(self.columns_per_revolution(), self.columns_per_frame());
+        completeness.columns.insert(measurement_id);
+        completeness.invalid_columns = invalid_columns;
+
+        let mut timestamps = Vec::with_capacity(COLUMNS_PER_PACKET);
+        timestamps.push((measurement_id, timestamp));
+        Frame {
+            frame_id,
+            skipped_frame_ids,
+            timestamps,
+            points,
+            completeness,
+        }
+    }
+
+    /// Drops, keeps or fills a frame according to the partial frame
+    /// policy.
+    fn apply_partial_frame_policy(&mut self, mut frame: Frame) -> Option<Frame> {
+        if frame.completeness.is_complete() {
+            return Some(frame);
+        }
+        if frame.completeness.ratio() < self.min_completeness
+            || self.partial_frame_policy == PartialFramePolicy::Drop
+        {
+            self.dropped_partial_frames += 1;
+            return None;
+        }
+        if self.partial_frame_policy == PartialFramePolicy::ZeroFill {
+            self.zero_fill(&mut frame);
+        }
+        Some(frame)
+    }
+
+    /// Adds zero-range points for the columns in the window without
+    /// valid data. Their timestamps are extrapolated from the first
+    /// column of the frame.
+    fn zero_fill(&self, frame: &mut Frame) {
+        let columns = self.columns_per_revolution();
+        // Known as the converter rejects modes of unknown dimensions
+        let column_time = self
+            .lidar_mode()
+            .column_time()
+            .unwrap_or_default()
+            .as_nanos() as i64;
+        let (first_mid, first_ts) = frame.timestamps[0];
+
+        let filled: Vec<u16> = (0..columns)
+            .filter(|&measurement_id| {
+                self.azimuth_window.contains(measurement_id, columns)
+                    && !frame.completeness.columns.contains(measurement_id)
+            })
+            .collect();
+        for &measurement_id in &filled {
+            let offset = (measurement_id as i64 - first_mid as i64) * column_time;
+            let timestamp = Duration::from_nanos(first_ts.saturating_add_signed(offset));
+            frame.points.extend(
+                self.pcd_converter
+                    .empty_column_points(measurement_id, timestamp),
+            );
+        }
+        frame.points.sort_by_key(|point| point.measurement_id);
+        frame.completeness.filled_columns = filled.len() as u16;
+    }
+
+    /// Returns the interpolated timestamp if a valid column misses its
+    /// timestamp, and updates the last timed column otherwise.
+    fn interpolate_timestamp(&mut self, column: &Column) -> Option<u64> {
+        if !column.valid() {
+            return None;
+        }
+        let timestamp = column.timestamp;
+        let encoder_ticks = column.encoder_ticks % ENCODER_TICKS_PER_REV;
+        if timestamp != 0 {
+            self.last_timed_column = Some((timestamp, encoder_ticks));
+            return None;
+        }
+
+        let (last_timestamp, last_encoder_ticks) = self.last_timed_column?;
+        let ticks =
+            (encoder_ticks + ENCODER_TICKS_PER_REV - last_encoder_ticks) % ENCODER_TICKS_PER_REV;
+        let frame_period = self
+            .pcd_converter
+            .lidar_mode()
+            .frame_period()
+            .unwrap_or_default();
+        let elapsed = frame_period.as_nanos() as u64 * ticks as u64 / ENCODER_TICKS_PER_REV as u64;
+        Some(last_timestamp + elapsed)
     }
 
     /// Pushes new [Packet] to converter.
@@ -221,13 +704,88 @@ impl FrameConverter {
         Ok(frames)
     }
 
+    /// Pushes a batch of packets. Points of the columns are computed in
+    /// parallel if the `rayon` feature is enabled, and the frames are
+    /// the same as if the packets were pushed one by one.
+    ///
+    /// Pushing stops at the first rejected packet. The frames
+    /// completed by the packets before it are returned in the
+    /// [BatchError], and the packets after it are not pushed.
+    pub fn push_packets<P>(&mut self, packets: &[P]) -> Result<Vec<Frame>, BatchError>
+    where
+        P: AsRef<Packet> + Sync,
+    {
+        let pcd_converter = &self.pcd_converter;
+        let points = parallel::map_ordered(packets, |packet| {
+            packet
+                .as_ref()
+                .columns
+                .iter()
+                .map(|column| pcd_converter.column_to_points(column))
+                .collect::<Result<Vec<_>>>()
+        });
+
+        let mut frames = vec![];
+        for (index, (packet, points)) in packets.iter().zip(points).enumerate() {
+            let result = points.and_then(|points| {
+                let mut packet_frames = vec![];
+                for (column, points) in packet.as_ref().columns.iter().zip(points) {
+                    packet_frames.extend(self.push_column_points(column, points)?);
+                }
+                Ok(packet_frames)
+            });
+            match result {
+                Ok(packet_frames) => frames.extend(packet_frames),
+                Err(error) => {
+                    return Err(BatchError {
+                        frames,
+                        index,
+                        error,
+                    })
+                }
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Discards the frame in progress, e.g. after a column is rejected,
+    /// so the next column starts a new frame.
+    pub fn reset(&mut self) {
+        self.state = None;
+        self.last_timed_column = None;
+    }
+
     /// Consumes the instance and outputs last maybe
-    /// incomplete frame.
+    /// incomplete frame. The frame is marked
+    /// [truncated](FrameCompleteness::truncated) since its last column
+    /// never arrived, and is subject to the partial frame policy.
     pub fn finish(mut self) -> Option<Frame> {
-        self.state
-            .take()
-            .map(|mut state| state.frame.take())
-            .unwrap_or(None)
+        let mut frame = self.state.take()?.frame.take()?;
+        frame.completeness.truncated = true;
+        self.apply_partial_frame_policy(frame)
+    }
+}
+
+/// Error of [FrameConverter::push_packets] on a rejected packet.
+#[derive(Debug)]
+pub struct BatchError {
+    /// Frames completed by the packets before the rejected one.
+    pub frames: Vec<Frame>,
+    /// Index of the rejected packet in the batch.
+    pub index: usize,
+    /// Why the packet was rejected.
+    pub error: Error,
+}
+
+impl Display for BatchError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        write!(formatter, "packet {} of the batch is rejected", self.index)
+    }
+}
+
+impl std::error::Error for BatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
     }
 }
 
@@ -235,5 +793,23 @@ impl FrameConverter {
 struct FrameConverterState {
     last_mid: u16,
     last_fid: u16,
+    /// Frame ID of the last started frame. Differs from `last_fid`
+    /// while a frame starts with invalid columns.
+    last_frame_id: Option<u16>,
     frame: Option<Frame>,
+    /// Whether the frame of `last_fid` was emitted.
+    emitted: bool,
+    /// Invalid columns in the window before the frame starts.
+    pending_invalid_columns: u16,
+}
+
+impl FrameConverterState {
+    /// Frame IDs between the last started frame and a new frame.
+    fn skipped_frame_ids(&self, frame_id: u16) -> Range<u16> {
+        let start = self
+            .last_frame_id
+            .unwrap_or(self.last_fid)
+            .saturating_add(1);
+        start.min(frame_id)..frame_id
+    }
 }
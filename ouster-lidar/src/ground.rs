@@ -0,0 +1,373 @@
+//! Ground segmentation on organized frames.
+
+use super::{
+    config::Config, consts::PIXELS_PER_COLUMN, frame_converter::Frame, organized::OrganizedIndex,
+    pcd_converter::Point,
+};
+use crate::common::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// A ground plane `normal · p + offset = 0` in meters.
+///
+/// The normal is a unit vector pointing upwards.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GroundModel {
+    pub normal: [f64; 3],
+    pub offset: f64,
+}
+
+impl GroundModel {
+    /// Creates a horizontal plane `sensor_height` meters below the sensor origin.
+    pub fn horizontal(sensor_height: f64) -> Self {
+        Self {
+            normal: [0.0, 0.0, 1.0],
+            offset: sensor_height,
+        }
+    }
+
+    /// Creates a plane passing through three points. It returns `None`
+    /// if the points are collinear.
+    pub fn from_three_points(p1: [f64; 3], p2: [f64; 3], p3: [f64; 3]) -> Option<Self> {
+        let u = [p2[0] - p1[0], p2[1] - p1[1], p2[2] - p1[2]];
+        let v = [p3[0] - p1[0], p3[1] - p1[1], p3[2] - p1[2]];
+        let normal = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        Self::from_normal_and_point(normal, p1)
+    }
+
+    /// Fits the plane `z = a x + b y + c` to points in least squares sense.
+    /// It returns `None` if the fit is degenerated.
+    pub fn fit(points: &[[f64; 3]]) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+
+        let mut sums = [0.0; 9];
+        for [x, y, z] in points.iter().copied() {
+            let terms = [x * x, x * y, x, y * y, y, 1.0, x * z, y * z, z];
+            izip!(sums.iter_mut(), terms.iter()).for_each(|(sum, term)| *sum += term);
+        }
+        let [xx, xy, x, yy, y, n, xz, yz, z] = sums;
+
+        // Solve the normal equations by Cramer's rule
+        let det3 = |m: [[f64; 3]; 3]| {
+            m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+                - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+                + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+        };
+        let matrix = [[xx, xy, x], [xy, yy, y], [x, y, n]];
+        let rhs = [xz, yz, z];
+        let det = det3(matrix);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let solve = |col: usize| {
+            let mut m = matrix;
+            (0..3).for_each(|row| m[row][col] = rhs[row]);
+            det3(m) / det
+        };
+        let (a, b, c) = (solve(0), solve(1), solve(2));
+
+        Self::from_normal_and_point([-a, -b, 1.0], [0.0, 0.0, c])
+    }
+
+    fn from_normal_and_point(normal: [f64; 3], point: [f64; 3]) -> Option<Self> {
+        let norm = normal.iter().map(|v| v * v).sum::<f64>().sqrt();
+        if norm < 1e-12 {
+            return None;
+        }
+        let sign = if normal[2] < 0.0 { -1.0 } else { 1.0 };
+        let normal = normal.map(|v| sign * v / norm);
+        let offset = -izip!(normal.iter(), point.iter())
+            .map(|(n, p)| n * p)
+            .sum::<f64>();
+        Some(Self { normal, offset })
+    }
+
+    /// Returns the signed height of the point above the plane in meters.
+    pub fn height(&self, point: [f64; 3]) -> f64 {
+        izip!(self.normal.iter(), point.iter())
+            .map(|(n, p)| n * p)
+            .sum::<f64>()
+            + self.offset
+    }
+
+    /// Returns the angle between the normal and the Z axis in degrees.
+    pub fn tilt_degrees(&self) -> f64 {
+        self.normal[2].clamp(-1.0, 1.0).acos().to_degrees()
+    }
+}
+
+/// Parameters of the ring-based slope method.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RingSlopeParams {
+    /// Mounting height of sensor origin above ground in meters.
+    pub sensor_height: f64,
+    /// Maximum slope between adjacent ground points in degrees.
+    pub max_slope_degrees: f64,
+    /// Maximum distance in meters from the expected ground height
+    /// for a point to start a ground segment.
+    pub max_height_deviation: f64,
+}
+
+impl Default for RingSlopeParams {
+    fn default() -> Self {
+        Self {
+            sensor_height: 1.8,
+            max_slope_degrees: 10.0,
+            max_height_deviation: 0.3,
+        }
+    }
+}
+
+/// Parameters of the RANSAC plane fit.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RansacParams {
+    pub iterations: usize,
+    /// Maximum distance from the plane in meters for ground points.
+    pub distance_threshold: f64,
+    /// Maximum angle between plane normal and Z axis in degrees.
+    pub max_tilt_degrees: f64,
+    /// Seed of the random sampler.
+    pub seed: u64,
+}
+
+impl Default for RansacParams {
+    fn default() -> Self {
+        Self {
+            iterations: 100,
+            distance_threshold: 0.15,
+            max_tilt_degrees: 15.0,
+            seed: 0,
+        }
+    }
+}
+
+/// The ground segmentation algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GroundMethod {
+    /// Walks every column from the lowest beam upwards and marks
+    /// points as ground while the slope to the previous ground
+    /// point stays small.
+    RingSlope(RingSlopeParams),
+    /// Fits a single plane to the whole frame.
+    Ransac(RansacParams),
+}
+
+/// The result of ground segmentation.
+#[derive(Debug, Clone)]
+pub struct GroundSegmentation {
+    /// Per-point labels in the same order of input points.
+    /// True if the point is on the ground.
+    pub is_ground: Vec<bool>,
+    /// The fitted ground plane.
+    pub model: GroundModel,
+}
+
+impl GroundSegmentation {
+    /// Returns the number of ground points.
+    pub fn num_ground(&self) -> usize {
+        self.is_ground.iter().filter(|ground| **ground).count()
+    }
+
+    /// Returns the indices of ground points.
+    pub fn ground_indices(&self) -> Vec<usize> {
+        self.indices_by_label(true)
+    }
+
+    /// Returns the indices of non-ground points.
+    pub fn non_ground_indices(&self) -> Vec<usize> {
+        self.indices_by_label(false)
+    }
+
+    /// Returns the non-ground subset of the points that
+    /// were segmented.
+    pub fn non_ground_points(&self, points: &[Point]) -> Vec<Point> {
+        izip!(points.iter(), self.is_ground.iter())
+            .filter(|(_, ground)| !**ground)
+            .map(|(point, _)| point.clone())
+            .collect()
+    }
+
+    fn indices_by_label(&self, label: bool) -> Vec<usize> {
+        self.is_ground
+            .iter()
+            .enumerate()
+            .filter(|(_, ground)| **ground == label)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// Splits frame points into ground and non-ground.
+#[derive(Debug, Clone)]
+pub struct GroundSegmenter {
+    /// Laser IDs sorted by ascending altitude angle.
+    row_order: Vec<usize>,
+    /// Altitude angles in degrees, indexed by laser ID.
+    altitude_degrees: [f64; PIXELS_PER_COLUMN],
+    columns_per_revolution: u16,
+    method: GroundMethod,
+}
+
+impl GroundSegmenter {
+    /// Creates a segmenter for the sensor config. It fails if the lidar
+    /// mode has unknown dimensions.
+    pub fn new(config: &Config, method: GroundMethod) -> Result<Self> {
+        let altitude_degrees = config.beam_altitude_angles.map(|angle| angle.raw());
+        let mut row_order: Vec<usize> = (0..PIXELS_PER_COLUMN).collect();
+        row_order.sort_by_key(|&row| R64::new(altitude_degrees[row]));
+
+        Ok(Self {
+            row_order,
+            altitude_degrees,
+            columns_per_revolution: config.lidar_mode.checked_columns_per_revolution()?,
+            method,
+        })
+    }
+
+    /// Segments the points of a frame.
+    pub fn segment(&self, frame: &Frame) -> GroundSegmentation {
+        self.segment_points(&frame.points)
+    }
+
+    /// Segments points produced by the converters. Points are
+    /// located in the scan by their laser and measurement IDs.
+    pub fn segment_points(&self, points: &[Point]) -> GroundSegmentation {
+        let positions: Vec<[f64; 3]> = points
+            .iter()
+            .map(|point| point.point.map(|v| v.as_meters()))
+            .collect();
+        let has_return: Vec<bool> = points
+            .iter()
+            .map(|point| point.distance.as_meters() > 0.0)
+            .collect();
+
+        match self.method {
+            GroundMethod::RingSlope(params) => {
+                self.segment_ring_slope(points, &positions, &has_return, &params)
+            }
+            GroundMethod::Ransac(params) => segment_ransac(&positions, &has_return, &params),
+        }
+    }
+
+    fn segment_ring_slope(
+        &self,
+        points: &[Point],
+        positions: &[[f64; 3]],
+        has_return: &[bool],
+        params: &RingSlopeParams,
+    ) -> GroundSegmentation {
+        let index = OrganizedIndex::new(
+            points,
+            self.columns_per_revolution as usize,
+            PIXELS_PER_COLUMN,
+        );
+        let mut is_ground = vec![false; points.len()];
+
+        for col in 0..index.width() {
+            let mut prev: Option<([f64; 3], bool)> = None;
+
+            for &row in self.row_order.iter() {
+                // Beams pointing above the steepest allowed slope never hit ground
+                if self.altitude_degrees[row] > params.max_slope_degrees {
+                    break;
+                }
+                let point_index = match index.get(row, col) {
+                    Some(point_index) if has_return[point_index] => point_index,
+                    _ => continue,
+                };
+
+                let position = positions[point_index];
+                let near_expected_height =
+                    (position[2] + params.sensor_height).abs() <= params.max_height_deviation;
+                let ground = match prev {
+                    Some((prev_position, true)) => {
+                        let prev_range = prev_position[0].hypot(prev_position[1]);
+                        let range = position[0].hypot(position[1]);
+                        let slope = (position[2] - prev_position[2])
+                            .atan2((range - prev_range).abs())
+                            .to_degrees();
+                        slope.abs() <= params.max_slope_degrees
+                    }
+                    _ => near_expected_height,
+                };
+
+                is_ground[point_index] = ground;
+                prev = Some((position, ground));
+            }
+        }
+
+        let ground_positions: Vec<_> = izip!(positions.iter(), is_ground.iter())
+            .filter(|(_, ground)| **ground)
+            .map(|(position, _)| *position)
+            .collect();
+        let model = GroundModel::fit(&ground_positions)
+            .unwrap_or_else(|| GroundModel::horizontal(params.sensor_height));
+
+        GroundSegmentation { is_ground, model }
+    }
+}
+
+fn segment_ransac(
+    positions: &[[f64; 3]],
+    has_return: &[bool],
+    params: &RansacParams,
+) -> GroundSegmentation {
+    let candidates: Vec<usize> = (0..positions.len())
+        .filter(|&index| has_return[index])
+        .collect();
+    let count_inliers = |model: &GroundModel| {
+        candidates
+            .iter()
+            .filter(|&&index| model.height(positions[index]).abs() <= params.distance_threshold)
+            .count()
+    };
+
+    let mut best: Option<(GroundModel, usize)> = None;
+    if candidates.len() >= 3 {
+        let mut rng = StdRng::seed_from_u64(params.seed);
+        for _ in 0..params.iterations {
+            let [p1, p2, p3] =
+                [(); 3].map(|_| positions[candidates[rng.gen_range(0..candidates.len())]]);
+            let model = match GroundModel::from_three_points(p1, p2, p3) {
+                Some(model) if model.tilt_degrees() <= params.max_tilt_degrees => model,
+                _ => continue,
+            };
+            let num_inliers = count_inliers(&model);
+            if best.is_none_or(|(_, best_inliers)| num_inliers > best_inliers) {
+                best = Some((model, num_inliers));
+            }
+        }
+    }
+
+    let model = match best {
+        Some((model, _)) => {
+            // Refine the plane on inliers
+            let inliers: Vec<_> = candidates
+                .iter()
+                .map(|&index| positions[index])
+                .filter(|&position| model.height(position).abs() <= params.distance_threshold)
+                .collect();
+            GroundModel::fit(&inliers)
+                .filter(|refined| refined.tilt_degrees() <= params.max_tilt_degrees)
+                .unwrap_or(model)
+        }
+        None => {
+            return GroundSegmentation {
+                is_ground: vec![false; positions.len()],
+                model: GroundModel::horizontal(0.0),
+            }
+        }
+    };
+
+    let is_ground = izip!(positions.iter(), has_return.iter())
+        .map(|(position, has_return)| {
+            *has_return && model.height(*position).abs() <= params.distance_threshold
+        })
+        .collect();
+    GroundSegmentation { is_ground, model }
+}
@@ -0,0 +1,527 @@
+//! Health monitoring of packet streams.
+//!
+//! [HealthMonitor] watches lidar packets as they arrive and collects
+//! loss, timing and ordering metrics over fixed windows of host time.
+//! Each completed window produces a [HealthSnapshot], which is checked
+//! against [HealthThresholds] to raise [HealthAlarm]s.
+
+use super::{
+    config::{AzimuthWindow, Config},
+    consts::ENCODER_TICKS_PER_REV,
+    packet::Packet,
+};
+use crate::common::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Limits that raise alarms when exceeded. Unset limits are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthThresholds {
+    /// Minimum packets per second.
+    pub min_packet_rate: Option<f64>,
+    /// Minimum frames per second.
+    pub min_frame_rate: Option<f64>,
+    /// Maximum missing columns in a single frame.
+    pub max_missing_columns: Option<usize>,
+    /// Maximum invalid columns in a window.
+    pub max_invalid_columns: Option<usize>,
+    /// Maximum standard deviation of column intervals.
+    pub max_jitter: Option<Duration>,
+    /// Maximum mean sensor-to-host latency.
+    pub max_latency: Option<Duration>,
+    /// Maximum out-of-order columns in a window.
+    pub max_out_of_order: Option<usize>,
+    /// Maximum encoder tick discontinuities in a window.
+    pub max_encoder_discontinuities: Option<usize>,
+}
+
+/// Configuration of [HealthMonitor].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthConfig {
+    /// Length of a statistics window in host time.
+    pub window: Duration,
+    pub thresholds: HealthThresholds,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(1),
+            thresholds: HealthThresholds::default(),
+        }
+    }
+}
+
+/// Stream statistics over a window.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HealthSnapshot {
+    /// Host time span of received packets.
+    pub elapsed: Duration,
+    pub packets: usize,
+    /// Frames completed in the window.
+    pub frames: usize,
+    pub packet_rate: f64,
+    pub frame_rate: f64,
+    /// Frame IDs skipped entirely.
+    pub dropped_frames: usize,
+    /// Missing columns in the azimuth window summed over completed
+    /// frames.
+    pub missing_columns: usize,
+    /// The most missing columns in a completed frame.
+    pub max_missing_columns: usize,
+    /// Columns in the azimuth window not marked valid by the sensor.
+    pub invalid_columns: usize,
+    /// Standard deviation of intervals between consecutive columns.
+    pub jitter: Duration,
+    /// Mean host receive time minus sensor timestamp in nanoseconds.
+    /// It is negative if the sensor clock is ahead of the host.
+    pub mean_latency_ns: i64,
+    /// Maximum host receive time minus sensor timestamp in nanoseconds.
+    pub max_latency_ns: i64,
+    /// Columns that arrive after a later column.
+    pub out_of_order: usize,
+    /// Consecutive columns whose encoder ticks do not advance by one
+    /// column.
+    pub encoder_discontinuities: usize,
+}
+
+impl HealthSnapshot {
+    /// Checks the snapshot against thresholds.
+    pub fn alarms(&self, thresholds: &HealthThresholds) -> Vec<HealthAlarm> {
+        let mut alarms = vec![];
+        if let Some(min) = thresholds.min_packet_rate {
+            if self.packet_rate < min {
+                alarms.push(HealthAlarm::LowPacketRate {
+                    rate: self.packet_rate,
+                    min,
+                });
+            }
+        }
+        if let Some(min) = thresholds.min_frame_rate {
+            if self.frame_rate < min {
+                alarms.push(HealthAlarm::LowFrameRate {
+                    rate: self.frame_rate,
+                    min,
+                });
+            }
+        }
+        if let Some(max) = thresholds.max_missing_columns {
+            if self.max_missing_columns > max {
+                alarms.push(HealthAlarm::MissingColumns {
+                    count: self.max_missing_columns,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = thresholds.max_invalid_columns {
+            if self.invalid_columns > max {
+                alarms.push(HealthAlarm::InvalidColumns {
+                    count: self.invalid_columns,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = thresholds.max_jitter {
+            if self.jitter > max {
+                alarms.push(HealthAlarm::HighJitter {
+                    jitter: self.jitter,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = thresholds.max_latency {
+            if self.mean_latency_ns > i64::try_from(max.as_nanos()).unwrap_or(i64::MAX) {
+                alarms.push(HealthAlarm::HighLatency {
+                    latency_ns: self.mean_latency_ns,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = thresholds.max_out_of_order {
+            if self.out_of_order > max {
+                alarms.push(HealthAlarm::OutOfOrder {
+                    count: self.out_of_order,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = thresholds.max_encoder_discontinuities {
+            if self.encoder_discontinuities > max {
+                alarms.push(HealthAlarm::EncoderDiscontinuities {
+                    count: self.encoder_discontinuities,
+                    max,
+                });
+            }
+        }
+        alarms
+    }
+
+    fn add_counts(&mut self, other: &HealthSnapshot) {
+        self.elapsed += other.elapsed;
+        self.packets += other.packets;
+        self.frames += other.frames;
+        self.dropped_frames += other.dropped_frames;
+        self.missing_columns += other.missing_columns;
+        self.max_missing_columns = self.max_missing_columns.max(other.max_missing_columns);
+        self.invalid_columns += other.invalid_columns;
+        self.out_of_order += other.out_of_order;
+        self.encoder_discontinuities += other.encoder_discontinuities;
+    }
+}
+
+/// A threshold exceeded in a window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HealthAlarm {
+    LowPacketRate { rate: f64, min: f64 },
+    LowFrameRate { rate: f64, min: f64 },
+    MissingColumns { count: usize, max: usize },
+    InvalidColumns { count: usize, max: usize },
+    HighJitter { jitter: Duration, max: Duration },
+    HighLatency { latency_ns: i64, max: Duration },
+    OutOfOrder { count: usize, max: usize },
+    EncoderDiscontinuities { count: usize, max: usize },
+}
+
+impl Display for HealthAlarm {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        use HealthAlarm::*;
+        match self {
+            LowPacketRate { rate, min } => {
+                write!(formatter, "packet rate {:.1}/s is below {:.1}/s", rate, min)
+            }
+            LowFrameRate { rate, min } => {
+                write!(formatter, "frame rate {:.1}/s is below {:.1}/s", rate, min)
+            }
+            MissingColumns { count, max } => {
+                write!(
+                    formatter,
+                    "{} missing columns in a frame exceed {}",
+                    count, max
+                )
+            }
+            InvalidColumns { count, max } => {
+                write!(formatter, "{} invalid columns exceed {}", count, max)
+            }
+            HighJitter { jitter, max } => {
+                write!(formatter, "jitter {:?} exceeds {:?}", jitter, max)
+            }
+            HighLatency { latency_ns, max } => write!(
+                formatter,
+                "latency {:?} exceeds {:?}",
+                Duration::from_nanos(*latency_ns as u64),
+                max
+            ),
+            OutOfOrder { count, max } => {
+                write!(formatter, "{} out-of-order columns exceed {}", count, max)
+            }
+            EncoderDiscontinuities { count, max } => write!(
+                formatter,
+                "{} encoder tick discontinuities exceed {}",
+                count, max
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LastColumn {
+    frame_id: u16,
+    measurement_id: u16,
+    timestamp: u64,
+    encoder_ticks: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Window {
+    start: Option<u64>,
+    last: u64,
+    snapshot: HealthSnapshot,
+    num_intervals: usize,
+    interval_sum: f64,
+    interval_square_sum: f64,
+    num_latencies: usize,
+    latency_sum: i128,
+}
+
+impl Window {
+    fn finish(&self) -> HealthSnapshot {
+        let mut snapshot = self.snapshot.clone();
+        let elapsed = self.last.saturating_sub(self.start.unwrap_or(self.last));
+        snapshot.elapsed = Duration::from_nanos(elapsed);
+        if elapsed > 0 {
+            let secs = elapsed as f64 / 1e9;
+            snapshot.packet_rate = snapshot.packets as f64 / secs;
+            snapshot.frame_rate = snapshot.frames as f64 / secs;
+        }
+        if self.num_intervals > 0 {
+            let count = self.num_intervals as f64;
+            let mean = self.interval_sum / count;
+            let variance = (self.interval_square_sum / count - mean * mean).max(0.0);
+            snapshot.jitter = Duration::from_nanos(variance.sqrt().round() as u64);
+        }
+        if self.num_latencies > 0 {
+            snapshot.mean_latency_ns = (self.latency_sum / self.num_latencies as i128) as i64;
+        }
+        snapshot
+    }
+}
+
+/// Watches a lidar packet stream and reports its health.
+#[derive(Debug, Clone)]
+pub struct HealthMonitor {
+    columns_per_revolution: u16,
+    azimuth_window: AzimuthWindow,
+    config: HealthConfig,
+    window: Window,
+    last_column: Option<LastColumn>,
+    current_frame: Option<u16>,
+    frame_columns: usize,
+    latest: Option<HealthSnapshot>,
+    totals: HealthSnapshot,
+}
+
+impl HealthMonitor {
+    /// Creates a monitor for the lidar mode and azimuth window in
+    /// sensor config. It fails if the lidar mode has unknown
+    /// dimensions.
+    pub fn new(config: &Config, health_config: HealthConfig) -> Result<Self> {
+        let columns = config.lidar_mode.checked_columns_per_revolution()?;
+        let mut monitor = Self::with_columns(columns, health_config)?;
+        monitor.set_azimuth_window(config.azimuth_window);
+        Ok(monitor)
+    }
+
+    /// Creates a monitor for `columns_per_revolution` columns over the
+    /// full circle.
+    pub fn with_columns(columns_per_revolution: u16, config: HealthConfig) -> Result<Self> {
+        ensure!(
+            columns_per_revolution > 0,
+            "the number of columns per revolution must be positive"
+        );
+        Ok(Self {
+            columns_per_revolution,
+            azimuth_window: AzimuthWindow::full(),
+            config,
+            window: Window::default(),
+            last_column: None,
+            current_frame: None,
+            frame_columns: 0,
+            latest: None,
+            totals: HealthSnapshot::default(),
+        })
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &HealthConfig {
+        &self.config
+    }
+
+    /// Pushes a packet received now.
+    pub fn push_packet(&mut self, packet: &Packet) -> Vec<HealthAlarm> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+        self.push_packet_at(packet, now)
+    }
+
+    /// Pushes a packet received at Unix time `host_time` in
+    /// nanoseconds. It returns alarms of the window that the packet
+    /// completes, if any.
+    pub fn push_packet_at(&mut self, packet: &Packet, host_time: u64) -> Vec<HealthAlarm> {
+        let mut alarms = vec![];
+        if self.window_elapsed(host_time) {
+            alarms = self.close_window();
+        }
+        self.window.start.get_or_insert(host_time);
+        self.window.last = host_time;
+        self.window.snapshot.packets += 1;
+
+        let mut last_timestamp = None;
+        for column in packet.columns.iter() {
+            if !column.valid() {
+                if self
+                    .azimuth_window
+                    .contains(column.measurement_id, self.columns_per_revolution)
+                {
+                    self.window.snapshot.invalid_columns += 1;
+                }
+                continue;
+            }
+            self.push_column(LastColumn {
+                frame_id: column.frame_id,
+                measurement_id: column.measurement_id,
+                timestamp: column.timestamp,
+                encoder_ticks: column.encoder_ticks,
+            });
+            last_timestamp = Some(column.timestamp);
+        }
+
+        if let Some(timestamp) = last_timestamp {
+            let latency = host_time as i64 - timestamp as i64;
+            let snapshot = &mut self.window.snapshot;
+            snapshot.max_latency_ns = if self.window.num_latencies == 0 {
+                latency
+            } else {
+                snapshot.max_latency_ns.max(latency)
+            };
+            self.window.num_latencies += 1;
+            self.window.latency_sum += latency as i128;
+        }
+        alarms
+    }
+
+    /// Checks the stream now without a packet. See [Self::poll_at].
+    pub fn poll(&mut self) -> Vec<HealthAlarm> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+        self.poll_at(now)
+    }
+
+    /// Checks the stream at Unix time `host_time` in nanoseconds, e.g.
+    /// when receiving a packet times out. It closes the window if it
+    /// has elapsed and returns its alarms, so that a stream that stops
+    /// completely still raises rate alarms. A window without packets
+    /// starts at the first poll.
+    pub fn poll_at(&mut self, host_time: u64) -> Vec<HealthAlarm> {
+        self.window.start.get_or_insert(host_time);
+        if !self.window_elapsed(host_time) {
+            return vec![];
+        }
+        self.window.last = host_time;
+        let alarms = self.close_window();
+        self.window.start = Some(host_time);
+        self.window.last = host_time;
+        alarms
+    }
+
+    /// Returns statistics of the ongoing window.
+    pub fn snapshot(&self) -> HealthSnapshot {
+        self.window.finish()
+    }
+
+    /// Returns statistics of the last completed window.
+    pub fn latest(&self) -> Option<&HealthSnapshot> {
+        self.latest.as_ref()
+    }
+
+    /// Returns counts since the monitor was created. Rates, jitter and
+    /// latencies are left zero.
+    pub fn totals(&self) -> HealthSnapshot {
+        let mut totals = self.totals.clone();
+        totals.add_counts(&self.window.snapshot);
+        totals
+    }
+
+    /// Returns the number of columns in a revolution.
+    pub fn columns_per_revolution(&self) -> u16 {
+        self.columns_per_revolution
+    }
+
+    /// Returns the azimuth window.
+    pub fn azimuth_window(&self) -> AzimuthWindow {
+        self.azimuth_window
+    }
+
+    /// Sets the azimuth window, e.g. after changing it on the sensor.
+    /// Only columns in the window are expected in frames.
+    pub fn set_azimuth_window(&mut self, azimuth_window: AzimuthWindow) {
+        self.azimuth_window = azimuth_window;
+    }
+
+    /// Returns the number of columns of a complete frame in the
+    /// azimuth window.
+    pub fn columns_per_frame(&self) -> u16 {
+        self.azimuth_window.num_columns(self.columns_per_revolution)
+    }
+
+    /// Returns alarms of the ongoing window.
+    pub fn alarms(&self) -> Vec<HealthAlarm> {
+        self.snapshot().alarms(&self.config.thresholds)
+    }
+
+    fn window_elapsed(&self, host_time: u64) -> bool {
+        let window = u64::try_from(self.config.window.as_nanos()).unwrap_or(u64::MAX);
+        self.window
+            .start
+            .is_some_and(|start| host_time.saturating_sub(start) >= window)
+    }
+
+    /// Makes the ongoing window the latest one and returns its alarms.
+    fn close_window(&mut self) -> Vec<HealthAlarm> {
+        let snapshot = self.window.finish();
+        let alarms = snapshot.alarms(&self.config.thresholds);
+        self.totals.add_counts(&snapshot);
+        self.latest = Some(snapshot);
+        self.window = Window::default();
+        alarms
+    }
+
+    fn push_column(&mut self, column: LastColumn) {
+        let columns_per_revolution = self.columns_per_revolution as usize;
+        let columns_per_frame = self.columns_per_frame() as usize;
+        let snapshot = &mut self.window.snapshot;
+
+        // Frame boundaries
+        match self.current_frame {
+            Some(frame_id) if frame_id == column.frame_id => {}
+            Some(frame_id) => {
+                let diff = column.frame_id.wrapping_sub(frame_id);
+                if diff >= 0x8000 {
+                    // A column of an earlier frame
+                    snapshot.out_of_order += 1;
+                    return;
+                }
+                let missing = columns_per_frame.saturating_sub(self.frame_columns);
+                snapshot.frames += 1;
+                snapshot.dropped_frames += diff as usize - 1;
+                snapshot.missing_columns += missing;
+                snapshot.max_missing_columns = snapshot.max_missing_columns.max(missing);
+                self.current_frame = Some(column.frame_id);
+                self.frame_columns = 0;
+            }
+            None => {
+                self.current_frame = Some(column.frame_id);
+            }
+        }
+
+        // Ordering, timing and encoder continuity
+        if let Some(last) = self.last_column {
+            let same_frame = last.frame_id == column.frame_id;
+            if same_frame && column.measurement_id <= last.measurement_id {
+                snapshot.out_of_order += 1;
+                return;
+            }
+
+            let consecutive = (same_frame && column.measurement_id == last.measurement_id + 1)
+                || (column.frame_id == last.frame_id.wrapping_add(1)
+                    && column.measurement_id == 0
+                    && last.measurement_id as usize + 1 == columns_per_revolution);
+            if consecutive {
+                if column.timestamp > last.timestamp {
+                    let interval = (column.timestamp - last.timestamp) as f64;
+                    self.window.num_intervals += 1;
+                    self.window.interval_sum += interval;
+                    self.window.interval_square_sum += interval * interval;
+                }
+
+                let expected = ENCODER_TICKS_PER_REV / self.columns_per_revolution as u32;
+                let delta = (column.encoder_ticks + ENCODER_TICKS_PER_REV - last.encoder_ticks)
+                    % ENCODER_TICKS_PER_REV;
+                if delta.abs_diff(expected) > expected / 2 {
+                    snapshot.encoder_discontinuities += 1;
+                }
+            }
+        }
+
+        if self
+            .azimuth_window
+            .contains(column.measurement_id, self.columns_per_revolution)
+        {
+            self.frame_columns += 1;
+        }
+        self.last_column = Some(column);
+    }
+}
@@ -1,18 +1,68 @@
 //! Ouster packet format types, configs and converters.
 
+#[cfg(feature = "parquet")]
+pub mod arrow_export;
+#[cfg(feature = "mcap")]
+pub mod bag;
+pub mod capture;
 pub mod client;
+pub mod cluster;
 mod common;
 pub mod config;
 pub mod consts;
+#[cfg(feature = "dds")]
+pub mod dds;
+pub mod downsample;
 pub mod enums;
+pub mod export;
+pub mod filter;
 pub mod frame_converter;
+pub mod ground;
+pub mod health;
+pub mod metrics;
+pub mod organized;
 pub mod packet;
+pub mod parallel;
 pub mod pcd_converter;
+pub mod point_buffer;
+pub mod point_xyzirt;
+pub mod rig;
+#[cfg(feature = "dds")]
+pub mod ros2;
+pub mod sensor;
+pub mod spatial;
+pub mod time_sync;
 mod utils;
+pub mod xyz_lut;
 
+#[cfg(feature = "parquet")]
+pub use arrow_export::*;
+#[cfg(feature = "mcap")]
+pub use bag::*;
+pub use capture::*;
 pub use client::*;
+pub use cluster::*;
 pub use config::*;
+#[cfg(feature = "dds")]
+pub use dds::*;
+pub use downsample::*;
 pub use enums::*;
+pub use export::*;
+pub use filter::*;
 pub use frame_converter::*;
+pub use ground::*;
+pub use health::*;
+pub use metrics::*;
+pub use organized::*;
 pub use packet::*;
+pub use parallel::*;
 pub use pcd_converter::*;
+pub use point_buffer::*;
+pub use point_xyzirt::*;
+pub use rig::*;
+#[cfg(feature = "dds")]
+pub use ros2::*;
+pub use sensor::*;
+pub use spatial::*;
+pub use time_sync::*;
+pub use xyz_lut::*;
@@ -1,3 +1,658 @@
-fn main() {
-    println!("Ouster Lidar Data extraction, run the publisher/subscriber by specfying its bin name");
-}
\ No newline at end of file
+//! The `ouster` command line tool for sensors and recordings.
+
+#[path = "../applications/publish.rs"]
+mod publish;
+
+use std::{
+    fmt, mem,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+
+use publish::PublishArgs;
+
+use ouster_lidar::{
+    client::CommandClient, parallel, save_points, BagMessage, BagReader, BagWriter, BeamIntrinsics,
+    CapturedPacket, Config, ConfigText, Frame, FrameConverter, HealthConfig, HealthMonitor,
+    ImuIntrinsics, LidarIntrinsics, LidarMode, Packet, PartialFramePolicy, PcapReader, PcapWriter,
+    PointCloudFormat, Sensor, SensorConfig, TimeInfo, TimeSyncStatus, UdpDatagram,
+    PACKETS_PER_BATCH,
+};
+
+/// Exit code of runtime failures.
+const EXIT_FAILURE: u8 = 1;
+/// Exit code of invalid arguments, the same as clap uses.
+const EXIT_USAGE: u8 = 2;
+
+/// Tools for Ouster sensors and their recordings.
+#[derive(Debug, Parser)]
+#[command(name = "ouster", version)]
+struct Cli {
+    /// TOML file with sensor connection settings, see `SensorConfig`.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// IP address of the sensor. Overrides the config file.
+    #[arg(long, global = true)]
+    lidar_addr: Option<Ipv4Addr>,
+    /// IP address of this host where the sensor sends UDP packets to.
+    /// Overrides the config file.
+    #[arg(long, global = true)]
+    listen_addr: Option<Ipv4Addr>,
+    /// Timeout of sensor commands in seconds. Overrides the config file.
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
+    /// Lidar mode set on the sensor. Overrides the config file.
+    #[arg(long, global = true)]
+    lidar_mode: Option<LidarMode>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Prints sensor metadata as JSON.
+    Info,
+    /// Reads or changes the sensor configuration.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Records packets from the sensor to an `.mcap` or `.pcap` file.
+    Record {
+        /// Output file. The format is chosen by the extension.
+        output: PathBuf,
+        /// Stop after this many seconds instead of on Ctrl-C.
+        #[arg(long)]
+        duration: Option<f64>,
+        /// Record IMU packets as well.
+        #[arg(long)]
+        imu: bool,
+    },
+    /// Sends recorded lidar packets over UDP at their original rate.
+    Replay {
+        /// An `.mcap` or `.pcap` recording.
+        input: PathBuf,
+        /// Where packets are sent to.
+        #[arg(long, default_value = "127.0.0.1:7502")]
+        destination: SocketAddr,
+        /// Playback speed factor. Zero sends packets as fast as possible.
+        #[arg(long, default_value_t = 1.0)]
+        rate: f64,
+    },
+    /// Converts recorded packets to one point cloud file per frame.
+    Convert {
+        /// An `.mcap` or `.pcap` recording.
+        input: PathBuf,
+        /// Directory where frames are written to.
+        #[arg(long)]
+        output_dir: PathBuf,
+        /// Format of written frames, `pcd`, `ply` or `las`.
+        #[arg(long, default_value = "pcd")]
+        format: PointCloudFormat,
+        /// Sensor config JSON with beam intrinsics and lidar mode.
+        /// Defaults to the config embedded in MCAP files, or the OS-1
+        /// config.
+        #[arg(long)]
+        intrinsics: Option<PathBuf>,
+        /// What to do with frames that miss columns, `emit`, `drop` or
+        /// `zero-fill`.
+        #[arg(long, default_value = "emit")]
+        partial_frames: PartialFramePolicy,
+        /// Minimum fraction of valid columns of written frames.
+        #[arg(long, default_value = "0")]
+        min_completeness: f64,
+    },
+    /// Writes the frames of a recording to Parquet files partitioned
+    /// by frame, with the sensor config in the file metadata.
+    #[cfg(feature = "parquet")]
+    Parquet {
+        /// An `.mcap` or `.pcap` recording.
+        input: PathBuf,
+        /// Directory of the dataset.
+        #[arg(long)]
+        output_dir: PathBuf,
+        /// Sensor config JSON with beam intrinsics and lidar mode.
+        #[arg(long)]
+        intrinsics: Option<PathBuf>,
+    },
+    /// Prints packet and frame statistics of a recording.
+    Stats {
+        /// An `.mcap` or `.pcap` recording.
+        input: PathBuf,
+        /// Sensor config JSON with beam intrinsics and lidar mode.
+        #[arg(long)]
+        intrinsics: Option<PathBuf>,
+    },
+    /// Streams packets and frames from the sensor to DDS.
+    Publish(PublishArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Prints the configuration as JSON, or the value of a parameter.
+    Get {
+        /// Parameter name, e.g. `lidar_mode`.
+        param: Option<String>,
+    },
+    /// Sets a parameter and reinitializes the sensor.
+    Set {
+        /// Parameter name, e.g. `lidar_mode`.
+        param: String,
+        /// New value, e.g. `1024x10`.
+        value: String,
+        /// Also write the configuration to the sensor flash.
+        #[arg(long)]
+        persist: bool,
+    },
+}
+
+/// An error caused by missing or invalid arguments.
+#[derive(Debug)]
+struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+#[derive(Debug, Serialize)]
+struct SensorInfo {
+    config_txt: ConfigText,
+    time_info: TimeInfo,
+    time_sync: TimeSyncStatus,
+    beam_intrinsics: BeamIntrinsics,
+    lidar_intrinsics: LidarIntrinsics,
+    imu_intrinsics: ImuIntrinsics,
+}
+
+/// A recording in either supported format.
+enum Recording {
+    Bag(BagReader),
+    Pcap(PathBuf),
+}
+
+impl Recording {
+    fn open(path: &Path) -> Result<Self> {
+        match extension(path).as_str() {
+            "mcap" => Ok(Self::Bag(BagReader::open(path)?)),
+            "pcap" => Ok(Self::Pcap(path.to_path_buf())),
+            _ => {
+                Err(UsageError(format!("{} is not an .mcap or .pcap file", path.display())).into())
+            }
+        }
+    }
+
+    /// Returns the converter config from the intrinsics file, the
+    /// recording, or the OS-1 defaults in this order.
+    fn config(&self, intrinsics: Option<&Path>) -> Result<Config> {
+        if let Some(path) = intrinsics {
+            return Config::from_path(path);
+        }
+        if let Self::Bag(reader) = self {
+            if let Some(config) = reader.config()? {
+                return Ok(config);
+            }
+        }
+        eprintln!("warning: no sensor config is given, using the OS-1 config");
+        Ok(Config::os_1_config())
+    }
+
+    /// Calls `f` on every lidar packet with its recording time in nanoseconds.
+    fn for_each_packet<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(u64, &Packet) -> Result<()>,
+    {
+        match self {
+            Self::Bag(reader) => {
+                for message in reader.messages()? {
+                    if let (timestamp, BagMessage::LidarPacket(packet)) = message? {
+                        f(timestamp, &packet)?;
+                    }
+                }
+            }
+            Self::Pcap(path) => {
+                for datagram in PcapReader::open(path)? {
+                    let datagram = datagram?;
+                    if let Some(CapturedPacket::Lidar(packet)) = datagram.to_packet() {
+                        f(datagram.timestamp, &packet)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+fn unix_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// Returns a flag that turns false on Ctrl-C.
+fn running_flag() -> Result<Arc<AtomicBool>> {
+    let running = Arc::new(AtomicBool::new(true));
+    let flag = running.clone();
+    ctrlc::set_handler(move || flag.store(false, Ordering::SeqCst))?;
+    Ok(running)
+}
+
+impl Cli {
+    /// Merges the config file with address flags.
+    fn sensor_config(&self, require_listen_addr: bool) -> Result<SensorConfig> {
+        let file = self
+            .config
+            .as_ref()
+            .map(SensorConfig::from_path)
+            .transpose()?;
+        let lidar_addr = self
+            .lidar_addr
+            .or_else(|| file.as_ref().map(|config| config.lidar_addr))
+            .ok_or_else(|| UsageError("the sensor address is not given".into()))?;
+        let listen_addr = match self
+            .listen_addr
+            .or_else(|| file.as_ref().map(|config| config.listen_addr))
+        {
+            Some(addr) => addr,
+            None if require_listen_addr => {
+                return Err(UsageError("the listen address is not given".into()).into())
+            }
+            None => Ipv4Addr::UNSPECIFIED,
+        };
+
+        let mut config = file.unwrap_or_else(|| SensorConfig::new(lidar_addr, listen_addr));
+        config.lidar_addr = lidar_addr;
+        config.listen_addr = listen_addr;
+        if let Some(timeout) = self.timeout {
+            config.timeout = timeout;
+        }
+        if self.lidar_mode.is_some() {
+            config.lidar_mode = self.lidar_mode.clone();
+        }
+        Ok(config)
+    }
+
+    fn client(&self) -> Result<CommandClient> {
+        self.sensor_config(false)?.connect_client()
+    }
+
+    fn run(&self) -> Result<()> {
+        match &self.command {
+            Command::Info => self.info(),
+            Command::Config { command } => self.config(command),
+            Command::Record {
+                output,
+                duration,
+                imu,
+            } => self.record(output, *duration, *imu),
+            Command::Replay {
+                input,
+                destination,
+                rate,
+            } => replay(input, *destination, *rate),
+            Command::Convert {
+                input,
+                output_dir,
+                format,
+                intrinsics,
+                partial_frames,
+                min_completeness,
+            } => convert(
+                input,
+                output_dir,
+                *format,
+                intrinsics.as_deref(),
+                *partial_frames,
+                *min_completeness,
+            ),
+            #[cfg(feature = "parquet")]
+            Command::Parquet {
+                input,
+                output_dir,
+                intrinsics,
+            } => parquet(input, output_dir, intrinsics.as_deref()),
+            Command::Stats { input, intrinsics } => stats(input, intrinsics.as_deref()),
+            Command::Publish(args) => publish::run(&self.sensor_config(true)?, args),
+        }
+    }
+
+    fn info(&self) -> Result<()> {
+        let mut client = self.client()?;
+        let time_info = client.get_time_info()?;
+        let info = SensorInfo {
+            config_txt: client.get_config_txt()?,
+            time_sync: TimeSyncStatus::from_time_info(&time_info),
+            time_info,
+            beam_intrinsics: client.get_beam_intrinsics()?,
+            lidar_intrinsics: client.get_lidar_intrinsics()?,
+            imu_intrinsics: client.get_imu_intrinsics()?,
+        };
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        Ok(())
+    }
+
+    fn config(&self, command: &ConfigCommand) -> Result<()> {
+        let mut client = self.client()?;
+        match command {
+            ConfigCommand::Get { param } => {
+                let config_txt = serde_json::to_value(client.get_config_txt()?)?;
+                let value = match param {
+                    Some(param) => config_txt.get(param).cloned().ok_or_else(|| {
+                        UsageError(format!("unknown configuration parameter '{}'", param))
+                    })?,
+                    None => config_txt,
+                };
+                println!("{}", serde_json::to_string_pretty(&value)?);
+            }
+            ConfigCommand::Set {
+                param,
+                value,
+                persist,
+            } => {
+                client.set_config_param(param, value)?;
+                if *persist {
+                    client.write_config_txt()?;
+                }
+                client.reinitialize()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn record(&self, output: &Path, duration: Option<f64>, imu: bool) -> Result<()> {
+        enum Output {
+            Bag(Box<BagWriter<std::io::BufWriter<std::fs::File>>>),
+            Pcap(PcapWriter<std::io::BufWriter<std::fs::File>>),
+        }
+
+        let mut sensor_config = self.sensor_config(true)?;
+        sensor_config.imu |= imu;
+        let mut output_writer = match extension(output).as_str() {
+            "mcap" => Output::Bag(Box::new(BagWriter::create(output)?)),
+            "pcap" => Output::Pcap(PcapWriter::create(output)?),
+            _ => {
+                let message = format!("{} is not an .mcap or .pcap file", output.display());
+                return Err(UsageError(message).into());
+            }
+        };
+        let mut sensor = Sensor::connect(&sensor_config)?;
+        if let Output::Bag(writer) = &mut output_writer {
+            writer.write_config(sensor.config())?;
+        }
+
+        let source = SocketAddrV4::new(sensor_config.lidar_addr, 7502);
+        let lidar_destination = SocketAddrV4::new(
+            sensor_config.listen_addr,
+            sensor.config_txt().udp_port_lidar,
+        );
+        let imu_destination =
+            SocketAddrV4::new(sensor_config.listen_addr, sensor.config_txt().udp_port_imu);
+        let running = running_flag()?;
+        let deadline = duration.map(|secs| Instant::now() + Duration::from_secs_f64(secs));
+        println!(
+            "Recording {} to {}. Press Ctrl-C to stop.",
+            sensor_config.lidar_addr,
+            output.display()
+        );
+
+        let mut num_packets = 0usize;
+        while running.load(Ordering::SeqCst) && deadline.is_none_or(|t| Instant::now() < t) {
+            for packet in sensor.recv_imu_packets()? {
+                match &mut output_writer {
+                    Output::Bag(writer) => writer.write_imu_packet(&packet, unix_nanos())?,
+                    Output::Pcap(writer) => writer.write_datagram(&UdpDatagram {
+                        timestamp: unix_nanos(),
+                        source,
+                        destination: imu_destination,
+                        payload: packet.as_bytes().to_vec(),
+                    })?,
+                }
+            }
+            if let Some(packet) = sensor.recv_packet()? {
+                match &mut output_writer {
+                    Output::Bag(writer) => writer.write_packet(&packet, unix_nanos())?,
+                    Output::Pcap(writer) => writer.write_datagram(&UdpDatagram {
+                        timestamp: unix_nanos(),
+                        source,
+                        destination: lidar_destination,
+                        payload: packet.as_bytes().to_vec(),
+                    })?,
+                }
+                num_packets += 1;
+            }
+        }
+        match output_writer {
+            Output::Bag(writer) => drop(writer.finish()?),
+            Output::Pcap(writer) => drop(writer.finish()?),
+        }
+
+        println!("Recorded {} packets.", num_packets);
+        Ok(())
+    }
+}
+
+fn replay(input: &Path, destination: SocketAddr, rate: f64) -> Result<()> {
+    if rate < 0.0 {
+        return Err(UsageError("the rate must not be negative".into()).into());
+    }
+    let recording = Recording::open(input)?;
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let running = running_flag()?;
+
+    let mut start = None;
+    let mut num_packets = 0usize;
+    recording.for_each_packet(|timestamp, packet| {
+        // Skip the rest after Ctrl-C
+        if !running.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let (first_timestamp, start_time) = *start.get_or_insert((timestamp, Instant::now()));
+        if rate > 0.0 {
+            let offset = timestamp.saturating_sub(first_timestamp) as f64 / rate;
+            let target = start_time + Duration::from_nanos(offset as u64);
+            if let Some(wait) = target.checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            }
+        }
+        socket.send_to(packet.as_bytes(), destination)?;
+        num_packets += 1;
+        Ok(())
+    })?;
+
+    println!("Sent {} packets to {}.", num_packets, destination);
+    Ok(())
+}
+
+fn convert(
+    input: &Path,
+    output_dir: &Path,
+    format: PointCloudFormat,
+    intrinsics: Option<&Path>,
+    partial_frames: PartialFramePolicy,
+    min_completeness: f64,
+) -> Result<()> {
+    let recording = Recording::open(input)?;
+    let mut converter = FrameConverter::from_config(recording.config(intrinsics)?)?;
+    converter.set_partial_frame_policy(partial_frames);
+    converter.set_min_completeness(min_completeness);
+    std::fs::create_dir_all(output_dir)?;
+
+    // Frames are converted and written in batches, in parallel with the
+    // rayon feature
+    let mut num_frames = 0usize;
+    let mut save = |frames: Vec<Frame>| -> Result<()> {
+        let first = num_frames;
+        parallel::try_for_each_indexed(&frames, |index, frame| {
+            let name = format!("frame{:06}.{}", first + index, format.extension());
+            save_points(output_dir.join(name), &frame.points, format)
+        })?;
+        num_frames += frames.len();
+        Ok(())
+    };
+    // Frames completed before a rejected packet are still written
+    let mut push_batch = |converter: &mut FrameConverter, batch: &[Packet]| -> Result<()> {
+        match converter.push_packets(batch) {
+            Ok(frames) => save(frames),
+            Err(mut error) => {
+                save(mem::take(&mut error.frames))?;
+                Err(error.into())
+            }
+        }
+    };
+    let mut batch = Vec::with_capacity(PACKETS_PER_BATCH);
+    recording.for_each_packet(|_, packet| {
+        batch.push(*packet);
+        if batch.len() == PACKETS_PER_BATCH {
+            push_batch(&mut converter, &batch)?;
+            batch.clear();
+        }
+        Ok(())
+    })?;
+    push_batch(&mut converter, &batch)?;
+    if let Some(frame) = converter.finish() {
+        if let Some(reason) = frame.completeness.partial_reason() {
+            eprintln!("Last frame {} is partial: {}", frame.frame_id, reason);
+        }
+        save(vec![frame])?;
+    }
+
+    println!("Wrote {} frames to {}.", num_frames, output_dir.display());
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+fn parquet(input: &Path, output_dir: &Path, intrinsics: Option<&Path>) -> Result<()> {
+    let recording = Recording::open(input)?;
+    let config = recording.config(intrinsics)?;
+    let mut writer = ouster_lidar::ParquetFrameWriter::create(output_dir, &config)?;
+    writer.add_metadata("ouster.source", &input.display().to_string());
+    let mut converter = FrameConverter::from_config(config)?;
+
+    recording.for_each_packet(|_, packet| {
+        for frame in converter.push_packet(packet)? {
+            writer.write_frame(&frame)?;
+        }
+        Ok(())
+    })?;
+    if let Some(frame) = converter.finish() {
+        writer.write_frame(&frame)?;
+    }
+
+    println!(
+        "Wrote {} frames to {}.",
+        writer.num_frames(),
+        output_dir.display()
+    );
+    Ok(())
+}
+
+fn stats(input: &Path, intrinsics: Option<&Path>) -> Result<()> {
+    let recording = Recording::open(input)?;
+    let config = recording.config(intrinsics)?;
+    // A single window over the whole recording
+    let health_config = HealthConfig {
+        window: Duration::MAX,
+        ..Default::default()
+    };
+    let mut monitor = HealthMonitor::new(&config, health_config)?;
+    let mut converter = FrameConverter::from_config(config)?;
+    let columns_per_frame = converter.columns_per_frame() as usize;
+
+    let mut num_packets = 0usize;
+    let mut num_errors = 0usize;
+    let mut time_range: Option<(u64, u64)> = None;
+    let mut frames = vec![];
+    recording.for_each_packet(|timestamp, packet| {
+        num_packets += 1;
+        let (first, _) = time_range.unwrap_or((timestamp, timestamp));
+        time_range = Some((first, timestamp));
+        monitor.push_packet_at(packet, timestamp);
+        match converter.push_packet(packet) {
+            Ok(new_frames) => frames.extend(
+                new_frames
+                    .into_iter()
+                    .map(|frame| (frame.skipped_frame_ids.len(), frame.timestamps.len())),
+            ),
+            // Rewound or replayed packets are counted, and the
+            // recording goes on with a new frame
+            Err(_) => {
+                num_errors += 1;
+                converter.reset();
+            }
+        }
+        Ok(())
+    })?;
+    if let Some(frame) = converter.finish() {
+        frames.push((frame.skipped_frame_ids.len(), frame.timestamps.len()));
+    }
+
+    let duration = time_range
+        .map(|(first, last)| Duration::from_nanos(last.saturating_sub(first)))
+        .unwrap_or_default();
+    let secs = duration.as_secs_f64();
+    let dropped_frames: usize = frames.iter().map(|(dropped, _)| dropped).sum();
+    let missing_columns: usize = frames
+        .iter()
+        .map(|(_, columns)| columns_per_frame.saturating_sub(*columns))
+        .sum();
+    println!("packets:         {}", num_packets);
+    println!("frames:          {}", frames.len());
+    println!("dropped frames:  {}", dropped_frames);
+    println!("missing columns: {}", missing_columns);
+    println!("bad packets:     {}", num_errors);
+    println!("duration:        {:.3} s", secs);
+    if secs > 0.0 {
+        println!("packet rate:     {:.1} /s", num_packets as f64 / secs);
+        println!("frame rate:      {:.1} /s", frames.len() as f64 / secs);
+    }
+    let health = monitor.snapshot();
+    println!("invalid columns: {}", health.invalid_columns);
+    println!("out of order:    {}", health.out_of_order);
+    println!("encoder jumps:   {}", health.encoder_discontinuities);
+    println!(
+        "jitter:          {:.3} ms",
+        health.jitter.as_secs_f64() * 1e3
+    );
+    println!(
+        "latency:         {:.3} ms mean, {:.3} ms max",
+        health.mean_latency_ns as f64 / 1e6,
+        health.max_latency_ns as f64 / 1e6
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match cli.run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {:#}", error);
+            if error.is::<UsageError>() {
+                ExitCode::from(EXIT_USAGE)
+            } else {
+                ExitCode::from(EXIT_FAILURE)
+            }
+        }
+    }
+}
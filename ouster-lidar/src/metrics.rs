@@ -0,0 +1,518 @@
+//! Prometheus metrics of sensors and their packet streams.
+//!
+//! [MetricsRegistry] collects stream statistics from a [HealthMonitor]
+//! and time sync state and alerts from the sensor command API, and
+//! renders them in the Prometheus text or OpenMetrics format. The
+//! metrics can be scraped over HTTP from [MetricsServer].
+//!
+//! Every sample is labeled with the sensor name given on update.
+
+use super::{
+    client::{Alerts, CommandClient, TimeInfo},
+    enums::TimestampMode,
+    health::{HealthMonitor, HealthSnapshot},
+};
+use crate::common::*;
+use log::warn;
+use std::{
+    collections::BTreeMap,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+/// Exposition formats of metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetricsFormat {
+    /// Prometheus text format 0.0.4.
+    Prometheus,
+    /// OpenMetrics text format 1.0.0.
+    OpenMetrics,
+}
+
+impl MetricsFormat {
+    /// Returns the HTTP content type.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Prometheus => "text/plain; version=0.0.4; charset=utf-8",
+            Self::OpenMetrics => "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        }
+    }
+
+    /// Picks the format from an HTTP `Accept` header.
+    pub fn from_accept(accept: &str) -> Self {
+        if accept.contains("application/openmetrics-text") {
+            Self::OpenMetrics
+        } else {
+            Self::Prometheus
+        }
+    }
+}
+
+/// Name, help and getter of a metric.
+type Field<T, V> = (&'static str, &'static str, fn(&T) -> V);
+/// Name, help and getter of a window metric given columns per frame.
+type WindowField = (&'static str, &'static str, fn(&HealthSnapshot, u16) -> f64);
+
+#[derive(Debug, Clone, Default)]
+struct SensorMetrics {
+    totals: Option<HealthSnapshot>,
+    latest: Option<HealthSnapshot>,
+    columns_per_frame: u16,
+    time_info: Option<TimeInfo>,
+    alerts: Option<Alerts>,
+}
+
+/// Shared store of sensor metrics. Clones refer to the same store.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+    sensors: Arc<Mutex<BTreeMap<String, SensorMetrics>>>,
+}
+
+impl MetricsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates stream metrics from a monitor. Counters are taken from
+    /// all packets seen by the monitor, while rates, jitter and latency
+    /// come from its last completed window.
+    pub fn update_stream(&self, sensor: &str, monitor: &HealthMonitor) {
+        self.with_sensor(sensor, |metrics| {
+            metrics.totals = Some(monitor.totals());
+            metrics.latest = monitor.latest().cloned();
+            metrics.columns_per_frame = monitor.columns_per_frame();
+        });
+    }
+
+    /// Updates time sync metrics.
+    pub fn update_time_info(&self, sensor: &str, time_info: &TimeInfo) {
+        self.with_sensor(sensor, |metrics| {
+            metrics.time_info = Some(time_info.clone())
+        });
+    }
+
+    /// Updates alert metrics.
+    pub fn update_alerts(&self, sensor: &str, alerts: &Alerts) {
+        self.with_sensor(sensor, |metrics| metrics.alerts = Some(alerts.clone()));
+    }
+
+    /// Queries time info and alerts from the sensor and updates them.
+    pub fn poll_sensor(&self, sensor: &str, client: &mut CommandClient) -> Result<()> {
+        let time_info = client.get_time_info()?;
+        self.update_time_info(sensor, &time_info);
+        let alerts = client.get_alerts()?;
+        self.update_alerts(sensor, &alerts);
+        Ok(())
+    }
+
+    /// Forgets all metrics of a sensor.
+    pub fn remove(&self, sensor: &str) {
+        self.sensors.lock().unwrap().remove(sensor);
+    }
+
+    /// Renders all metrics.
+    pub fn render(&self, format: MetricsFormat) -> String {
+        let sensors = self.sensors.lock().unwrap();
+        let mut renderer = Renderer::new(format);
+
+        let stream: Vec<_> = sensors
+            .iter()
+            .filter_map(|(name, metrics)| Some((name, metrics.totals.as_ref()?, metrics)))
+            .collect();
+        let counters: [Field<HealthSnapshot, usize>; 7] = [
+            ("packets", "Lidar packets received.", |totals| {
+                totals.packets
+            }),
+            ("frames", "Frames completed.", |totals| totals.frames),
+            ("dropped_frames", "Frames skipped entirely.", |totals| {
+                totals.dropped_frames
+            }),
+            (
+                "missing_columns",
+                "Columns missing in completed frames.",
+                |totals| totals.missing_columns,
+            ),
+            (
+                "invalid_columns",
+                "Columns not marked valid by the sensor.",
+                |totals| totals.invalid_columns,
+            ),
+            (
+                "out_of_order_columns",
+                "Columns that arrived after a later column.",
+                |totals| totals.out_of_order,
+            ),
+            (
+                "encoder_discontinuities",
+                "Consecutive columns whose encoder ticks jump.",
+                |totals| totals.encoder_discontinuities,
+            ),
+        ];
+        for (name, help, value) in counters {
+            let samples = stream
+                .iter()
+                .map(|(sensor, totals, _)| (labels(sensor, &[]), value(totals) as f64));
+            renderer.family(name, MetricKind::Counter, help, samples);
+        }
+
+        let windows: Vec<_> = stream
+            .iter()
+            .filter_map(|(sensor, _, metrics)| Some((sensor, metrics.latest.as_ref()?, metrics)))
+            .collect();
+        let gauges: [WindowField; 5] = [
+            ("packet_rate", "Lidar packets per second.", |window, _| {
+                window.packet_rate
+            }),
+            ("frame_rate", "Frames per second.", |window, _| {
+                window.frame_rate
+            }),
+            (
+                "frame_completeness_ratio",
+                "Fraction of columns in the azimuth window received in completed frames.",
+                |window, columns| {
+                    let expected = window.frames * columns as usize;
+                    if expected == 0 {
+                        1.0
+                    } else {
+                        1.0 - window.missing_columns as f64 / expected as f64
+                    }
+                },
+            ),
+            (
+                "jitter_seconds",
+                "Standard deviation of intervals between consecutive columns.",
+                |window, _| window.jitter.as_secs_f64(),
+            ),
+            (
+                "latency_seconds",
+                "Mean host receive time minus sensor timestamp.",
+                |window, _| window.mean_latency_ns as f64 / 1e9,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            let samples = windows.iter().map(|(sensor, window, metrics)| {
+                let value = value(window, metrics.columns_per_frame);
+                (labels(sensor, &[]), value)
+            });
+            renderer.family(name, MetricKind::Gauge, help, samples);
+        }
+
+        let time_infos: Vec<_> = sensors
+            .iter()
+            .filter_map(|(sensor, metrics)| Some((sensor, metrics.time_info.as_ref()?)))
+            .collect();
+        renderer.family(
+            "timestamp_mode",
+            MetricKind::Gauge,
+            "Timestamp source of the sensor.",
+            time_infos.iter().flat_map(|(sensor, time_info)| {
+                [
+                    TimestampMode::TimeFromInternalOsc,
+                    TimestampMode::TimeFromPtp1588,
+                    TimestampMode::TimeFromSyncPulseIn,
+                ]
+                .into_iter()
+                .map(move |mode| {
+                    let value = (mode == time_info.timestamp.mode) as u8 as f64;
+                    (labels(sensor, &[("mode", &mode.to_string())]), value)
+                })
+            }),
+        );
+        let time_gauges: [Field<TimeInfo, f64>; 2] = [
+            (
+                "sync_pulse_in_locked",
+                "Whether the sensor is locked to the sync pulse input.",
+                |time_info| time_info.sync_pulse_in.locked as u8 as f64,
+            ),
+            (
+                "nmea_locked",
+                "Whether the sensor is locked to NMEA messages.",
+                |time_info| time_info.multipurpose_io.nmea.locked as u8 as f64,
+            ),
+        ];
+        for (name, help, value) in time_gauges {
+            let samples = time_infos
+                .iter()
+                .map(|(sensor, time_info)| (labels(sensor, &[]), value(time_info)));
+            renderer.family(name, MetricKind::Gauge, help, samples);
+        }
+
+        let ptp_infos: Vec<_> = time_infos
+            .iter()
+            .filter_map(|(sensor, time_info)| Some((sensor, time_info.ptp.as_ref()?)))
+            .collect();
+        renderer.family(
+            "ptp_locked",
+            MetricKind::Gauge,
+            "Whether the sensor follows a PTP master.",
+            ptp_infos
+                .iter()
+                .map(|(sensor, ptp)| (labels(sensor, &[]), ptp.locked() as u8 as f64)),
+        );
+        renderer.family(
+            "ptp_offset_from_master_seconds",
+            MetricKind::Gauge,
+            "Offset of the sensor clock from the PTP master.",
+            ptp_infos.iter().map(|(sensor, ptp)| {
+                let offset = ptp.current_data_set.offset_from_master.raw() / 1e9;
+                (labels(sensor, &[]), offset)
+            }),
+        );
+        renderer.family(
+            "ptp_info",
+            MetricKind::Gauge,
+            "PTP profile and grandmaster of the sensor.",
+            ptp_infos.iter().map(|(sensor, ptp)| {
+                let labels = labels(
+                    sensor,
+                    &[
+                        ("profile", &ptp.profile),
+                        ("grandmaster", &ptp.parent_data_set.grandmaster_identity),
+                    ],
+                );
+                (labels, 1.0)
+            }),
+        );
+        let time_counters: [Field<TimeInfo, u64>; 4] = [
+            (
+                "sync_pulse_in_pulses",
+                "Sync pulses received.",
+                |time_info| time_info.sync_pulse_in.diagnostics.count,
+            ),
+            (
+                "nmea_utc_decoded",
+                "NMEA messages with UTC time decoded.",
+                |time_info| {
+                    time_info
+                        .multipurpose_io
+                        .nmea
+                        .diagnostics
+                        .decoding
+                        .utc_decoded_count
+                },
+            ),
+            (
+                "nmea_date_decoded",
+                "NMEA messages with date decoded.",
+                |time_info| {
+                    time_info
+                        .multipurpose_io
+                        .nmea
+                        .diagnostics
+                        .decoding
+                        .date_decoded_count
+                },
+            ),
+            (
+                "nmea_not_valid",
+                "NMEA messages rejected as not valid.",
+                |time_info| {
+                    time_info
+                        .multipurpose_io
+                        .nmea
+                        .diagnostics
+                        .decoding
+                        .not_valid_count
+                },
+            ),
+        ];
+        for (name, help, value) in time_counters {
+            let samples = time_infos
+                .iter()
+                .map(|(sensor, time_info)| (labels(sensor, &[]), value(time_info) as f64));
+            renderer.family(name, MetricKind::Counter, help, samples);
+        }
+
+        let alerts: Vec<_> = sensors
+            .iter()
+            .filter_map(|(sensor, metrics)| Some((sensor, metrics.alerts.as_ref()?)))
+            .collect();
+        renderer.family(
+            "alerts_logged",
+            MetricKind::Counter,
+            "Alert events logged by the sensor.",
+            alerts
+                .iter()
+                .map(|(sensor, alerts)| (labels(sensor, &[]), alerts.next_cursor as f64)),
+        );
+        renderer.family(
+            "alert_active",
+            MetricKind::Gauge,
+            "Alerts currently active on the sensor.",
+            alerts.iter().flat_map(|(sensor, alerts)| {
+                alerts.active.iter().map(move |alert| {
+                    let labels = labels(
+                        sensor,
+                        &[
+                            ("id", &alert.id),
+                            ("category", &alert.category),
+                            ("level", &alert.level),
+                        ],
+                    );
+                    (labels, 1.0)
+                })
+            }),
+        );
+
+        renderer.finish()
+    }
+
+    fn with_sensor<F>(&self, sensor: &str, f: F)
+    where
+        F: FnOnce(&mut SensorMetrics),
+    {
+        let mut sensors = self.sensors.lock().unwrap();
+        f(sensors.entry(sensor.to_string()).or_default());
+    }
+}
+
+/// An HTTP server serving metrics on `/metrics` from a background
+/// thread.
+#[derive(Debug)]
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+    _handle: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    /// Binds the address and starts serving the registry.
+    pub fn bind<A: ToSocketAddrs>(address: A, registry: MetricsRegistry) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let local_addr = listener.local_addr()?;
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream
+                    .map_err(Error::from)
+                    .and_then(|stream| serve_request(stream, &registry));
+                if let Err(error) = result {
+                    warn!("metrics request error: {:?}", error);
+                }
+            }
+        });
+        Ok(Self {
+            local_addr,
+            _handle: handle,
+        })
+    }
+
+    /// Returns the bound address.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+fn serve_request(stream: TcpStream, registry: &MetricsRegistry) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut accept = String::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("accept") {
+                accept = value.trim().to_string();
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let format = MetricsFormat::from_accept(&accept);
+            ("200 OK", format.content_type(), registry.render(format))
+        }
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "not found\n".into()),
+        _ => ("405 Method Not Allowed", "text/plain", "GET only\n".into()),
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+struct Renderer {
+    format: MetricsFormat,
+    text: String,
+}
+
+impl Renderer {
+    fn new(format: MetricsFormat) -> Self {
+        Self {
+            format,
+            text: String::new(),
+        }
+    }
+
+    /// Writes a metric family. It is omitted if it has no samples.
+    fn family<I>(&mut self, name: &str, kind: MetricKind, help: &str, samples: I)
+    where
+        I: IntoIterator<Item = (String, f64)>,
+    {
+        use std::fmt::Write;
+
+        let mut samples = samples.into_iter().peekable();
+        if samples.peek().is_none() {
+            return;
+        }
+        let (family, sample_name, kind) = match (kind, self.format) {
+            (MetricKind::Gauge, _) => (
+                format!("ouster_{}", name),
+                format!("ouster_{}", name),
+                "gauge",
+            ),
+            (MetricKind::Counter, MetricsFormat::Prometheus) => {
+                let name = format!("ouster_{}_total", name);
+                (name.clone(), name, "counter")
+            }
+            (MetricKind::Counter, MetricsFormat::OpenMetrics) => (
+                format!("ouster_{}", name),
+                format!("ouster_{}_total", name),
+                "counter",
+            ),
+        };
+        let _ = writeln!(self.text, "# HELP {} {}", family, help);
+        let _ = writeln!(self.text, "# TYPE {} {}", family, kind);
+        for (labels, value) in samples {
+            let _ = writeln!(self.text, "{}{{{}}} {}", sample_name, labels, value);
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if self.format == MetricsFormat::OpenMetrics {
+            self.text.push_str("# EOF\n");
+        }
+        self.text
+    }
+}
+
+fn labels(sensor: &str, extra: &[(&str, &str)]) -> String {
+    iter::once(("sensor", sensor))
+        .chain(extra.iter().copied())
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+        .join(",")
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,69 @@
+//! Organized access to frame points by beam and column.
+
+use super::{consts::PIXELS_PER_COLUMN, frame_converter::Frame, pcd_converter::Point};
+
+/// Maps pixel coordinates of a frame to point indices.
+///
+/// Rows are laser IDs and columns are measurement IDs, so
+/// the layout follows the beam rows × columns scan pattern
+/// even if some points were filtered out.
+#[derive(Debug, Clone)]
+pub struct OrganizedIndex {
+    width: usize,
+    height: usize,
+    indices: Vec<Option<usize>>,
+}
+
+impl OrganizedIndex {
+    /// Builds the index from points. Points outside of
+    /// `width` × `height` are ignored.
+    pub fn new(points: &[Point], width: usize, height: usize) -> Self {
+        let mut indices = vec![None; width * height];
+        for (index, point) in points.iter().enumerate() {
+            let row = point.laser_id as usize;
+            let col = point.measurement_id as usize;
+            if row < height && col < width {
+                indices[row * width + col] = Some(index);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            indices,
+        }
+    }
+
+    /// Builds the index of a frame with `columns_per_revolution` columns.
+    pub fn from_frame(frame: &Frame, columns_per_revolution: u16) -> Self {
+        Self::new(
+            &frame.points,
+            columns_per_revolution as usize,
+            PIXELS_PER_COLUMN,
+        )
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the index of point at the pixel if it exists.
+    pub fn get(&self, row: usize, col: usize) -> Option<usize> {
+        if row < self.height && col < self.width {
+            self.indices[row * self.width + col]
+        } else {
+            None
+        }
+    }
+
+    /// Returns the point indices of a column from row 0 upwards.
+    pub fn column(&self, col: usize) -> impl Iterator<Item = Option<usize>> + '_ {
+        (0..self.height).map(move |row| self.get(row, col))
+    }
+}
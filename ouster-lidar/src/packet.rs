@@ -1,10 +1,12 @@
 //! Provides a set of _C-packed_ structs for Ouster packets.
-use rustdds::Keyed;
 pub use serde_big_array::BigArray;
 
 use crate::common::*;
 
-use super::consts::{COLUMNS_PER_PACKET, ENCODER_TICKS_PER_REV, PIXELS_PER_COLUMN};
+use super::{
+    consts::{COLUMNS_PER_PACKET, ENCODER_TICKS_PER_REV, PIXELS_PER_COLUMN},
+    rig::SensorId,
+};
 
 /// Represents a point of signal measurement.
 #[repr(C, packed)]
@@ -48,20 +50,19 @@ pub struct Column {
     pub raw_valid: u32,
 }
 
-impl Keyed for Column {
-    type K = u64;
-
-    fn key(&self) -> Self::K {
-        self.frame_id as u64 + self.measurement_id  as u64 + self.encoder_ticks as u64
-    }
-}
-
 impl Column {
     /// Construct [NaiveDateTime](chrono::NaiveDateTime) object from column timestamp.
+    ///
+    /// It assumes the timestamp is time since the Unix epoch, which
+    /// only holds for some timestamp modes. Use
+    /// [TimeConverter](crate::time_sync::TimeConverter) to take the
+    /// mode into account.
     pub fn datetime(&self) -> NaiveDateTime {
         let secs = self.timestamp / 1_000_000_000;
         let nsecs = self.timestamp % 1_000_000_000;
-        NaiveDateTime::from_timestamp(secs as i64, nsecs as u32)
+        chrono::DateTime::from_timestamp(secs as i64, nsecs as u32)
+            .expect("timestamp is out of range")
+            .naive_utc()
     }
 
     pub fn time(&self) -> Duration {
@@ -91,10 +92,9 @@ impl Column {
 
 /// Represents a data packet from Ouster sensor.
 #[repr(C, packed)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize , Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Packet {
     pub columns: [Column; COLUMNS_PER_PACKET],
-    id: u64,
 }
 
 impl Packet {
@@ -114,13 +114,12 @@ impl Packet {
         let packet = unsafe { &*(buffer.as_ptr() as *const Packet) };
         Ok(packet)
     }
-}
 
-impl Keyed for Packet {
-    type K = u64;
-
-    fn key(&self) -> u64 {
-        self.id
+    /// Returns the binary representation of the packet.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(self as *const Packet as *const u8, mem::size_of::<Packet>())
+        }
     }
 }
 
@@ -130,9 +129,75 @@ impl AsRef<Packet> for Packet {
     }
 }
 
+/// Standard gravity in m/s².
+const STANDARD_GRAVITY: f64 = 9.80665;
+
+/// Represents an IMU packet from Ouster sensor.
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImuPacket {
+    /// System time of the sensor in nanoseconds since boot when the
+    /// packet is sent.
+    pub sys_timestamp: u64,
+    /// Timestamp in nanoseconds of the accelerometer reading, in the
+    /// clock of the timestamp mode like lidar columns.
+    pub accel_timestamp: u64,
+    /// Timestamp in nanoseconds of the gyroscope reading, in the clock
+    /// of the timestamp mode like lidar columns.
+    pub gyro_timestamp: u64,
+    /// Linear acceleration in g.
+    pub linear_acceleration: [f32; 3],
+    /// Angular velocity in degrees per second.
+    pub angular_velocity: [f32; 3],
+}
+
+impl ImuPacket {
+    /// Construct packet from binary buffer.
+    pub fn from_bytes(buffer: [u8; mem::size_of::<ImuPacket>()]) -> ImuPacket {
+        unsafe { std::mem::transmute::<_, ImuPacket>(buffer) }
+    }
 
-#[derive(Serialize, Deserialize, Debug)]
+    /// Construct packet from slice of bytes. Error if the slice size is not correct.
+    pub fn from_slice(buffer: &[u8]) -> Result<&ImuPacket> {
+        ensure!(
+            buffer.len() == mem::size_of::<ImuPacket>(),
+            "Requre the slice length to be {}, but get {}",
+            mem::size_of::<ImuPacket>(),
+            buffer.len(),
+        );
+        let packet = unsafe { &*(buffer.as_ptr() as *const ImuPacket) };
+        Ok(packet)
+    }
+
+    /// Returns the binary representation of the packet.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(
+                self as *const ImuPacket as *const u8,
+                mem::size_of::<ImuPacket>(),
+            )
+        }
+    }
+
+    /// Returns linear acceleration in m/s².
+    pub fn linear_acceleration_mps2(&self) -> [f64; 3] {
+        let acceleration = self.linear_acceleration;
+        acceleration.map(|v| v as f64 * STANDARD_GRAVITY)
+    }
+
+    /// Returns angular velocity in radians per second.
+    pub fn angular_velocity_radps(&self) -> [f64; 3] {
+        let velocity = self.angular_velocity;
+        velocity.map(|v| (v as f64).to_radians())
+    }
+}
+
+/// Timing information of a [Column].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PacketMetaData {
+    /// The sensor that produced the column.
+    #[serde(default)]
+    pub sensor_id: SensorId,
     /// Unix timestamp in nanoseconds.
     pub timestamp: u64,
     /// The column index.
@@ -141,10 +206,20 @@ pub struct PacketMetaData {
     pub frame_id: u16,
 }
 
-impl Keyed for PacketMetaData {
-    type K = u64;
+impl PacketMetaData {
+    /// Creates the metadata of a column produced by a sensor.
+    pub fn new(sensor_id: SensorId, column: &Column) -> Self {
+        Self {
+            sensor_id,
+            timestamp: column.timestamp,
+            measurement_id: column.measurement_id,
+            frame_id: column.frame_id,
+        }
+    }
+}
 
-    fn key(&self) -> Self::K {
-        self.timestamp
+impl From<&Column> for PacketMetaData {
+    fn from(column: &Column) -> Self {
+        Self::new(0, column)
     }
 }
@@ -0,0 +1,50 @@
+//! Order-preserving helpers that run on the rayon thread pool when the
+//! `rayon` feature is enabled, and sequentially otherwise.
+
+use crate::common::*;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Number of packets to convert at once with
+/// [push_packets](crate::frame_converter::FrameConverter::push_packets),
+/// about four frames of 1024 columns.
+pub const PACKETS_PER_BATCH: usize = 256;
+
+/// Returns true if the helpers run in parallel.
+pub const fn is_parallel() -> bool {
+    cfg!(feature = "rayon")
+}
+
+/// Maps items to results in the order of the items.
+pub fn map_ordered<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    let outputs = items.par_iter().map(f).collect();
+    #[cfg(not(feature = "rayon"))]
+    let outputs = items.iter().map(f).collect();
+    outputs
+}
+
+/// Calls `f` with the index and item of every item, and returns one of
+/// the errors if any call fails.
+pub fn try_for_each_indexed<T, F>(items: &[T], f: F) -> Result<()>
+where
+    T: Sync,
+    F: Fn(usize, &T) -> Result<()> + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    let result = items
+        .par_iter()
+        .enumerate()
+        .try_for_each(|(index, item)| f(index, item));
+    #[cfg(not(feature = "rayon"))]
+    let result = items
+        .iter()
+        .enumerate()
+        .try_for_each(|(index, item)| f(index, item));
+    result
+}
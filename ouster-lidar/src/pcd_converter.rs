@@ -3,27 +3,29 @@
 use super::{
     config::Config,
     consts::PIXELS_PER_COLUMN,
+    enums::LidarMode,
+    filter::{FilterPipeline, RawPoint},
     packet::{Column, Packet},
+    point_buffer::PointBuffer,
+    xyz_lut::XyzLut,
 };
-use crate::{common::*, utils::AngleExt as _};
+use crate::common::*;
 use std::f64::consts::PI;
 
-fn spherical_to_xyz(range: Length, azimuth_angle: Angle, altitude_angle: Angle) -> [Length; 3] {
-    let x = range * altitude_angle.sin() * azimuth_angle.cos();
-    let y = range * altitude_angle.sin() * azimuth_angle.sin();
-    let z = range * altitude_angle.cos();
-    [x, y, z]
-}
-
 #[derive(Clone, Debug)]
 pub struct Point {
     pub timestamp: Duration,
+    /// The clockwise azimuth from the encoder ticks of the column plus
+    /// the beam azimuth correction. Zero-filled points take the
+    /// azimuth of their measurement ID.
     pub azimuth_angle: Angle,
     pub distance: Length,
     pub reflectivity: u16,
     pub signal_photons: u16,
     pub noise_photons: u16,
     pub laser_id: u32,
+    /// The index of the column the point belongs to.
+    pub measurement_id: u16,
     pub point: [Length; 3],
 }
 
@@ -31,32 +33,24 @@ pub struct Point {
 /// into point clouds.
 #[derive(Debug, Clone)]
 pub struct PointCloudConverter {
-    altitude_angles: [Angle; PIXELS_PER_COLUMN],
     azimuth_angle_corrections: [Angle; PIXELS_PER_COLUMN],
+    lidar_mode: LidarMode,
     columns_per_revolution: u16,
+    xyz_lut: XyzLut,
+    filter: FilterPipeline,
 }
 
 impl PointCloudConverter {
-    /// Create a converter from config.
-    pub fn from_config(config: Config) -> Self {
+    /// Create a converter from config. It fails if the lidar mode has
+    /// unknown dimensions.
+    pub fn from_config(config: Config) -> Result<Self> {
+        let xyz_lut = XyzLut::from_config(&config)?;
         let Config {
             beam_azimuth_angle_corrections,
-            beam_altitude_angles,
             lidar_mode,
+            ..
         } = config;
 
-        let altitude_angles = {
-            let mut array = [Angle::from_radians(0.0); PIXELS_PER_COLUMN];
-            debug_assert_eq!(array.len(), beam_altitude_angles.len());
-
-            for idx in 0..(array.len()) {
-                let angle =
-                    std::f64::consts::FRAC_PI_2 - beam_altitude_angles[idx].to_radians().raw();
-                array[idx] = Angle::from_radians(angle);
-            }
-            array
-        };
-
         let azimuth_angle_corrections = {
             let mut array = [Angle::from_radians(0.0); PIXELS_PER_COLUMN];
             debug_assert_eq!(array.len(), beam_azimuth_angle_corrections.len());
@@ -68,13 +62,24 @@ impl PointCloudConverter {
             array
         };
 
-        let columns_per_revolution = lidar_mode.columns_per_revolution();
-
-        Self {
-            altitude_angles,
+        Ok(Self {
             azimuth_angle_corrections,
-            columns_per_revolution,
-        }
+            lidar_mode,
+            columns_per_revolution: xyz_lut.columns_per_revolution(),
+            xyz_lut,
+            filter: FilterPipeline::new(),
+        })
+    }
+
+    /// Sets the filter applied to every computed point.
+    /// Rejected points are dropped before being pushed to the output.
+    pub fn set_filter(&mut self, filter: FilterPipeline) {
+        self.filter = filter;
+    }
+
+    /// Returns the filter applied to computed points.
+    pub fn filter(&self) -> &FilterPipeline {
+        &self.filter
     }
 
     /// Get lidar scene width by its mode. For example,
@@ -84,60 +89,171 @@ impl PointCloudConverter {
         self.columns_per_revolution
     }
 
+    /// Returns the lidar mode of the config.
+    pub fn lidar_mode(&self) -> &LidarMode {
+        &self.lidar_mode
+    }
+
+    /// Returns the lookup table that point positions are computed
+    /// from.
+    pub fn xyz_lut(&self) -> &XyzLut {
+        &self.xyz_lut
+    }
+
     /// Compute point locations from column returned from lidar.
     ///
     /// The method takes [Column.measurement_id](Column.measurement_id) as column index.
     /// It returns error if the index is out of bound.
     pub(crate) fn column_to_points(&self, column: &Column) -> Result<Vec<Point>> {
-        // sanity check
+        Ok(self.column_points(column)?.collect())
+    }
+
+    /// Writes the points of a column to the end of a buffer, and
+    /// returns the number of points written. It does not allocate if
+    /// the buffer has room for a column.
+    pub fn column_into(&self, column: &Column, buffer: &mut PointBuffer) -> Result<usize> {
+        self.check_column(column)?;
+        if !column.valid() {
+            return Ok(0);
+        }
+
+        let measurement_id = column.measurement_id;
+        let timestamp = column.timestamp;
+        let pixels = column.pixels;
+        let ranges = pixels.map(|pixel| pixel.distance_millimeter());
+        let mut xyz = [[0.0; PIXELS_PER_COLUMN]; 3];
+        self.xyz_lut.column_xyz(measurement_id, &ranges, &mut xyz);
+
+        let len = buffer.len();
+        for (laser_id, pixel) in pixels.iter().enumerate() {
+            let [x, y, z] = [0, 1, 2].map(|axis| xyz[axis][laser_id]);
+            if !self.filter.is_empty() {
+                let point = RawPoint {
+                    xyz: [x, y, z].map(f64::from),
+                    distance: pixel.distance().as_meters(),
+                    reflectivity: pixel.reflectivity,
+                    signal_photons: pixel.signal_photons,
+                };
+                if !self.filter.keep_raw(&point) {
+                    continue;
+                }
+            }
+
+            buffer.x.push(x);
+            buffer.y.push(y);
+            buffer.z.push(z);
+            buffer.range.push(ranges[laser_id]);
+            buffer.reflectivity.push(pixel.reflectivity);
+            buffer.signal_photons.push(pixel.signal_photons);
+            buffer.noise_photons.push(pixel.noise_photons);
+            buffer.laser_id.push(laser_id as u16);
+            buffer.measurement_id.push(measurement_id);
+            buffer.timestamp.push(timestamp);
+        }
+        Ok(buffer.len() - len)
+    }
+
+    /// Writes the points of a packet to the end of a buffer, and
+    /// returns the number of points written. It does not allocate if
+    /// the buffer has room for a packet. On error the buffer is left as
+    /// it was.
+    pub fn convert_into<P>(&self, packet: P, buffer: &mut PointBuffer) -> Result<usize>
+    where
+        P: AsRef<Packet>,
+    {
+        let len = buffer.len();
+        let written: Result<usize> = packet
+            .as_ref()
+            .columns
+            .iter()
+            .map(|column| self.column_into(column, buffer))
+            .sum();
+        if written.is_err() {
+            buffer.truncate(len);
+        }
+        written
+    }
+
+    /// Checks the measurement ID of a column against the lidar mode.
+    fn check_column(&self, column: &Column) -> Result<()> {
         let col_index = column.measurement_id;
         ensure!(
-            col_index < self.columns_per_revolution,
+            col_index < self.columns_per_revolution(),
             "measurement_id {} is exceeds the upper bound {}. Is the lidar_mode configured correctly?",
             col_index,
-            self.columns_per_revolution,
+            self.columns_per_revolution(),
         );
+        Ok(())
+    }
 
-        // return empty list if the column is not valid
-        if !column.valid() {
-            return Ok(vec![]);
+    /// Returns the clockwise azimuth of a column from its measurement
+    /// ID, for columns without encoder ticks.
+    fn column_azimuth(&self, measurement_id: u16) -> Angle {
+        Angle::from_radians(PI * 2.0 * measurement_id as f64 / self.columns_per_revolution() as f64)
+    }
+
+    /// Returns the filtered points of a column, or none if the column
+    /// is not valid. Positions come from the [lookup table](XyzLut).
+    pub(crate) fn column_points<'a>(
+        &'a self,
+        column: &'a Column,
+    ) -> Result<impl Iterator<Item = Point> + 'a> {
+        self.check_column(column)?;
+        let col_index = column.measurement_id;
+        let column_azimuth = column.azimuth();
+        let mut xyz = [[0.0; PIXELS_PER_COLUMN]; 3];
+        if column.valid() {
+            let ranges = column.pixels.map(|pixel| pixel.distance_millimeter());
+            self.xyz_lut.column_xyz(col_index, &ranges, &mut xyz);
         }
 
-        let pixels_iter = column.pixels.iter();
+        // yield nothing if the column is not valid
+        let num_pixels = if column.valid() { PIXELS_PER_COLUMN } else { 0 };
+        let pixels_iter = column.pixels.iter().take(num_pixels);
 
-        let points = izip!(
-            pixels_iter,
-            self.altitude_angles.iter(),
-            self.azimuth_angle_corrections.iter(),
-            0..
-        )
-        .map(
-            |(pixel, altitude_angle, azimuth_angle_correction, laser_id)| {
+        let points = izip!(pixels_iter, self.azimuth_angle_corrections.iter(), 0..)
+            .map(move |(pixel, azimuth_angle_correction, laser_id)| Point {
+                timestamp: column.time(),
+                reflectivity: pixel.reflectivity,
+                signal_photons: pixel.signal_photons,
+                noise_photons: pixel.noise_photons,
                 // add correction according to manual
-                let clockwise_azimuth_angle = column.azimuth() + *azimuth_angle_correction;
-                let counter_clockwise_azimuth_angle =
-                    Angle::from_radians(PI * 2.0) - clockwise_azimuth_angle;
-                let distance = pixel.distance();
-                let timestamp = column.time();
-                let point =
-                    spherical_to_xyz(distance, counter_clockwise_azimuth_angle, *altitude_angle);
-
-                Point {
-                    timestamp,
-                    reflectivity: pixel.reflectivity,
-                    signal_photons: pixel.signal_photons,
-                    noise_photons: pixel.noise_photons,
-                    azimuth_angle: clockwise_azimuth_angle,
-                    distance,
-                    laser_id,
-                    point,
-                }
-            },
-        )
-        .collect::<Vec<_>>();
+                azimuth_angle: column_azimuth + *azimuth_angle_correction,
+                distance: pixel.distance(),
+                laser_id,
+                measurement_id: col_index,
+                point: [0, 1, 2]
+                    .map(|axis| Length::from_meters(xyz[axis][laser_id as usize] as f64)),
+            })
+            .filter(|point| self.filter.keep(point));
         Ok(points)
     }
 
+    /// Creates zero-range points standing in for a column that was not
+    /// received. The filter is not applied.
+    pub(crate) fn empty_column_points(
+        &self,
+        measurement_id: u16,
+        timestamp: Duration,
+    ) -> Vec<Point> {
+        let column_azimuth = self.column_azimuth(measurement_id);
+        self.azimuth_angle_corrections
+            .iter()
+            .zip(0..)
+            .map(|(azimuth_angle_correction, laser_id)| Point {
+                timestamp,
+                azimuth_angle: column_azimuth + *azimuth_angle_correction,
+                distance: Length::from_meters(0.0),
+                reflectivity: 0,
+                signal_photons: 0,
+                noise_photons: 0,
+                laser_id,
+                measurement_id,
+                point: [Length::from_meters(0.0); 3],
+            })
+            .collect()
+    }
+
     /// Compute point positions from a packet.
     pub fn convert<P>(&self, packet: P) -> Result<Vec<Point>>
     where
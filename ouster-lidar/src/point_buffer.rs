@@ -0,0 +1,167 @@
+//! Struct-of-arrays point storage that is reused across packets.
+
+use super::pcd_converter::Point;
+use crate::common::*;
+use std::f64::consts::TAU;
+
+/// Points stored as one array per attribute.
+///
+/// The buffer is meant to be allocated once and [cleared](PointBuffer::clear)
+/// between packets or frames. Pushing points does not allocate as long
+/// as the buffer has enough [capacity](PointBuffer::capacity).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointBuffer {
+    /// X coordinates in meters.
+    pub x: Vec<f32>,
+    /// Y coordinates in meters.
+    pub y: Vec<f32>,
+    /// Z coordinates in meters.
+    pub z: Vec<f32>,
+    /// Ranges in millimeters.
+    pub range: Vec<u32>,
+    pub reflectivity: Vec<u16>,
+    pub signal_photons: Vec<u16>,
+    pub noise_photons: Vec<u16>,
+    pub laser_id: Vec<u16>,
+    pub measurement_id: Vec<u16>,
+    /// Column timestamps in nanoseconds.
+    pub timestamp: Vec<u64>,
+}
+
+impl PointBuffer {
+    /// Creates an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a buffer holding `capacity` points without reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            x: Vec::with_capacity(capacity),
+            y: Vec::with_capacity(capacity),
+            z: Vec::with_capacity(capacity),
+            range: Vec::with_capacity(capacity),
+            reflectivity: Vec::with_capacity(capacity),
+            signal_photons: Vec::with_capacity(capacity),
+            noise_photons: Vec::with_capacity(capacity),
+            laser_id: Vec::with_capacity(capacity),
+            measurement_id: Vec::with_capacity(capacity),
+            timestamp: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of points.
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    /// Returns true if the buffer has no points.
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
+    /// Returns the number of points the buffer holds without
+    /// reallocating.
+    pub fn capacity(&self) -> usize {
+        [
+            self.x.capacity(),
+            self.y.capacity(),
+            self.z.capacity(),
+            self.range.capacity(),
+            self.reflectivity.capacity(),
+            self.signal_photons.capacity(),
+            self.noise_photons.capacity(),
+            self.laser_id.capacity(),
+            self.measurement_id.capacity(),
+            self.timestamp.capacity(),
+        ]
+        .into_iter()
+        .min()
+        .unwrap()
+    }
+
+    /// Reserves room for at least `additional` more points.
+    pub fn reserve(&mut self, additional: usize) {
+        self.x.reserve(additional);
+        self.y.reserve(additional);
+        self.z.reserve(additional);
+        self.range.reserve(additional);
+        self.reflectivity.reserve(additional);
+        self.signal_photons.reserve(additional);
+        self.noise_photons.reserve(additional);
+        self.laser_id.reserve(additional);
+        self.measurement_id.reserve(additional);
+        self.timestamp.reserve(additional);
+    }
+
+    /// Removes all points and keeps the capacity.
+    pub fn clear(&mut self) {
+        self.x.clear();
+        self.y.clear();
+        self.z.clear();
+        self.range.clear();
+        self.reflectivity.clear();
+        self.signal_photons.clear();
+        self.noise_photons.clear();
+        self.laser_id.clear();
+        self.measurement_id.clear();
+        self.timestamp.clear();
+    }
+
+    /// Keeps the first `len` points and drops the rest.
+    pub fn truncate(&mut self, len: usize) {
+        self.x.truncate(len);
+        self.y.truncate(len);
+        self.z.truncate(len);
+        self.range.truncate(len);
+        self.reflectivity.truncate(len);
+        self.signal_photons.truncate(len);
+        self.noise_photons.truncate(len);
+        self.laser_id.truncate(len);
+        self.measurement_id.truncate(len);
+        self.timestamp.truncate(len);
+    }
+
+    /// Appends a point.
+    pub fn push(&mut self, point: &Point) {
+        let [x, y, z] = point.point.map(|value| value.as_meters() as f32);
+        self.x.push(x);
+        self.y.push(y);
+        self.z.push(z);
+        self.range
+            .push(point.distance.as_millimeters().round() as u32);
+        self.reflectivity.push(point.reflectivity);
+        self.signal_photons.push(point.signal_photons);
+        self.noise_photons.push(point.noise_photons);
+        self.laser_id.push(point.laser_id as u16);
+        self.measurement_id.push(point.measurement_id);
+        self.timestamp.push(point.timestamp.as_nanos() as u64);
+    }
+
+    /// Returns the point at an index. See [points](PointBuffer::points)
+    /// for the azimuth.
+    pub fn get(&self, index: usize) -> Option<Point> {
+        if index >= self.len() {
+            return None;
+        }
+        let [x, y, z] = [&self.x, &self.y, &self.z].map(|values| values[index]);
+        let distance = Length::from_millimeters(self.range[index] as f64);
+        Some(Point {
+            timestamp: Duration::from_nanos(self.timestamp[index]),
+            azimuth_angle: Angle::from_radians((-y as f64).atan2(x as f64).rem_euclid(TAU)),
+            distance,
+            reflectivity: self.reflectivity[index],
+            signal_photons: self.signal_photons[index],
+            noise_photons: self.noise_photons[index],
+            laser_id: self.laser_id[index] as u32,
+            measurement_id: self.measurement_id[index],
+            point: [x, y, z].map(|value| Length::from_meters(value as f64)),
+        })
+    }
+
+    /// Iterates over the points. The azimuth of each point is recovered
+    /// from its position, so it is zero for points without range.
+    pub fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+}
@@ -0,0 +1,145 @@
+//! Compact plain-old-data points for GPU and machine learning code.
+
+use super::{
+    frame_converter::Frame,
+    packet::Packet,
+    pcd_converter::{Point, PointCloudConverter},
+};
+use crate::common::*;
+use bytemuck::{Pod, Zeroable};
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+/// Floating point type of [PointXYZIRT] coordinates, either `f32` or
+/// `f64`.
+pub trait Precision: Pod + Default + PartialEq + Debug + Send + Sync + sealed::Sealed {
+    /// Converts from `f64`, rounding if the precision is lower.
+    fn from_f64(value: f64) -> Self;
+
+    /// Converts to `f64`.
+    fn to_f64(self) -> f64;
+}
+
+impl Precision for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Precision for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+/// A point with position, intensity, ring and time, laid out as a C
+/// struct without padding.
+///
+/// It takes 32 bytes with `f32` and 48 bytes with `f64` coordinates.
+/// Slices of points can be viewed as bytes with
+/// [bytemuck::cast_slice].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PointXYZIRT<T: Precision = f32> {
+    /// X coordinate in meters.
+    pub x: T,
+    /// Y coordinate in meters.
+    pub y: T,
+    /// Z coordinate in meters.
+    pub z: T,
+    /// Signal photons.
+    pub intensity: T,
+    /// Time since the frame start in nanoseconds.
+    pub t: u32,
+    /// Range in millimeters.
+    pub range: u32,
+    /// Laser ID.
+    pub ring: u16,
+    pub reflectivity: u16,
+    /// Noise photons.
+    pub ambient: u16,
+    /// Measurement ID of the column.
+    pub column: u16,
+}
+
+// SAFETY: The fields are plain numbers, 4 of T followed by 16 bytes of
+// integers, so there is no padding for 4 and 8 byte T.
+unsafe impl Zeroable for PointXYZIRT<f32> {}
+unsafe impl Pod for PointXYZIRT<f32> {}
+unsafe impl Zeroable for PointXYZIRT<f64> {}
+unsafe impl Pod for PointXYZIRT<f64> {}
+
+impl<T: Precision> PointXYZIRT<T> {
+    /// Converts a point, taking time relative to `start_time`.
+    pub fn from_point(point: &Point, start_time: Duration) -> Self {
+        let [x, y, z] = point.point.map(|value| T::from_f64(value.as_meters()));
+        let t = point.timestamp.saturating_sub(start_time).as_nanos();
+        Self {
+            x,
+            y,
+            z,
+            intensity: T::from_f64(point.signal_photons as f64),
+            t: u32::try_from(t).unwrap_or(u32::MAX),
+            range: point.distance.as_millimeters().round() as u32,
+            ring: point.laser_id as u16,
+            reflectivity: point.reflectivity,
+            ambient: point.noise_photons,
+            column: point.measurement_id,
+        }
+    }
+
+    /// Returns the position in meters.
+    pub fn position(&self) -> [T; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+impl Frame {
+    /// Converts the points to [PointXYZIRT] with time relative to the
+    /// frame start.
+    pub fn to_xyzirt<T: Precision>(&self) -> Vec<PointXYZIRT<T>> {
+        let start_time = self.start_time();
+        self.points
+            .iter()
+            .map(|point| PointXYZIRT::from_point(point, start_time))
+            .collect()
+    }
+}
+
+impl PointCloudConverter {
+    /// Appends the points of a packet as [PointXYZIRT] with time
+    /// relative to `start_time`, and returns the number of points
+    /// appended. It does not allocate if the vector has room for a
+    /// packet.
+    pub fn convert_xyzirt_into<T, P>(
+        &self,
+        packet: P,
+        start_time: Duration,
+        points: &mut Vec<PointXYZIRT<T>>,
+    ) -> Result<usize>
+    where
+        T: Precision,
+        P: AsRef<Packet>,
+    {
+        let len = points.len();
+        for column in packet.as_ref().columns.iter() {
+            points.extend(
+                self.column_points(column)?
+                    .map(|point| PointXYZIRT::from_point(&point, start_time)),
+            );
+        }
+        Ok(points.len() - len)
+    }
+}
@@ -0,0 +1,456 @@
+//! Multi-sensor rigs with extrinsic calibration.
+//!
+//! A [SensorRig] owns one [FrameConverter] per mounted sensor,
+//! transforms every frame into the common vehicle frame using
+//! the sensor [Extrinsic], and merges frames that were captured
+//! at about the same time into a single [RigFrame].
+
+use super::{
+    config::Config,
+    frame_converter::{Frame, FrameConverter},
+    packet::Packet,
+    pcd_converter::Point,
+};
+use crate::common::*;
+use std::collections::VecDeque;
+
+/// The index of a sensor within a [SensorRig].
+pub type SensorId = u16;
+
+/// Default maximum number of frames waiting for partners per sensor,
+/// about a second at 10 Hz.
+pub const DEFAULT_MAX_PENDING_FRAMES: usize = 10;
+
+/// Rigid transform from sensor frame to vehicle frame.
+///
+/// It is deserialized either from a row-major 4x4 homogeneous
+/// `matrix`, or from a unit quaternion `rotation` in `[w, x, y, z]`
+/// order along with a `translation` in meters.
+///
+/// ```toml
+/// rotation = [1.0, 0.0, 0.0, 0.0]
+/// translation = [1.2, 0.0, 1.6]
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Extrinsic {
+    Matrix {
+        matrix: [f64; 16],
+    },
+    QuaternionTranslation {
+        rotation: [f64; 4],
+        translation: [f64; 3],
+    },
+}
+
+impl Default for Extrinsic {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Extrinsic {
+    /// Creates the identity transform.
+    pub fn identity() -> Self {
+        Self::QuaternionTranslation {
+            rotation: [1.0, 0.0, 0.0, 0.0],
+            translation: [0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Loads extrinsic from path. The file is parsed as TOML
+    /// if it has `.toml` extension, otherwise as JSON.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Extrinsic> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&text),
+            _ => Self::from_json_str(&text),
+        }
+    }
+
+    /// Parses from JSON string. It fails if the transform is not
+    /// [valid](Self::validate).
+    pub fn from_json_str(data: &str) -> Result<Extrinsic> {
+        let ret: Extrinsic = serde_json::from_str(data)?;
+        ret.validate()?;
+        Ok(ret)
+    }
+
+    /// Parses from TOML string. It fails if the transform is not
+    /// [valid](Self::validate).
+    pub fn from_toml_str(data: &str) -> Result<Extrinsic> {
+        let ret: Extrinsic = toml::from_str(data)?;
+        ret.validate()?;
+        Ok(ret)
+    }
+
+    /// Checks that all values are finite, the rotation quaternion is
+    /// not zero, and the bottom row of a matrix is `[0, 0, 0, 1]`.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Matrix { matrix } => {
+                ensure!(
+                    matrix.iter().all(|value| value.is_finite()),
+                    "the extrinsic matrix has non-finite values"
+                );
+                ensure!(
+                    matrix[12..] == [0.0, 0.0, 0.0, 1.0],
+                    "the bottom row of the extrinsic matrix must be [0, 0, 0, 1]"
+                );
+            }
+            Self::QuaternionTranslation {
+                rotation,
+                translation,
+            } => {
+                ensure!(
+                    rotation
+                        .iter()
+                        .chain(translation)
+                        .all(|value| value.is_finite()),
+                    "the extrinsic rotation or translation has non-finite values"
+                );
+                let norm = rotation.iter().map(|v| v * v).sum::<f64>().sqrt();
+                ensure!(
+                    norm > f64::EPSILON,
+                    "the extrinsic rotation quaternion must not be zero"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the row-major 4x4 homogeneous matrix.
+    pub fn to_matrix(&self) -> [f64; 16] {
+        match *self {
+            Self::Matrix { matrix } => matrix,
+            Self::QuaternionTranslation {
+                rotation,
+                translation,
+            } => {
+                let norm = rotation.iter().map(|v| v * v).sum::<f64>().sqrt();
+                let [w, x, y, z] = rotation.map(|v| v / norm);
+                let [tx, ty, tz] = translation;
+
+                [
+                    1.0 - 2.0 * (y * y + z * z),
+                    2.0 * (x * y - z * w),
+                    2.0 * (x * z + y * w),
+                    tx,
+                    2.0 * (x * y + z * w),
+                    1.0 - 2.0 * (x * x + z * z),
+                    2.0 * (y * z - x * w),
+                    ty,
+                    2.0 * (x * z - y * w),
+                    2.0 * (y * z + x * w),
+                    1.0 - 2.0 * (x * x + y * y),
+                    tz,
+                    0.0,
+                    0.0,
+                    0.0,
+                    1.0,
+                ]
+            }
+        }
+    }
+
+    /// Transforms a point from sensor frame to vehicle frame.
+    pub fn transform(&self, point: [Length; 3]) -> [Length; 3] {
+        transform_point(&self.to_matrix(), point)
+    }
+}
+
+fn transform_point(matrix: &[f64; 16], point: [Length; 3]) -> [Length; 3] {
+    let [x, y, z] = point.map(|v| v.as_meters());
+    let row = |r: usize| {
+        let m = &matrix[(r * 4)..(r * 4 + 4)];
+        Length::from_meters(m[0] * x + m[1] * y + m[2] * z + m[3])
+    };
+    [row(0), row(1), row(2)]
+}
+
+/// Per-sensor entry of [RigConfig].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RigSensorConfig {
+    pub name: String,
+    pub extrinsic: Extrinsic,
+    pub config: Config,
+}
+
+/// A serializable description of a [SensorRig].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RigConfig {
+    /// Maximum difference of frame start times in milliseconds
+    /// for frames to be merged together.
+    pub sync_tolerance_ms: u64,
+    /// Maximum number of frames waiting for partners per sensor.
+    #[serde(default = "default_max_pending_frames")]
+    pub max_pending_frames: usize,
+    pub sensors: Vec<RigSensorConfig>,
+}
+
+fn default_max_pending_frames() -> usize {
+    DEFAULT_MAX_PENDING_FRAMES
+}
+
+impl RigConfig {
+    /// Loads rig config from path. The file is parsed as TOML
+    /// if it has `.toml` extension, otherwise as JSON.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<RigConfig> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let ret: RigConfig = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&text)?,
+            _ => serde_json::from_str(&text)?,
+        };
+        for sensor in ret.sensors.iter() {
+            if let Err(error) = sensor.extrinsic.validate() {
+                bail!("sensor {}: {}", sensor.name, error);
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// A point tagged with the sensor it comes from.
+#[derive(Clone, Debug)]
+pub struct RigPoint {
+    pub sensor_id: SensorId,
+    /// Point with coordinates in vehicle frame.
+    pub point: Point,
+}
+
+/// A point cloud merged from time-aligned frames of all sensors.
+#[derive(Debug, Clone)]
+pub struct RigFrame {
+    /// The earliest start time among merged frames.
+    pub timestamp: Duration,
+    /// Pairs of `(sensor_id, frame_id)` of merged frames.
+    pub frame_ids: Vec<(SensorId, u16)>,
+    /// Point cloud data in vehicle frame.
+    pub points: Vec<RigPoint>,
+}
+
+#[derive(Debug)]
+struct RigSensor {
+    name: String,
+    matrix: [f64; 16],
+    pending: VecDeque<Frame>,
+    dropped_frames: usize,
+}
+
+impl RigSensor {
+    /// Queues the frame, dropping the oldest ones beyond `max_pending`.
+    fn enqueue(&mut self, mut frame: Frame, max_pending: usize) {
+        frame.points.iter_mut().for_each(|point| {
+            point.point = transform_point(&self.matrix, point.point);
+        });
+        self.pending.push_back(frame);
+        while self.pending.len() > max_pending {
+            self.pending.pop_front();
+            self.dropped_frames += 1;
+        }
+    }
+}
+
+/// It gathers frames from several sensors, transforms
+/// them into the vehicle frame and merges time-aligned
+/// frames into [RigFrame]s.
+///
+/// A merged frame is produced once every sensor has a
+/// frame whose start time is within the sync tolerance.
+/// Frames that cannot be matched are dropped, and so are the
+/// oldest frames of a sensor whose partners stop arriving.
+#[derive(Debug)]
+pub struct SensorRig {
+    sync_tolerance: Duration,
+    max_pending_frames: usize,
+    sensors: Vec<RigSensor>,
+    converters: Vec<FrameConverter>,
+}
+
+impl SensorRig {
+    /// Creates an empty rig.
+    pub fn new(sync_tolerance: Duration) -> Self {
+        Self {
+            sync_tolerance,
+            max_pending_frames: DEFAULT_MAX_PENDING_FRAMES,
+            sensors: vec![],
+            converters: vec![],
+        }
+    }
+
+    /// Creates a rig from config.
+    pub fn from_config(config: RigConfig) -> Result<Self> {
+        let RigConfig {
+            sync_tolerance_ms,
+            max_pending_frames,
+            sensors,
+        } = config;
+
+        let mut rig = Self::new(Duration::from_millis(sync_tolerance_ms));
+        rig.set_max_pending_frames(max_pending_frames);
+        for sensor in sensors {
+            rig.add_sensor(sensor.name, sensor.config, sensor.extrinsic)?;
+        }
+        Ok(rig)
+    }
+
+    /// Adds a sensor and returns its ID. It fails if the lidar mode
+    /// has unknown dimensions or the extrinsic is not
+    /// [valid](Extrinsic::validate).
+    pub fn add_sensor<S>(
+        &mut self,
+        name: S,
+        config: Config,
+        extrinsic: Extrinsic,
+    ) -> Result<SensorId>
+    where
+        S: ToString,
+    {
+        extrinsic.validate()?;
+        let converter = FrameConverter::from_config(config)?;
+        let id = self.sensors.len() as SensorId;
+        self.sensors.push(RigSensor {
+            name: name.to_string(),
+            matrix: extrinsic.to_matrix(),
+            pending: VecDeque::new(),
+            dropped_frames: 0,
+        });
+        self.converters.push(converter);
+        Ok(id)
+    }
+
+    /// Looks up sensor ID by name.
+    pub fn sensor_id(&self, name: &str) -> Option<SensorId> {
+        self.sensors
+            .iter()
+            .position(|sensor| sensor.name == name)
+            .map(|idx| idx as SensorId)
+    }
+
+    /// Returns the number of sensors.
+    pub fn num_sensors(&self) -> usize {
+        self.sensors.len()
+    }
+
+    /// Returns the maximum number of frames waiting for partners per
+    /// sensor.
+    pub fn max_pending_frames(&self) -> usize {
+        self.max_pending_frames
+    }
+
+    /// Sets the maximum number of frames waiting for partners per
+    /// sensor, at least one. The oldest frames are dropped beyond it.
+    pub fn set_max_pending_frames(&mut self, max_pending_frames: usize) {
+        self.max_pending_frames = max_pending_frames.max(1);
+    }
+
+    /// Returns the number of frames of a sensor dropped because its
+    /// queue was full.
+    pub fn dropped_frames(&self, sensor_id: SensorId) -> Result<usize> {
+        let index = self.sensor_index(sensor_id)?;
+        Ok(self.sensors[index].dropped_frames)
+    }
+
+    /// Pushes new [Packet] from a sensor to the rig.
+    pub fn push_packet<P>(&mut self, sensor_id: SensorId, packet: P) -> Result<Vec<RigFrame>>
+    where
+        P: AsRef<Packet>,
+    {
+        let index = self.sensor_index(sensor_id)?;
+        let frames = self.converters[index].push_packet(packet)?;
+        for frame in frames {
+            self.sensors[index].enqueue(frame, self.max_pending_frames);
+        }
+        Ok(self.merge_pending())
+    }
+
+    /// Pushes an already converted [Frame] in sensor frame.
+    ///
+    /// The merged frames are returned by subsequent calls to
+    /// [push_packet](SensorRig::push_packet) or [finish](SensorRig::finish).
+    pub fn push_frame(&mut self, sensor_id: SensorId, frame: Frame) -> Result<()> {
+        let index = self.sensor_index(sensor_id)?;
+        self.sensors[index].enqueue(frame, self.max_pending_frames);
+        Ok(())
+    }
+
+    /// Consumes the instance, flushes the last frames of
+    /// all sensors and outputs remaining merged frames.
+    pub fn finish(mut self) -> Vec<RigFrame> {
+        let converters = mem::take(&mut self.converters);
+        for (sensor, converter) in izip!(self.sensors.iter_mut(), converters) {
+            if let Some(frame) = converter.finish() {
+                sensor.enqueue(frame, self.max_pending_frames);
+            }
+        }
+        self.merge_pending()
+    }
+
+    fn sensor_index(&self, sensor_id: SensorId) -> Result<usize> {
+        let index = sensor_id as usize;
+        ensure!(
+            index < self.sensors.len(),
+            "sensor ID {} is out of bound. The rig has {} sensors",
+            sensor_id,
+            self.sensors.len()
+        );
+        Ok(index)
+    }
+
+    fn merge_pending(&mut self) -> Vec<RigFrame> {
+        let mut output = vec![];
+        if self.sensors.is_empty() {
+            return output;
+        }
+
+        loop {
+            let start_times: Option<Vec<Duration>> = self
+                .sensors
+                .iter()
+                .map(|sensor| sensor.pending.front().map(Frame::start_time))
+                .collect();
+            let start_times = match start_times {
+                Some(times) => times,
+                None => break,
+            };
+            let latest = *start_times.iter().max().unwrap();
+
+            // Drop heading frames that are too old to match the latest one
+            let mut dropped = false;
+            for (sensor, start) in izip!(self.sensors.iter_mut(), start_times.iter()) {
+                if latest - *start > self.sync_tolerance {
+                    sensor.pending.pop_front();
+                    dropped = true;
+                }
+            }
+            if dropped {
+                continue;
+            }
+
+            let earliest = *start_times.iter().min().unwrap();
+            let mut frame_ids = Vec::with_capacity(self.sensors.len());
+            let mut points = vec![];
+            for (sensor_id, sensor) in self.sensors.iter_mut().enumerate() {
+                let frame = sensor.pending.pop_front().unwrap();
+                let sensor_id = sensor_id as SensorId;
+                frame_ids.push((sensor_id, frame.frame_id));
+                points.extend(
+                    frame
+                        .points
+                        .into_iter()
+                        .map(|point| RigPoint { sensor_id, point }),
+                );
+            }
+
+            output.push(RigFrame {
+                timestamp: earliest,
+                frame_ids,
+                points,
+            });
+        }
+
+        output
+    }
+}
@@ -0,0 +1,667 @@
+//! ROS 2 message types and publishers over DDS.
+//!
+//! Messages are serialized in the CDR layout of their ROS 2
+//! counterparts and published on `rt/` prefixed topics, so ROS 2
+//! nodes such as rviz2 can subscribe without a ROS install on the
+//! Rust side.
+
+use super::{
+    consts::PIXELS_PER_COLUMN,
+    dds::DdsQos,
+    frame_converter::Frame,
+    organized::OrganizedIndex,
+    packet::{ImuPacket, Packet},
+    pcd_converter::Point,
+};
+use crate::common::*;
+use rustdds::{
+    no_key::{DataWriter, DeserializerAdapter, SerializerAdapter},
+    CDRDeserializerAdapter, CDRSerializerAdapter, DomainParticipant, RepresentationIdentifier,
+    TopicKind,
+};
+use serde::de::DeserializeOwned;
+
+/// The CDR little-endian encapsulation header of serialized messages.
+const CDR_LE_HEADER: [u8; 4] = [0x00, 0x01, 0x00, 0x00];
+
+/// Serializes a message to CDR bytes with the encapsulation header,
+/// the same format as rosbag2 stores.
+pub fn to_cdr_bytes<T: Serialize>(message: &T) -> Result<Vec<u8>> {
+    let body = <CDRSerializerAdapter<T> as SerializerAdapter<T>>::to_bytes(message)?;
+    let mut bytes = CDR_LE_HEADER.to_vec();
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+/// Deserializes a message from CDR bytes with the encapsulation header.
+pub fn from_cdr_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    ensure!(bytes.len() >= 4, "CDR message is too short");
+    let encoding = match bytes[..4] {
+        [0x00, 0x01, ..] => RepresentationIdentifier::CDR_LE,
+        [0x00, 0x00, ..] => RepresentationIdentifier::CDR_BE,
+        _ => bail!("unsupported CDR encapsulation {:?}", &bytes[..4]),
+    };
+    let message =
+        <CDRDeserializerAdapter<T> as DeserializerAdapter<T>>::from_bytes(&bytes[4..], encoding)?;
+    Ok(message)
+}
+
+/// `builtin_interfaces/msg/Time`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Time {
+    pub sec: i32,
+    pub nanosec: u32,
+}
+
+impl Time {
+    /// Creates the time from Unix timestamp in nanoseconds.
+    pub fn from_nanos(nanos: u64) -> Self {
+        Self {
+            sec: (nanos / 1_000_000_000) as i32,
+            nanosec: (nanos % 1_000_000_000) as u32,
+        }
+    }
+
+    /// Returns the Unix timestamp in nanoseconds.
+    pub fn as_nanos(&self) -> u64 {
+        self.sec as u64 * 1_000_000_000 + self.nanosec as u64
+    }
+}
+
+/// `std_msgs/msg/Header`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Header {
+    pub stamp: Time,
+    pub frame_id: String,
+}
+
+/// `sensor_msgs/msg/PointField`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PointField {
+    pub name: String,
+    pub offset: u32,
+    pub datatype: u8,
+    pub count: u32,
+}
+
+impl PointField {
+    pub const INT8: u8 = 1;
+    pub const UINT8: u8 = 2;
+    pub const INT16: u8 = 3;
+    pub const UINT16: u8 = 4;
+    pub const INT32: u8 = 5;
+    pub const UINT32: u8 = 6;
+    pub const FLOAT32: u8 = 7;
+    pub const FLOAT64: u8 = 8;
+
+    fn new(name: &str, offset: u32, datatype: u8) -> Self {
+        Self {
+            name: name.to_string(),
+            offset,
+            datatype,
+            count: 1,
+        }
+    }
+}
+
+/// `sensor_msgs/msg/PointCloud2`.
+///
+/// Clouds built from frames use the point layout of the ouster-ros
+/// driver.
+///
+/// | field        | offset | type    | content                            |
+/// |--------------|--------|---------|------------------------------------|
+/// | x, y, z      | 0..12  | float32 | position in meters                 |
+/// | intensity    | 16     | float32 | signal photons                     |
+/// | t            | 20     | uint32  | nanoseconds since the header stamp |
+/// | reflectivity | 24     | uint16  | reflectivity                       |
+/// | ring         | 26     | uint16  | laser ID                           |
+/// | ambient      | 28     | uint16  | noise photons                      |
+/// | range        | 32     | uint32  | distance in millimeters            |
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PointCloud2 {
+    pub header: Header,
+    pub height: u32,
+    pub width: u32,
+    pub fields: Vec<PointField>,
+    pub is_bigendian: bool,
+    pub point_step: u32,
+    pub row_step: u32,
+    pub data: Vec<u8>,
+    pub is_dense: bool,
+}
+
+impl PointCloud2 {
+    /// Size of a point in bytes.
+    pub const POINT_STEP: u32 = 48;
+
+    /// Returns fields of the ouster-ros point layout.
+    pub fn ouster_fields() -> Vec<PointField> {
+        vec![
+            PointField::new("x", 0, PointField::FLOAT32),
+            PointField::new("y", 4, PointField::FLOAT32),
+            PointField::new("z", 8, PointField::FLOAT32),
+            PointField::new("intensity", 16, PointField::FLOAT32),
+            PointField::new("t", 20, PointField::UINT32),
+            PointField::new("reflectivity", 24, PointField::UINT16),
+            PointField::new("ring", 26, PointField::UINT16),
+            PointField::new("ambient", 28, PointField::UINT16),
+            PointField::new("range", 32, PointField::UINT32),
+        ]
+    }
+
+    /// Builds an unorganized cloud with points in frame order.
+    pub fn from_frame(frame: &Frame, frame_id: &str) -> Self {
+        let stamp = frame.start_time().as_nanos() as u64;
+        let mut data = Vec::with_capacity(frame.points.len() * Self::POINT_STEP as usize);
+        frame
+            .points
+            .iter()
+            .for_each(|point| write_point(&mut data, point, stamp));
+
+        Self::new(frame_id, stamp, frame.points.len() as u32, 1, data, true)
+    }
+
+    /// Builds an organized cloud of [PIXELS_PER_COLUMN] rows by
+    /// `columns_per_revolution` columns as ouster-ros does. Missing
+    /// pixels are filled with zeros.
+    pub fn from_frame_organized(
+        frame: &Frame,
+        columns_per_revolution: u16,
+        frame_id: &str,
+    ) -> Self {
+        let stamp = frame.start_time().as_nanos() as u64;
+        let index = OrganizedIndex::from_frame(frame, columns_per_revolution);
+        let width = index.width();
+        let mut data = Vec::with_capacity(width * PIXELS_PER_COLUMN * Self::POINT_STEP as usize);
+        let mut is_dense = true;
+
+        for row in 0..index.height() {
+            for col in 0..width {
+                match index.get(row, col) {
+                    Some(point_index) => write_point(&mut data, &frame.points[point_index], stamp),
+                    None => {
+                        is_dense = false;
+                        data.resize(data.len() + Self::POINT_STEP as usize, 0);
+                    }
+                }
+            }
+        }
+
+        Self::new(
+            frame_id,
+            stamp,
+            width as u32,
+            index.height() as u32,
+            data,
+            is_dense,
+        )
+    }
+
+    fn new(
+        frame_id: &str,
+        stamp: u64,
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+        is_dense: bool,
+    ) -> Self {
+        Self {
+            header: Header {
+                stamp: Time::from_nanos(stamp),
+                frame_id: frame_id.to_string(),
+            },
+            height,
+            width,
+            fields: Self::ouster_fields(),
+            is_bigendian: false,
+            point_step: Self::POINT_STEP,
+            row_step: Self::POINT_STEP * width,
+            data,
+            is_dense,
+        }
+    }
+
+    /// Returns the number of points.
+    pub fn len(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Returns true if the cloud has no points.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the position in meters of the point at `index`,
+    /// assuming the ouster-ros layout.
+    pub fn position(&self, index: usize) -> [f32; 3] {
+        let offset = index * self.point_step as usize;
+        [0, 4, 8].map(|field| {
+            let start = offset + field;
+            f32::from_le_bytes(self.data[start..(start + 4)].try_into().unwrap())
+        })
+    }
+}
+
+fn write_point(data: &mut Vec<u8>, point: &Point, stamp: u64) {
+    let [x, y, z] = point.point.map(|v| v.as_meters() as f32);
+    let t = (point.timestamp.as_nanos() as u64).saturating_sub(stamp) as u32;
+    let range = point.distance.as_millimeters().round() as u32;
+
+    data.extend_from_slice(&x.to_le_bytes());
+    data.extend_from_slice(&y.to_le_bytes());
+    data.extend_from_slice(&z.to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&(point.signal_photons as f32).to_le_bytes());
+    data.extend_from_slice(&t.to_le_bytes());
+    data.extend_from_slice(&point.reflectivity.to_le_bytes());
+    data.extend_from_slice(&(point.laser_id as u16).to_le_bytes());
+    data.extend_from_slice(&point.noise_photons.to_le_bytes());
+    data.extend_from_slice(&[0; 2]);
+    data.extend_from_slice(&range.to_le_bytes());
+    data.extend_from_slice(&[0; 12]);
+}
+
+/// `geometry_msgs/msg/Vector3`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Vector3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl From<[f64; 3]> for Vector3 {
+    fn from([x, y, z]: [f64; 3]) -> Self {
+        Self { x, y, z }
+    }
+}
+
+/// `geometry_msgs/msg/Quaternion`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quaternion {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub w: f64,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        }
+    }
+}
+
+/// `sensor_msgs/msg/Imu`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Imu {
+    pub header: Header,
+    pub orientation: Quaternion,
+    pub orientation_covariance: [f64; 9],
+    pub angular_velocity: Vector3,
+    pub angular_velocity_covariance: [f64; 9],
+    pub linear_acceleration: Vector3,
+    pub linear_acceleration_covariance: [f64; 9],
+}
+
+impl Imu {
+    /// Builds the message from an IMU packet. The orientation is
+    /// marked unknown by a covariance of -1 as the sensor does not
+    /// estimate it.
+    pub fn from_packet(packet: &ImuPacket, frame_id: &str) -> Self {
+        let mut orientation_covariance = [0.0; 9];
+        orientation_covariance[0] = -1.0;
+
+        Self {
+            header: Header {
+                stamp: Time::from_nanos(packet.gyro_timestamp),
+                frame_id: frame_id.to_string(),
+            },
+            orientation: Quaternion::default(),
+            orientation_covariance,
+            angular_velocity: packet.angular_velocity_radps().into(),
+            angular_velocity_covariance: [0.0; 9],
+            linear_acceleration: packet.linear_acceleration_mps2().into(),
+            linear_acceleration_covariance: [0.0; 9],
+        }
+    }
+}
+
+/// `ouster_sensor_msgs/msg/PacketMsg`, the raw bytes of a lidar or IMU
+/// packet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct PacketMsg {
+    pub buf: Vec<u8>,
+}
+
+impl PacketMsg {
+    /// Decodes the bytes as a lidar packet.
+    pub fn to_packet(&self) -> Result<Packet> {
+        Ok(*Packet::from_slice(&self.buf)?)
+    }
+
+    /// Decodes the bytes as an IMU packet.
+    pub fn to_imu_packet(&self) -> Result<ImuPacket> {
+        Ok(*ImuPacket::from_slice(&self.buf)?)
+    }
+}
+
+impl From<&Packet> for PacketMsg {
+    fn from(packet: &Packet) -> Self {
+        Self {
+            buf: packet.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl From<&ImuPacket> for PacketMsg {
+    fn from(packet: &ImuPacket) -> Self {
+        Self {
+            buf: packet.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Pixel fields of a frame that can be rendered as images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ImageKind {
+    /// Distance in meters as `32FC1`.
+    Range,
+    /// Signal photons as `mono16`.
+    Signal,
+    /// Reflectivity as `mono16`.
+    Reflectivity,
+    /// Noise photons as `mono16`.
+    Noise,
+}
+
+impl ImageKind {
+    /// All kinds in the order of publication.
+    pub const ALL: [ImageKind; 4] = [
+        ImageKind::Range,
+        ImageKind::Signal,
+        ImageKind::Reflectivity,
+        ImageKind::Noise,
+    ];
+
+    /// Returns the image encoding.
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Self::Range => "32FC1",
+            Self::Signal | Self::Reflectivity | Self::Noise => "mono16",
+        }
+    }
+
+    /// Returns the number of bytes per pixel.
+    pub fn pixel_size(&self) -> usize {
+        match self {
+            Self::Range => 4,
+            Self::Signal | Self::Reflectivity | Self::Noise => 2,
+        }
+    }
+}
+
+/// `sensor_msgs/msg/Image`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Image {
+    pub header: Header,
+    pub height: u32,
+    pub width: u32,
+    pub encoding: String,
+    pub is_bigendian: u8,
+    pub step: u32,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    /// Builds the image of [PIXELS_PER_COLUMN] rows by
+    /// `columns_per_revolution` columns from a frame. Columns are
+    /// measurement IDs without destaggering, and missing pixels are
+    /// zeros.
+    pub fn from_frame(
+        frame: &Frame,
+        columns_per_revolution: u16,
+        kind: ImageKind,
+        frame_id: &str,
+    ) -> Self {
+        let index = OrganizedIndex::from_frame(frame, columns_per_revolution);
+        let (width, height) = (index.width(), index.height());
+        let pixel_size = kind.pixel_size();
+        let mut data = vec![0; width * height * pixel_size];
+
+        for row in 0..height {
+            for col in 0..width {
+                let point = match index.get(row, col) {
+                    Some(point_index) => &frame.points[point_index],
+                    None => continue,
+                };
+                let offset = (row * width + col) * pixel_size;
+                let pixel = &mut data[offset..(offset + pixel_size)];
+                match kind {
+                    ImageKind::Range => {
+                        pixel.copy_from_slice(&(point.distance.as_meters() as f32).to_le_bytes())
+                    }
+                    ImageKind::Signal => pixel.copy_from_slice(&point.signal_photons.to_le_bytes()),
+                    ImageKind::Reflectivity => {
+                        pixel.copy_from_slice(&point.reflectivity.to_le_bytes())
+                    }
+                    ImageKind::Noise => pixel.copy_from_slice(&point.noise_photons.to_le_bytes()),
+                }
+            }
+        }
+
+        Self {
+            header: Header {
+                stamp: Time::from_nanos(frame.start_time().as_nanos() as u64),
+                frame_id: frame_id.to_string(),
+            },
+            height: height as u32,
+            width: width as u32,
+            encoding: kind.encoding().to_string(),
+            is_bigendian: 0,
+            step: (width * pixel_size) as u32,
+            data,
+        }
+    }
+}
+
+/// DDS topic names of ROS 2 messages, e.g., `rt/ouster/points`
+/// for the ROS topic `/ouster/points`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Ros2Topics {
+    pub points: String,
+    pub imu: String,
+    pub range_image: String,
+    pub signal_image: String,
+    pub reflectivity_image: String,
+    pub noise_image: String,
+}
+
+impl Ros2Topics {
+    /// Returns the topic of an image kind.
+    pub fn image(&self, kind: ImageKind) -> &str {
+        match kind {
+            ImageKind::Range => &self.range_image,
+            ImageKind::Signal => &self.signal_image,
+            ImageKind::Reflectivity => &self.reflectivity_image,
+            ImageKind::Noise => &self.noise_image,
+        }
+    }
+}
+
+impl Default for Ros2Topics {
+    fn default() -> Self {
+        Self {
+            points: "rt/ouster/points".into(),
+            imu: "rt/ouster/imu".into(),
+            range_image: "rt/ouster/range_image".into(),
+            signal_image: "rt/ouster/signal_image".into(),
+            reflectivity_image: "rt/ouster/reflec_image".into(),
+            noise_image: "rt/ouster/nearir_image".into(),
+        }
+    }
+}
+
+/// Configuration of [Ros2Publisher].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Ros2Config {
+    pub domain_id: u16,
+    pub qos: DdsQos,
+    pub topics: Ros2Topics,
+    /// Frame ID put in headers of point clouds and images.
+    pub lidar_frame_id: String,
+    /// Frame ID put in headers of IMU messages.
+    pub imu_frame_id: String,
+    /// Publish organized clouds of beam rows × columns.
+    pub organized: bool,
+    /// Publish range, signal, reflectivity and noise images of frames.
+    pub images: bool,
+}
+
+impl Default for Ros2Config {
+    fn default() -> Self {
+        Self {
+            domain_id: 0,
+            // Same as the ROS 2 sensor data profile
+            qos: DdsQos {
+                history_depth: Some(5),
+                ..Default::default()
+            },
+            topics: Ros2Topics::default(),
+            lidar_frame_id: "os_lidar".into(),
+            imu_frame_id: "os_imu".into(),
+            organized: true,
+            images: true,
+        }
+    }
+}
+
+type Writer<D> = DataWriter<D, CDRSerializerAdapter<D>>;
+
+/// Publishes ROS 2 messages built from sensor data.
+pub struct Ros2Publisher {
+    config: Ros2Config,
+    participant: DomainParticipant,
+    points_writer: Writer<PointCloud2>,
+    imu_writer: Writer<Imu>,
+    image_writers: Vec<(ImageKind, Writer<Image>)>,
+}
+
+impl Ros2Publisher {
+    /// Joins the configured domain and creates writers.
+    pub fn new(config: Ros2Config) -> Result<Self> {
+        let participant = DomainParticipant::new(config.domain_id)?;
+        Self::with_participant(participant, config)
+    }
+
+    /// Creates writers on an existing domain participant.
+    /// The `domain_id` in config is ignored.
+    pub fn with_participant(participant: DomainParticipant, config: Ros2Config) -> Result<Self> {
+        let qos = config.qos.to_policies();
+        let publisher = participant.create_publisher(&qos)?;
+        let create_topic = |name: &str, type_name: &str| {
+            participant.create_topic(
+                name.to_string(),
+                type_name.to_string(),
+                &qos,
+                TopicKind::NoKey,
+            )
+        };
+
+        let points_topic = create_topic(
+            &config.topics.points,
+            "sensor_msgs::msg::dds_::PointCloud2_",
+        )?;
+        let imu_topic = create_topic(&config.topics.imu, "sensor_msgs::msg::dds_::Imu_")?;
+        let image_writers = ImageKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let topic =
+                    create_topic(config.topics.image(kind), "sensor_msgs::msg::dds_::Image_")?;
+                Ok((kind, publisher.create_datawriter_no_key_cdr(&topic, None)?))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            points_writer: publisher.create_datawriter_no_key_cdr(&points_topic, None)?,
+            imu_writer: publisher.create_datawriter_no_key_cdr(&imu_topic, None)?,
+            image_writers,
+            participant,
+            config,
+        })
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &Ros2Config {
+        &self.config
+    }
+
+    /// Returns the domain participant.
+    pub fn participant(&self) -> &DomainParticipant {
+        &self.participant
+    }
+
+    /// Builds the point cloud of a frame with `columns_per_revolution`
+    /// columns according to the configuration.
+    pub fn point_cloud(&self, frame: &Frame, columns_per_revolution: u16) -> PointCloud2 {
+        if self.config.organized {
+            PointCloud2::from_frame_organized(
+                frame,
+                columns_per_revolution,
+                &self.config.lidar_frame_id,
+            )
+        } else {
+            PointCloud2::from_frame(frame, &self.config.lidar_frame_id)
+        }
+    }
+
+    /// Publishes the point cloud of a frame.
+    pub fn publish_points(&self, frame: &Frame, columns_per_revolution: u16) -> Result<()> {
+        self.points_writer
+            .write(self.point_cloud(frame, columns_per_revolution), None)?;
+        Ok(())
+    }
+
+    /// Publishes all images of a frame.
+    pub fn publish_images(&self, frame: &Frame, columns_per_revolution: u16) -> Result<()> {
+        for (kind, writer) in self.image_writers.iter() {
+            let image = Image::from_frame(
+                frame,
+                columns_per_revolution,
+                *kind,
+                &self.config.lidar_frame_id,
+            );
+            writer.write(image, None)?;
+        }
+        Ok(())
+    }
+
+    /// Publishes the point cloud, and images if enabled, of a frame.
+    pub fn publish_frame(&self, frame: &Frame, columns_per_revolution: u16) -> Result<()> {
+        self.publish_points(frame, columns_per_revolution)?;
+        if self.config.images {
+            self.publish_images(frame, columns_per_revolution)?;
+        }
+        Ok(())
+    }
+
+    /// Publishes an IMU packet.
+    pub fn publish_imu(&self, packet: &ImuPacket) -> Result<()> {
+        self.imu_writer
+            .write(Imu::from_packet(packet, &self.config.imu_frame_id), None)?;
+        Ok(())
+    }
+}
+
+impl Debug for Ros2Publisher {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Ros2Publisher")
+            .field("config", &self.config)
+            .finish()
+    }
+}
@@ -0,0 +1,209 @@
+//! Streaming packets from a live sensor.
+
+use super::{
+    client::{CommandClient, ConfigText},
+    config::Config,
+    enums::LidarMode,
+    packet::{ImuPacket, Packet},
+};
+use crate::common::*;
+use log::warn;
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+};
+
+/// TCP port of the sensor command API.
+pub const SENSOR_COMMAND_PORT: u16 = 7501;
+
+const MAX_UDP_PACKET_SIZE: usize = 65507;
+
+fn default_timeout() -> u64 {
+    10
+}
+
+/// Connection settings of a sensor.
+///
+/// It can be loaded from a TOML file like
+///
+/// ```toml
+/// lidar_addr = "169.254.161.176"
+/// listen_addr = "169.254.50.41"
+/// timeout = 10
+/// lidar_mode = "1024x10"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SensorConfig {
+    /// IP address of the sensor.
+    pub lidar_addr: Ipv4Addr,
+    /// IP address of this host where the sensor sends UDP packets to.
+    pub listen_addr: Ipv4Addr,
+    /// Timeout of sensor commands in seconds.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Lidar mode set on the sensor. The current mode is kept if unset.
+    #[serde(default)]
+    pub lidar_mode: Option<LidarMode>,
+    /// Receive IMU packets as well.
+    #[serde(default)]
+    pub imu: bool,
+}
+
+impl SensorConfig {
+    /// Creates config with default timeout.
+    pub fn new(lidar_addr: Ipv4Addr, listen_addr: Ipv4Addr) -> Self {
+        Self {
+            lidar_addr,
+            listen_addr,
+            timeout: default_timeout(),
+            lidar_mode: None,
+            imu: false,
+        }
+    }
+
+    /// Loads config TOML file from path.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+        Self::from_toml_str(&text)
+    }
+
+    /// Parses from TOML string.
+    pub fn from_toml_str(data: &str) -> Result<Self> {
+        let ret = toml::from_str(data)?;
+        Ok(ret)
+    }
+
+    /// Connects to the command API of the sensor.
+    pub fn connect_client(&self) -> Result<CommandClient> {
+        let address = SocketAddr::new(self.lidar_addr.into(), SENSOR_COMMAND_PORT);
+        CommandClient::connect(address, Some(Duration::from_secs(self.timeout)))
+    }
+}
+
+/// A sensor configured to stream packets to this host.
+#[derive(Debug)]
+pub struct Sensor {
+    client: CommandClient,
+    config_txt: ConfigText,
+    config: Config,
+    lidar_socket: UdpSocket,
+    imu_socket: Option<UdpSocket>,
+    buffer: Vec<u8>,
+}
+
+impl Sensor {
+    /// Connects to the sensor, points its UDP destination to this host
+    /// and binds the lidar and, if enabled, IMU sockets.
+    ///
+    /// The sensor is reinitialized to apply the settings, and the
+    /// config is built from the lidar mode it reports afterwards.
+    pub fn connect(sensor_config: &SensorConfig) -> Result<Self> {
+        let mut client = sensor_config.connect_client()?;
+        let config_txt = client.get_config_txt()?;
+
+        if let Some(lidar_mode) = &sensor_config.lidar_mode {
+            client.set_lidar_mode(lidar_mode.clone())?;
+        }
+        client.set_udp_ip(sensor_config.listen_addr)?;
+        client.set_udp_port_lidar(config_txt.udp_port_lidar)?;
+        if sensor_config.imu {
+            client.set_udp_port_imu(config_txt.udp_port_imu)?;
+        }
+        client.reinitialize()?;
+        let config_txt = client.get_config_txt()?;
+        if let Some(lidar_mode) = &sensor_config.lidar_mode {
+            if *lidar_mode != config_txt.lidar_mode {
+                warn!(
+                    "sensor runs in lidar mode {} instead of the requested {}",
+                    config_txt.lidar_mode, lidar_mode
+                );
+            }
+        }
+
+        config_txt.lidar_mode.checked_columns_per_revolution()?;
+
+        let beam_intrinsics = client.get_beam_intrinsics()?;
+        let mut config = Config::new(
+            beam_intrinsics.beam_altitude_angles,
+            beam_intrinsics.beam_azimuth_angles,
+            config_txt.lidar_mode.clone(),
+        );
+        config.azimuth_window(config_txt.azimuth_window.into());
+        config.lidar_origin_to_beam_origin_mm(beam_intrinsics.lidar_origin_to_beam_origin_mm.raw());
+
+        let lidar_socket = UdpSocket::bind((sensor_config.listen_addr, config_txt.udp_port_lidar))?;
+        lidar_socket.set_read_timeout(Some(Duration::from_millis(500)))?;
+        let imu_socket = if sensor_config.imu {
+            let socket = UdpSocket::bind((sensor_config.listen_addr, config_txt.udp_port_imu))?;
+            socket.set_nonblocking(true)?;
+            Some(socket)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            client,
+            config_txt,
+            config,
+            lidar_socket,
+            imu_socket,
+            buffer: vec![0; MAX_UDP_PACKET_SIZE],
+        })
+    }
+
+    /// Returns the command client.
+    pub fn client_mut(&mut self) -> &mut CommandClient {
+        &mut self.client
+    }
+
+    /// Returns the sensor configuration read on connection.
+    pub fn config_txt(&self) -> &ConfigText {
+        &self.config_txt
+    }
+
+    /// Returns the converter config built from beam intrinsics.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Waits up to 500 ms for the next lidar packet. Returns `None`
+    /// on timeout or if the datagram is not a lidar packet.
+    pub fn recv_packet(&mut self) -> Result<Option<Packet>> {
+        let read_size = match self.lidar_socket.recv_from(&mut self.buffer) {
+            Ok((read_size, _peer_addr)) => read_size,
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(None)
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let packet_size = mem::size_of::<Packet>();
+        match Packet::from_slice(&self.buffer[..read_size.min(packet_size)]) {
+            Ok(packet) => Ok(Some(*packet)),
+            Err(error) => {
+                warn!("packet decoding error: {:?}", error);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns IMU packets received so far without blocking.
+    pub fn recv_imu_packets(&mut self) -> Result<Vec<ImuPacket>> {
+        let socket = match &self.imu_socket {
+            Some(socket) => socket,
+            None => return Ok(vec![]),
+        };
+        let mut packets = vec![];
+        loop {
+            let read_size = match socket.recv_from(&mut self.buffer) {
+                Ok((read_size, _peer_addr)) => read_size,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error.into()),
+            };
+            match ImuPacket::from_slice(&self.buffer[..read_size]) {
+                Ok(packet) => packets.push(*packet),
+                Err(error) => warn!("IMU packet decoding error: {:?}", error),
+            }
+        }
+        Ok(packets)
+    }
+}
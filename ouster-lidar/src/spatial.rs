@@ -0,0 +1,146 @@
+//! Spatial index over point clouds.
+
+use super::pcd_converter::Point;
+use crate::{common::*, utils::squared_distance};
+use std::collections::BinaryHeap;
+
+/// A static 3-dimensional k-d tree for radius and k-nearest
+/// neighbor queries.
+///
+/// Query results refer to points by their indices in the
+/// slice that the tree is built from.
+#[derive(Debug, Clone)]
+pub struct KdTree {
+    positions: Vec<[f64; 3]>,
+    /// Point indices arranged so that the median of each
+    /// range is the splitting node of that subtree.
+    order: Vec<usize>,
+}
+
+impl KdTree {
+    /// Builds the tree from points.
+    pub fn new(points: &[Point]) -> Self {
+        let positions = points
+            .iter()
+            .map(|point| point.point.map(|v| v.as_meters()))
+            .collect();
+        Self::from_positions(positions)
+    }
+
+    /// Builds the tree from positions in meters.
+    pub fn from_positions(positions: Vec<[f64; 3]>) -> Self {
+        let mut order: Vec<usize> = (0..positions.len()).collect();
+        build(&positions, &mut order, 0);
+        Self { positions, order }
+    }
+
+    /// Returns the number of indexed points.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns true if the tree has no points.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Returns the position of indexed point in meters.
+    pub fn position(&self, index: usize) -> [f64; 3] {
+        self.positions[index]
+    }
+
+    /// Finds indices of points within `radius` meters from `query`.
+    /// The output is not sorted.
+    pub fn radius_search(&self, query: [f64; 3], radius: f64) -> Vec<usize> {
+        let mut output = vec![];
+        self.radius_search_into(query, radius, &mut output);
+        output
+    }
+
+    /// Same as [radius_search](KdTree::radius_search), but appends
+    /// results to a caller-provided buffer.
+    pub fn radius_search_into(&self, query: [f64; 3], radius: f64, output: &mut Vec<usize>) {
+        let radius_sq = radius * radius;
+        self.visit(&self.order, 0, query, &mut |index, dist_sq| {
+            if dist_sq <= radius_sq {
+                output.push(index);
+            }
+            radius_sq
+        });
+    }
+
+    /// Finds `k` nearest points to `query` as `(index, distance)`
+    /// pairs sorted by ascending distance in meters.
+    pub fn nearest_neighbors(&self, query: [f64; 3], k: usize) -> Vec<(usize, f64)> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let mut heap: BinaryHeap<(R64, usize)> = BinaryHeap::with_capacity(k + 1);
+        self.visit(&self.order, 0, query, &mut |index, dist_sq| {
+            heap.push((R64::new(dist_sq), index));
+            if heap.len() > k {
+                heap.pop();
+            }
+            if heap.len() == k {
+                heap.peek().unwrap().0.raw()
+            } else {
+                f64::INFINITY
+            }
+        });
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(dist_sq, index)| (index, dist_sq.raw().sqrt()))
+            .collect()
+    }
+
+    /// Traverses the subtree in `order`. The callback receives each
+    /// candidate with its squared distance and returns the current
+    /// squared search bound.
+    fn visit<F>(&self, order: &[usize], depth: usize, query: [f64; 3], callback: &mut F) -> f64
+    where
+        F: FnMut(usize, f64) -> f64,
+    {
+        if order.is_empty() {
+            return f64::INFINITY;
+        }
+
+        let axis = depth % 3;
+        let mid = order.len() / 2;
+        let index = order[mid];
+        let position = self.positions[index];
+        let mut bound = callback(index, squared_distance(position, query));
+
+        let diff = query[axis] - position[axis];
+        let (near, far) = if diff < 0.0 {
+            (&order[..mid], &order[(mid + 1)..])
+        } else {
+            (&order[(mid + 1)..], &order[..mid])
+        };
+
+        bound = bound.min(self.visit(near, depth + 1, query, callback));
+        if diff * diff <= bound {
+            bound = bound.min(self.visit(far, depth + 1, query, callback));
+        }
+        bound
+    }
+}
+
+fn build(positions: &[[f64; 3]], order: &mut [usize], depth: usize) {
+    if order.len() <= 1 {
+        return;
+    }
+
+    let axis = depth % 3;
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by(mid, |lhs, rhs| {
+        positions[*lhs][axis]
+            .partial_cmp(&positions[*rhs][axis])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let (left, right) = order.split_at_mut(mid);
+    build(positions, left, depth + 1);
+    build(positions, &mut right[1..], depth + 1);
+}
@@ -0,0 +1,347 @@
+//! Interpretation of sensor timestamps.
+//!
+//! Depending on [TimestampMode], column timestamps are either the time
+//! since the sensor booted or an absolute time.
+//!
+//! | mode                      | lock                 | timestamps               |
+//! |---------------------------|----------------------|--------------------------|
+//! | `TIME_FROM_INTERNAL_OSC`  |                      | since boot               |
+//! | `TIME_FROM_SYNC_PULSE_IN` | NMEA unlocked        | since boot               |
+//! | `TIME_FROM_SYNC_PULSE_IN` | NMEA locked          | UTC plus leap seconds    |
+//! | `TIME_FROM_PTP_1588`      | PTP unlocked         | since boot               |
+//! | `TIME_FROM_PTP_1588`      | PTP locked           | TAI from the PTP master  |
+//!
+//! [TimeSyncStatus] reads the mode and lock states from [TimeInfo].
+//! [TimeConverter] converts timestamps to UTC and TAI. When timestamps
+//! are not absolute, it maps them to host time with a sensor-to-host
+//! clock offset and drift estimated by [ClockEstimator] from packet
+//! arrival times.
+
+use super::{
+    client::TimeInfo,
+    enums::TimestampMode,
+    packet::{Column, Packet},
+};
+use crate::common::*;
+use std::collections::VecDeque;
+
+/// Difference of TAI to UTC in seconds since 2017.
+pub const TAI_UTC_OFFSET_SECS: u64 = 37;
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+/// Time scale of absolute timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeScale {
+    /// UTC shifted by a number of leap seconds. It is UTC if the shift
+    /// is zero.
+    Utc { leap_seconds: u64 },
+    /// International atomic time.
+    Tai,
+}
+
+/// What column timestamps are relative to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeReference {
+    /// Time since the sensor booted.
+    SinceBoot,
+    /// Time since the Unix epoch on a time scale.
+    Absolute(TimeScale),
+}
+
+/// Time synchronization state of a sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TimeSyncStatus {
+    pub mode: TimestampMode,
+    pub sync_pulse_in_locked: bool,
+    pub nmea_locked: bool,
+    /// Whether the sensor follows a PTP master. False if the firmware
+    /// reports no PTP state.
+    pub ptp_locked: bool,
+    /// Leap seconds added to NMEA time.
+    pub leap_seconds: u64,
+    pub reference: TimeReference,
+}
+
+impl TimeSyncStatus {
+    /// Interprets time info of the sensor.
+    pub fn from_time_info(time_info: &TimeInfo) -> Self {
+        let mode = time_info.timestamp.mode;
+        let sync_pulse_in_locked = time_info.sync_pulse_in.locked;
+        let nmea = &time_info.multipurpose_io.nmea;
+        let ptp_locked = time_info.ptp.as_ref().is_some_and(|ptp| ptp.locked());
+        let reference = match mode {
+            TimestampMode::TimeFromInternalOsc => TimeReference::SinceBoot,
+            TimestampMode::TimeFromSyncPulseIn if nmea.locked => {
+                TimeReference::Absolute(TimeScale::Utc {
+                    leap_seconds: nmea.leap_seconds,
+                })
+            }
+            TimestampMode::TimeFromSyncPulseIn => TimeReference::SinceBoot,
+            TimestampMode::TimeFromPtp1588 if ptp_locked => TimeReference::Absolute(TimeScale::Tai),
+            TimestampMode::TimeFromPtp1588 => TimeReference::SinceBoot,
+        };
+
+        Self {
+            mode,
+            sync_pulse_in_locked,
+            nmea_locked: nmea.locked,
+            ptp_locked,
+            leap_seconds: nmea.leap_seconds,
+            reference,
+        }
+    }
+
+    /// Returns if timestamps are time since the Unix epoch.
+    pub fn is_absolute(&self) -> bool {
+        matches!(self.reference, TimeReference::Absolute(_))
+    }
+}
+
+/// A linear mapping from sensor time to host time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClockEstimate {
+    /// Sensor time in nanoseconds where the offset is taken.
+    pub reference: u64,
+    /// Host time minus sensor time at the reference in nanoseconds.
+    pub offset_ns: i64,
+    /// Host clock advance per sensor clock advance, minus one.
+    pub drift: f64,
+}
+
+impl ClockEstimate {
+    /// Maps sensor time to host time in nanoseconds.
+    pub fn to_host(&self, sensor_time: u64) -> i64 {
+        let elapsed = sensor_time as i64 - self.reference as i64;
+        sensor_time as i64 + self.offset_ns + (elapsed as f64 * self.drift).round() as i64
+    }
+}
+
+/// Estimates sensor-to-host clock offset and drift from arrival times.
+///
+/// Arrival times lag sensor time by a varying network and scheduling
+/// delay. The estimator keeps the least delayed sample per bucket of
+/// sensor time and fits a line to those minima, so that the estimate
+/// follows the lower envelope of the delay.
+#[derive(Debug, Clone)]
+pub struct ClockEstimator {
+    bucket: Duration,
+    max_buckets: usize,
+    /// Bucket start and the least delayed sample as sensor time and
+    /// offset.
+    current: Option<(u64, (u64, i64))>,
+    minima: VecDeque<(u64, i64)>,
+}
+
+impl Default for ClockEstimator {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), 60)
+    }
+}
+
+impl ClockEstimator {
+    /// Creates an estimator that fits the minima of up to `max_buckets`
+    /// buckets, each spanning `bucket` of sensor time.
+    pub fn new(bucket: Duration, max_buckets: usize) -> Self {
+        Self {
+            bucket,
+            max_buckets: max_buckets.max(1),
+            current: None,
+            minima: VecDeque::new(),
+        }
+    }
+
+    /// Pushes a sample of sensor time and host arrival time in
+    /// nanoseconds.
+    ///
+    /// Samples less than a bucket older than the current bucket, such
+    /// as reordered packets, are dropped. An older sample means that
+    /// the sensor clock went back, e.g. on reboot, and restarts the
+    /// estimation.
+    pub fn push(&mut self, sensor_time: u64, host_time: u64) {
+        let offset = host_time as i64 - sensor_time as i64;
+        let bucket = self.bucket.as_nanos() as u64;
+        match &mut self.current {
+            Some((start, min)) if sensor_time >= *start && sensor_time - *start < bucket => {
+                if offset < min.1 {
+                    *min = (sensor_time, offset);
+                }
+            }
+            // A late sample, delayed more than the samples after it
+            Some((start, _)) if sensor_time < *start && *start - sensor_time <= bucket => {}
+            Some((start, min)) => {
+                if sensor_time < *start {
+                    self.minima.clear();
+                } else {
+                    self.minima.push_back(*min);
+                    if self.minima.len() > self.max_buckets {
+                        self.minima.pop_front();
+                    }
+                }
+                self.current = Some((sensor_time, (sensor_time, offset)));
+            }
+            None => self.current = Some((sensor_time, (sensor_time, offset))),
+        }
+    }
+
+    /// Pushes the last valid column of a packet received at host time.
+    pub fn push_packet(&mut self, packet: &Packet, host_time: u64) {
+        if let Some(column) = packet.columns.iter().rev().find(|column| column.valid()) {
+            self.push(column.timestamp, host_time);
+        }
+    }
+
+    /// Returns the estimate, or `None` before any sample.
+    pub fn estimate(&self) -> Option<ClockEstimate> {
+        let (_, current) = self.current?;
+        let samples: Vec<_> = self
+            .minima
+            .iter()
+            .copied()
+            .chain(iter::once(current))
+            .collect();
+        let (reference, reference_offset) = *samples.last()?;
+
+        // Least squares of offsets over sensor time around the reference
+        let count = samples.len() as f64;
+        let points: Vec<(f64, f64)> = samples
+            .iter()
+            .map(|&(time, offset)| {
+                let x = (time as i64 - reference as i64) as f64;
+                let y = (offset - reference_offset) as f64;
+                (x, y)
+            })
+            .collect();
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+        let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        let sxy: f64 = points
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum();
+        let drift = if sxx > 0.0 { sxy / sxx } else { 0.0 };
+        let intercept = mean_y - drift * mean_x;
+
+        Some(ClockEstimate {
+            reference,
+            offset_ns: reference_offset + intercept.round() as i64,
+            drift,
+        })
+    }
+
+    /// Forgets all samples.
+    pub fn reset(&mut self) {
+        self.current = None;
+        self.minima.clear();
+    }
+}
+
+/// Converts column timestamps to UTC and TAI.
+#[derive(Debug, Clone)]
+pub struct TimeConverter {
+    status: TimeSyncStatus,
+    tai_utc_offset: u64,
+    estimator: ClockEstimator,
+}
+
+impl TimeConverter {
+    /// Creates a converter for the sync status. Host clocks are assumed
+    /// to be on UTC.
+    pub fn new(status: TimeSyncStatus) -> Self {
+        Self {
+            status,
+            tai_utc_offset: TAI_UTC_OFFSET_SECS,
+            estimator: ClockEstimator::default(),
+        }
+    }
+
+    /// Creates a converter from time info of the sensor.
+    pub fn from_time_info(time_info: &TimeInfo) -> Self {
+        Self::new(TimeSyncStatus::from_time_info(time_info))
+    }
+
+    /// Sets the difference of TAI to UTC in seconds.
+    pub fn with_tai_utc_offset(mut self, seconds: u64) -> Self {
+        self.tai_utc_offset = seconds;
+        self
+    }
+
+    /// Sets the clock estimator used for relative timestamps.
+    pub fn with_estimator(mut self, estimator: ClockEstimator) -> Self {
+        self.estimator = estimator;
+        self
+    }
+
+    pub fn status(&self) -> &TimeSyncStatus {
+        &self.status
+    }
+
+    /// Updates the sync status, e.g. after the NMEA lock changes. The
+    /// clock estimate is kept only if the time reference is unchanged.
+    pub fn set_status(&mut self, status: TimeSyncStatus) {
+        if status.reference != self.status.reference {
+            self.estimator.reset();
+        }
+        self.status = status;
+    }
+
+    /// Returns the sensor-to-host clock estimate.
+    pub fn clock_estimate(&self) -> Option<ClockEstimate> {
+        self.estimator.estimate()
+    }
+
+    /// Pushes a packet received at Unix time `host_time` in nanoseconds.
+    /// It only feeds the clock estimator if timestamps are not
+    /// absolute.
+    pub fn push_packet(&mut self, packet: &Packet, host_time: u64) {
+        if !self.status.is_absolute() {
+            self.estimator.push_packet(packet, host_time);
+        }
+    }
+
+    /// Converts a timestamp to UTC nanoseconds since the Unix epoch.
+    /// Returns `None` if timestamps are not absolute and no packet has
+    /// been pushed.
+    pub fn utc_nanos(&self, timestamp: u64) -> Option<i64> {
+        let nanos = match self.status.reference {
+            TimeReference::SinceBoot => self.estimator.estimate()?.to_host(timestamp),
+            TimeReference::Absolute(TimeScale::Utc { leap_seconds }) => {
+                timestamp as i64 - leap_seconds as i64 * NANOS_PER_SEC
+            }
+            TimeReference::Absolute(TimeScale::Tai) => {
+                timestamp as i64 - self.tai_utc_offset as i64 * NANOS_PER_SEC
+            }
+        };
+        Some(nanos)
+    }
+
+    /// Converts a timestamp to TAI nanoseconds since the Unix epoch.
+    pub fn tai_nanos(&self, timestamp: u64) -> Option<i64> {
+        match self.status.reference {
+            TimeReference::Absolute(TimeScale::Tai) => Some(timestamp as i64),
+            _ => Some(self.utc_nanos(timestamp)? + self.tai_utc_offset as i64 * NANOS_PER_SEC),
+        }
+    }
+
+    /// Converts a timestamp to UTC date and time.
+    pub fn utc_datetime(&self, timestamp: u64) -> Option<NaiveDateTime> {
+        datetime(self.utc_nanos(timestamp)?)
+    }
+
+    /// Converts a timestamp to TAI date and time.
+    pub fn tai_datetime(&self, timestamp: u64) -> Option<NaiveDateTime> {
+        datetime(self.tai_nanos(timestamp)?)
+    }
+
+    /// Converts the timestamp of a column to UTC date and time.
+    pub fn column_datetime(&self, column: &Column) -> Option<NaiveDateTime> {
+        let timestamp = column.timestamp;
+        self.utc_datetime(timestamp)
+    }
+}
+
+fn datetime(nanos: i64) -> Option<NaiveDateTime> {
+    let secs = nanos.div_euclid(NANOS_PER_SEC);
+    let nsecs = nanos.rem_euclid(NANOS_PER_SEC);
+    chrono::DateTime::from_timestamp(secs, nsecs as u32).map(|datetime| datetime.naive_utc())
+}
@@ -0,0 +1,110 @@
+//! Precomputed lookup table to convert ranges to Cartesian points.
+
+use super::{config::Config, consts::PIXELS_PER_COLUMN};
+use crate::common::*;
+use std::f64::consts::PI;
+
+/// Unit direction vectors and offsets of every (column, beam) pair of
+/// a lidar mode.
+///
+/// A point is `range * direction + offset`, where the offset accounts
+/// for the distance from the lidar origin to the beam origin. The
+/// column azimuth is taken from the measurement ID. Values are stored
+/// as one array per coordinate, so that the loop over the beams of a
+/// column is vectorized by the compiler.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XyzLut {
+    columns_per_revolution: u16,
+    direction: [Vec<f32>; 3],
+    offset: [Vec<f32>; 3],
+}
+
+impl XyzLut {
+    /// Computes the table for the beam intrinsics and lidar mode of a
+    /// config. It fails if the lidar mode has unknown dimensions.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let columns = config.lidar_mode.checked_columns_per_revolution()?;
+        let beam_offset = config.lidar_origin_to_beam_origin_mm.raw() / 1000.0;
+        let len = columns as usize * PIXELS_PER_COLUMN;
+        let mut direction = [(); 3].map(|_| Vec::with_capacity(len));
+        let mut offset = [(); 3].map(|_| Vec::with_capacity(len));
+
+        for measurement_id in 0..columns {
+            let encoder_angle = PI * 2.0 * measurement_id as f64 / columns as f64;
+            let (encoder_sin, encoder_cos) = (PI * 2.0 - encoder_angle).sin_cos();
+
+            for (altitude, azimuth_correction) in izip!(
+                config.beam_altitude_angles.iter(),
+                config.beam_azimuth_angle_corrections.iter()
+            ) {
+                let polar = PI / 2.0 - altitude.raw().to_radians();
+                let azimuth = PI * 2.0 - (encoder_angle + azimuth_correction.raw().to_radians());
+                let unit = [
+                    polar.sin() * azimuth.cos(),
+                    polar.sin() * azimuth.sin(),
+                    polar.cos(),
+                ];
+                let beam_origin = [encoder_cos, encoder_sin, 0.0];
+                for axis in 0..3 {
+                    direction[axis].push(unit[axis] as f32);
+                    offset[axis].push((beam_offset * (beam_origin[axis] - unit[axis])) as f32);
+                }
+            }
+        }
+
+        Ok(Self {
+            columns_per_revolution: columns,
+            direction,
+            offset,
+        })
+    }
+
+    /// Returns the number of columns in the table.
+    pub fn columns_per_revolution(&self) -> u16 {
+        self.columns_per_revolution
+    }
+
+    /// Returns the unit direction of a beam in a column.
+    pub fn direction(&self, measurement_id: u16, laser_id: usize) -> [f32; 3] {
+        let index = measurement_id as usize * PIXELS_PER_COLUMN + laser_id;
+        [0, 1, 2].map(|axis| self.direction[axis][index])
+    }
+
+    /// Returns the offset of a beam in a column in meters.
+    pub fn offset(&self, measurement_id: u16, laser_id: usize) -> [f32; 3] {
+        let index = measurement_id as usize * PIXELS_PER_COLUMN + laser_id;
+        [0, 1, 2].map(|axis| self.offset[axis][index])
+    }
+
+    /// Computes the X, Y and Z coordinates in meters of the ranges in
+    /// millimeters of a column. Pixels without range are put at the
+    /// origin.
+    ///
+    /// It panics if the measurement ID is out of range.
+    pub fn column_xyz(
+        &self,
+        measurement_id: u16,
+        ranges_mm: &[u32; PIXELS_PER_COLUMN],
+        xyz: &mut [[f32; PIXELS_PER_COLUMN]; 3],
+    ) {
+        let start = measurement_id as usize * PIXELS_PER_COLUMN;
+        let ranges = ranges_mm.map(|range| range as f32 / 1000.0);
+        let keep = ranges_mm.map(|range| (range > 0) as u32 as f32);
+
+        for (axis, output) in xyz.iter_mut().enumerate() {
+            let direction: &[f32; PIXELS_PER_COLUMN] = self.direction[axis]
+                [start..start + PIXELS_PER_COLUMN]
+                .try_into()
+                .unwrap();
+            let offset: &[f32; PIXELS_PER_COLUMN] = self.offset[axis]
+                [start..start + PIXELS_PER_COLUMN]
+                .try_into()
+                .unwrap();
+            for (output, range, direction, offset, keep) in
+                izip!(output.iter_mut(), &ranges, direction, offset, &keep)
+            {
+                *output = (range * direction + offset) * keep;
+            }
+        }
+    }
+}
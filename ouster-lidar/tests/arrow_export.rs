@@ -0,0 +1,104 @@
+#![cfg(feature = "parquet")]
+
+mod common;
+
+use anyhow::Result;
+use arrow_array::{Array, Float32Array, UInt16Array, UInt64Array};
+use ouster_lidar::{
+    arrow_export::{ParquetFrameWriter, PARQUET_CONFIG_KEY},
+    config::Config,
+    frame_converter::FrameConverter,
+};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+
+#[test]
+fn arrow_export_record_batch() -> Result<()> {
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let mut frames = vec![];
+    for packet in common::make_frame_packets(7, 1024, 1_000_000_000, 2000) {
+        frames.extend(converter.push_packet(packet)?);
+    }
+    let frame = &frames[0];
+
+    let batch = frame.to_record_batch()?;
+    assert_eq!(batch.num_rows(), frame.points.len());
+    assert_eq!(batch.num_columns(), 11);
+    let schema = batch.schema();
+    assert_eq!(schema.field_with_name("x")?.metadata()["unit"], "m");
+
+    let frame_id = batch
+        .column_by_name("frame_id")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<UInt16Array>()
+        .unwrap();
+    assert!(frame_id.values().iter().all(|&frame_id| frame_id == 7));
+    let z = batch
+        .column_by_name("z")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Float32Array>()
+        .unwrap();
+    let timestamp = batch
+        .column_by_name("timestamp")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<UInt64Array>()
+        .unwrap();
+    for (index, point) in frame.points.iter().enumerate() {
+        assert_eq!(z.value(index), point.point[2].as_meters() as f32);
+        assert_eq!(timestamp.value(index) as u128, point.timestamp.as_nanos());
+    }
+    Ok(())
+}
+
+#[test]
+fn arrow_export_parquet_partitions() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("ouster-parquet-{}", std::process::id()));
+    let config = common::downward_config();
+    let mut writer = ParquetFrameWriter::create(&dir, &config)?;
+    writer.add_metadata("ouster.serial", "992109000123");
+
+    let mut converter = FrameConverter::from_config(config.clone())?;
+    let mut paths = vec![];
+    for frame_id in [1, 2] {
+        for packet in common::make_frame_packets(frame_id, 1024, 1_000_000_000, 2000) {
+            for frame in converter.push_packet(packet)? {
+                paths.push(writer.write_frame(&frame)?);
+            }
+        }
+    }
+    assert_eq!(writer.num_frames(), 2);
+    assert!(paths[1].ends_with("frame=000001/part-0.parquet"));
+
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&paths[1])?)?;
+    let metadata: Vec<_> = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .unwrap()
+        .iter()
+        .map(|pair| (pair.key.clone(), pair.value.clone().unwrap_or_default()))
+        .collect();
+    let value = |key: &str| {
+        metadata
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.clone())
+            .unwrap()
+    };
+    assert_eq!(Config::from_json_str(&value(PARQUET_CONFIG_KEY))?, config);
+    assert_eq!(value("ouster.lidar_mode"), "1024x10");
+    assert_eq!(value("ouster.frame_id"), "2");
+    assert_eq!(value("ouster.serial"), "992109000123");
+
+    let rows: usize = builder
+        .build()?
+        .map(|batch| batch.map(|batch| batch.num_rows()))
+        .sum::<Result<_, _>>()?;
+    assert_eq!(rows, 1024 * 16);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    config::{AzimuthWindow, Config},
+    frame_converter::FrameConverter,
+};
+
+#[test]
+fn azimuth_window_columns() {
+    let full = AzimuthWindow::default();
+    assert_eq!(full, AzimuthWindow::new(0, 360_000));
+    assert_eq!(full.measurement_id_bounds(1024), (0, 1023));
+    assert_eq!(full.last_measurement_id(1024), 1023);
+    assert_eq!(full.num_columns(1024), 1024);
+
+    let window = AzimuthWindow::new(90_000, 180_000);
+    assert_eq!(window.to_string(), "[90000,180000]");
+    assert_eq!(window.measurement_id_bounds(1024), (256, 512));
+    assert_eq!(window.last_measurement_id(1024), 512);
+    assert_eq!(window.num_columns(1024), 257);
+    assert!(window.contains(300, 1024));
+    assert!(!window.contains(100, 1024));
+
+    let window = AzimuthWindow::new(270_000, 90_000);
+    assert!(window.wraps());
+    assert_eq!(window.measurement_id_bounds(2048), (1536, 512));
+    assert_eq!(window.last_measurement_id(2048), 2047);
+    assert_eq!(window.num_columns(2048), 1025);
+    assert!(window.contains(0, 2048));
+    assert!(!window.contains(1000, 2048));
+}
+
+#[test]
+fn azimuth_window_config() -> Result<()> {
+    let mut config = common::downward_config();
+    assert_eq!(config.azimuth_window, AzimuthWindow::default());
+    config.azimuth_window(AzimuthWindow::new(90_000, 180_000));
+
+    let json = serde_json::to_string(&config)?;
+    assert!(json.contains("\"azimuth_window\":[90000,180000]"));
+    assert_eq!(Config::from_json_str(&json)?, config);
+
+    // Configs without a window cover the full circle
+    let mut value: serde_json::Value = serde_json::from_str(&json)?;
+    value.as_object_mut().unwrap().remove("azimuth_window");
+    let config = Config::from_json_str(&value.to_string())?;
+    assert_eq!(config.azimuth_window, AzimuthWindow::default());
+    Ok(())
+}
+
+#[test]
+fn azimuth_window_emits_frames_early() -> Result<()> {
+    let mut config = common::downward_config();
+    config.azimuth_window(AzimuthWindow::new(90_000, 180_000));
+    let mut converter = FrameConverter::from_config(config)?;
+    assert_eq!(converter.columns_per_frame(), 257);
+
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    for column in packets
+        .iter_mut()
+        .flat_map(|packet| packet.columns.iter_mut())
+    {
+        let measurement_id = column.measurement_id;
+        if !(256..=512).contains(&measurement_id) {
+            column.raw_valid = 0;
+        }
+    }
+
+    for (index, packet) in packets.iter().enumerate() {
+        let frames = converter.push_packet(packet)?;
+        // Column 512 is the first of packet 32
+        if index == 32 {
+            assert_eq!(frames.len(), 1);
+            assert_eq!(frames[0].timestamps.len(), 257);
+            assert_eq!(frames[0].timestamps.last().unwrap().0, 512);
+        } else {
+            assert!(frames.is_empty());
+        }
+    }
+    assert!(converter.finish().is_none());
+    Ok(())
+}
+
+#[test]
+fn azimuth_window_emits_frames_at_invalid_last_column() -> Result<()> {
+    let mut config = common::downward_config();
+    config.azimuth_window(AzimuthWindow::new(90_000, 180_000));
+    let mut converter = FrameConverter::from_config(config)?;
+
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    for column in packets
+        .iter_mut()
+        .flat_map(|packet| packet.columns.iter_mut())
+    {
+        let measurement_id = column.measurement_id;
+        if !(256..512).contains(&measurement_id) {
+            column.raw_valid = 0;
+        }
+    }
+
+    for (index, packet) in packets.iter().enumerate() {
+        let frames = converter.push_packet(packet)?;
+        // Column 512 is the first of packet 32
+        if index == 32 {
+            assert_eq!(frames.len(), 1);
+            assert_eq!(frames[0].timestamps.len(), 256);
+            assert_eq!(frames[0].completeness.invalid_columns, 1);
+            assert_eq!(frames[0].completeness.valid_columns(), 256);
+        } else {
+            assert!(frames.is_empty());
+        }
+    }
+    assert!(converter.finish().is_none());
+    Ok(())
+}
+
+#[test]
+fn azimuth_window_reports_skipped_frames() -> Result<()> {
+    let mut config = common::downward_config();
+    config.azimuth_window(AzimuthWindow::new(90_000, 180_000));
+    let mut converter = FrameConverter::from_config(config)?;
+
+    // Frames 2 and 3 are lost, and every frame starts with invalid
+    // columns out of the window
+    let mut frames = vec![];
+    for (frame_id, start_timestamp) in [(1, 1_000_000_000), (4, 1_300_000_000)] {
+        let mut packets = common::make_frame_packets(frame_id, 1024, start_timestamp, 2000);
+        for column in packets
+            .iter_mut()
+            .flat_map(|packet| packet.columns.iter_mut())
+        {
+            let measurement_id = column.measurement_id;
+            if !(256..=512).contains(&measurement_id) {
+                column.raw_valid = 0;
+            }
+        }
+        for packet in packets.iter() {
+            frames.extend(converter.push_packet(packet)?);
+        }
+    }
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].frame_id, 1);
+    assert!(frames[0].skipped_frame_ids.is_empty());
+    assert_eq!(frames[1].frame_id, 4);
+    assert_eq!(frames[1].skipped_frame_ids, 2..4);
+    Ok(())
+}
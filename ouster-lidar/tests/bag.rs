@@ -0,0 +1,102 @@
+#![cfg(feature = "mcap")]
+
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    bag::{BagMessage, BagReader, BagTopics, BagWriter},
+    config::Config,
+    frame_converter::FrameConverter,
+    packet::ImuPacket,
+    ros2::{Imu, PointCloud2},
+};
+use std::io::Cursor;
+
+#[test]
+fn bag_roundtrip() -> Result<()> {
+    let config = Config::os_1_config();
+    let packets: Vec<_> = (1..=2)
+        .flat_map(|frame_id| {
+            common::make_frame_packets(frame_id, 1024, frame_id as u64 * 100_000_000, 4000)
+        })
+        .collect();
+    let mut buffer = [0u8; 48];
+    buffer[0..8].copy_from_slice(&150_000_000u64.to_le_bytes());
+    buffer[16..24].copy_from_slice(&150_000_000u64.to_le_bytes());
+    buffer[32..36].copy_from_slice(&1.0f32.to_le_bytes());
+    let imu_packet = ImuPacket::from_bytes(buffer);
+
+    let mut converter = FrameConverter::from_config(config.clone())?;
+    let mut expect = vec![];
+    for packet in packets.iter() {
+        expect.extend(converter.push_packet(packet)?);
+    }
+    let cloud = PointCloud2::from_frame_organized(&expect[0], 1024, "os_lidar");
+    let imu = Imu::from_packet(&imu_packet, "os_imu");
+
+    let topics = BagTopics::default();
+    let mut writer = BagWriter::new(Cursor::new(vec![]), &topics)?;
+    writer.write_config(&config)?;
+    // Log times are host receive times, on a different clock than the
+    // sensor timestamps
+    let host_time = 1_700_000_000_000_000_000;
+    for (index, packet) in packets.iter().enumerate() {
+        writer.write_packet(packet, host_time + index as u64)?;
+    }
+    writer.write_imu_packet(&imu_packet, host_time + 100)?;
+    writer.write_points(&cloud, host_time + 101)?;
+    writer.write_imu(&imu, host_time + 102)?;
+    let data = writer.finish()?.into_inner();
+
+    let reader = BagReader::from_bytes(data, topics);
+    assert_eq!(reader.config()?, Some(config));
+
+    let messages = reader.messages()?.collect::<Result<Vec<_>>>()?;
+    assert_eq!(messages.len(), packets.len() + 3);
+    for (index, ((log_time, message), packet)) in messages.iter().zip(packets.iter()).enumerate() {
+        assert_eq!(*log_time, host_time + index as u64);
+        assert_eq!(message, &BagMessage::LidarPacket(Box::new(*packet)));
+    }
+    let rest = messages[packets.len()..].to_vec();
+    assert_eq!(
+        rest,
+        vec![
+            (host_time + 100, BagMessage::ImuPacket(imu_packet)),
+            (host_time + 101, BagMessage::Points(cloud)),
+            (host_time + 102, BagMessage::Imu(Box::new(imu))),
+        ]
+    );
+
+    // Recorded packets are converted to the same frames
+    let frames = reader.frames()?;
+    assert_eq!(frames.len(), 2);
+    for (lhs, rhs) in frames.iter().zip(expect.iter()) {
+        assert_eq!(lhs.frame_id, rhs.frame_id);
+        assert_eq!(lhs.timestamps, rhs.timestamps);
+        assert_eq!(lhs.points.len(), rhs.points.len());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bag_open_file() -> Result<()> {
+    let config = Config::os_1_config();
+    let packets = common::make_frame_packets(1, 1024, 100_000_000, 4000);
+    let path = std::env::temp_dir().join(format!("ouster-bag-{}.mcap", std::process::id()));
+    let mut writer = BagWriter::create(&path)?;
+    writer.write_config(&config)?;
+    for (index, packet) in packets.iter().enumerate() {
+        writer.write_packet(packet, index as u64)?;
+    }
+    writer.finish()?;
+
+    let reader = BagReader::open(&path)?;
+    assert_eq!(reader.config()?, Some(config));
+    assert_eq!(reader.packets()?.count(), packets.len());
+    assert_eq!(reader.frames()?.len(), 1);
+
+    drop(reader);
+    std::fs::remove_file(path)?;
+    Ok(())
+}
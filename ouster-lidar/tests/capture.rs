@@ -0,0 +1,173 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    capture::{CapturedPacket, PcapReader, PcapWriter, UdpDatagram},
+    packet::ImuPacket,
+    sensor::SensorConfig,
+};
+use std::{io::Cursor, net::SocketAddrV4};
+
+/// Appends an Ethernet frame with an IPv4 fragment to a pcap buffer.
+fn push_fragment(pcap: &mut Vec<u8>, id: u16, offset: usize, more: bool, body: &[u8]) {
+    let mut frame = vec![0xff; 12];
+    frame.extend_from_slice(&0x0800u16.to_be_bytes());
+    let mut ip = [0u8; 20];
+    ip[0] = 0x45;
+    ip[2..4].copy_from_slice(&((20 + body.len()) as u16).to_be_bytes());
+    ip[4..6].copy_from_slice(&id.to_be_bytes());
+    let flags = (offset / 8) as u16 | if more { 0x2000 } else { 0 };
+    ip[6..8].copy_from_slice(&flags.to_be_bytes());
+    ip[9] = 17;
+    ip[12..16].copy_from_slice(&[10, 0, 0, 2]);
+    ip[16..20].copy_from_slice(&[10, 0, 0, 1]);
+    frame.extend_from_slice(&ip);
+    frame.extend_from_slice(body);
+
+    pcap.extend_from_slice(&5u32.to_le_bytes());
+    pcap.extend_from_slice(&0u32.to_le_bytes());
+    pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    pcap.extend_from_slice(&frame);
+}
+
+/// Returns a microsecond resolution header with Ethernet link type.
+fn pcap_header() -> Vec<u8> {
+    let mut pcap = vec![];
+    pcap.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    pcap.extend_from_slice(&[2, 0, 4, 0]);
+    pcap.extend_from_slice(&[0; 8]);
+    pcap.extend_from_slice(&65535u32.to_le_bytes());
+    pcap.extend_from_slice(&1u32.to_le_bytes());
+    pcap
+}
+
+/// Returns a UDP datagram carrying a lidar packet.
+fn lidar_udp() -> Vec<u8> {
+    let packet = common::make_frame_packets(1, 1024, 1_000_000_000, 2000)[0];
+    let mut udp = vec![];
+    udp.extend_from_slice(&7502u16.to_be_bytes());
+    udp.extend_from_slice(&7502u16.to_be_bytes());
+    udp.extend_from_slice(&((8 + packet.as_bytes().len()) as u16).to_be_bytes());
+    udp.extend_from_slice(&[0; 2]);
+    udp.extend_from_slice(packet.as_bytes());
+    udp
+}
+
+#[test]
+fn capture_roundtrip() -> Result<()> {
+    let packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    let imu = ImuPacket::from_bytes([0; 48]);
+    let source = "10.0.0.2:7502".parse::<SocketAddrV4>()?;
+    let destination = "10.0.0.1:7502".parse::<SocketAddrV4>()?;
+
+    let mut writer = PcapWriter::new(Cursor::new(vec![]))?;
+    for (index, packet) in packets.iter().take(3).enumerate() {
+        writer.write_datagram(&UdpDatagram {
+            timestamp: 1_000_000_000 + index as u64,
+            source,
+            destination,
+            payload: packet.as_bytes().to_vec(),
+        })?;
+    }
+    writer.write_datagram(&UdpDatagram {
+        timestamp: 2_000_000_000,
+        source,
+        destination: "10.0.0.1:7503".parse()?,
+        payload: imu.as_bytes().to_vec(),
+    })?;
+    let data = writer.finish()?.into_inner();
+
+    let datagrams = PcapReader::new(Cursor::new(data))?.collect::<Result<Vec<_>>>()?;
+    assert_eq!(datagrams.len(), 4);
+    for (index, (datagram, packet)) in datagrams.iter().take(3).zip(packets.iter()).enumerate() {
+        assert_eq!(datagram.timestamp, 1_000_000_000 + index as u64);
+        assert_eq!(
+            (datagram.source, datagram.destination),
+            (source, destination)
+        );
+        assert_eq!(
+            datagram.to_packet(),
+            Some(CapturedPacket::Lidar(Box::new(*packet)))
+        );
+    }
+    assert_eq!(datagrams[3].destination.port(), 7503);
+    assert_eq!(datagrams[3].to_packet(), Some(CapturedPacket::Imu(imu)));
+
+    Ok(())
+}
+
+#[test]
+fn capture_reassembles_fragments() -> Result<()> {
+    let packet = common::make_frame_packets(1, 1024, 1_000_000_000, 2000)[0];
+    let udp = lidar_udp();
+    let mut pcap = pcap_header();
+
+    // Fragments of 1480 bytes, out of order
+    let chunks: Vec<_> = udp.chunks(1480).enumerate().collect();
+    let last = chunks.len() - 1;
+    for (index, chunk) in chunks.iter().rev() {
+        push_fragment(&mut pcap, 42, index * 1480, *index != last, chunk);
+    }
+
+    let datagrams = PcapReader::new(Cursor::new(pcap))?.collect::<Result<Vec<_>>>()?;
+    assert_eq!(datagrams.len(), 1);
+    assert_eq!(datagrams[0].timestamp, 5_000_000_000);
+    assert_eq!(datagrams[0].payload, packet.as_bytes());
+
+    Ok(())
+}
+
+#[test]
+fn capture_malformed_fragments() -> Result<()> {
+    let udp = lidar_udp();
+    let chunks: Vec<_> = udp.chunks(1480).collect();
+    let last = chunks.len() - 1;
+    let mut pcap = pcap_header();
+
+    // Duplicates as on mirror ports, which must not complete the
+    // datagram before all fragments arrive
+    for (index, chunk) in chunks.iter().enumerate().take(last) {
+        push_fragment(&mut pcap, 1, index * 1480, true, chunk);
+        push_fragment(&mut pcap, 1, index * 1480, true, chunk);
+    }
+    push_fragment(&mut pcap, 1, last * 1480, false, chunks[last]);
+    push_fragment(&mut pcap, 1, last * 1480, false, chunks[last]);
+
+    // A fragment past the end of the datagram
+    push_fragment(&mut pcap, 2, 1480, false, &udp[..1480]);
+    push_fragment(&mut pcap, 2, 8000, true, &udp[..1480]);
+    push_fragment(&mut pcap, 2, 0, true, &udp[..1480]);
+
+    // Fragments of a datagram whose other fragments were lost
+    for id in 3..1000 {
+        push_fragment(&mut pcap, id, 0, true, &udp[..1480]);
+    }
+
+    let mut reader = PcapReader::new(Cursor::new(pcap))?;
+    let datagrams = reader.by_ref().collect::<Result<Vec<_>>>()?;
+    assert_eq!(datagrams.len(), 1);
+    assert_eq!(datagrams[0].payload, &udp[8..]);
+
+    // Records larger than the snapshot length are rejected
+    let mut pcap = pcap_header();
+    pcap.extend_from_slice(&[0; 8]);
+    pcap.extend_from_slice(&u32::MAX.to_le_bytes());
+    pcap.extend_from_slice(&u32::MAX.to_le_bytes());
+    let mut reader = PcapReader::new(Cursor::new(pcap))?;
+    assert!(reader.next_datagram().is_err());
+    Ok(())
+}
+
+#[test]
+fn capture_sensor_config_toml() -> Result<()> {
+    let config = SensorConfig::from_path("test_files/ouster_client_test.toml")?;
+    assert_eq!(
+        config.lidar_addr,
+        "169.254.161.176".parse::<std::net::Ipv4Addr>()?
+    );
+    assert_eq!(config.timeout, 10);
+    assert_eq!(config.lidar_mode, None);
+    assert!(!config.imu);
+    Ok(())
+}
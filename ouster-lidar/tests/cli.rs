@@ -0,0 +1,112 @@
+#![cfg(feature = "mcap")]
+
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    capture::{PcapWriter, UdpDatagram},
+    packet::Packet,
+};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+fn ouster() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_ouster"))
+}
+
+fn temp_dir(name: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("ouster-cli-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn write_pcap(path: &Path, packets: &[Packet]) -> Result<()> {
+    let mut writer = PcapWriter::create(path)?;
+    for packet in packets.iter() {
+        writer.write_datagram(&UdpDatagram {
+            timestamp: packet.columns[0].timestamp,
+            source: "10.0.0.2:7502".parse()?,
+            destination: "10.0.0.1:7502".parse()?,
+            payload: packet.as_bytes().to_vec(),
+        })?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+#[test]
+fn cli_convert_and_stats() -> Result<()> {
+    let dir = temp_dir("convert")?;
+    let pcap = dir.join("capture.pcap");
+    // The second frame lacks its last packet
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    packets.extend(common::make_frame_packets(2, 1024, 1_100_000_000, 2000));
+    packets.pop();
+    write_pcap(&pcap, &packets)?;
+
+    let output = ouster().arg("stats").arg(&pcap).output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains(&format!("packets:         {}\n", packets.len())));
+    assert!(stdout.contains("frames:          2\n"));
+    assert!(stdout.contains("missing columns: 16\n"));
+
+    let frames_dir = dir.join("frames");
+    let status = ouster()
+        .arg("convert")
+        .arg(&pcap)
+        .arg("--output-dir")
+        .arg(&frames_dir)
+        .args(["--format", "las"])
+        .status()?;
+    assert!(status.success());
+    assert!(frames_dir.join("frame000000.las").exists());
+    assert!(frames_dir.join("frame000001.las").exists());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_stats_counts_bad_packets() -> Result<()> {
+    let dir = temp_dir("bad-packets")?;
+    let pcap = dir.join("capture.pcap");
+    // A packet of the first frame is replayed in its middle
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    packets.insert(6, packets[0]);
+    packets.extend(common::make_frame_packets(2, 1024, 1_100_000_000, 2000));
+    write_pcap(&pcap, &packets)?;
+
+    let output = ouster().arg("stats").arg(&pcap).output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains(&format!("packets:         {}\n", packets.len())));
+    assert!(stdout.contains("frames:          2\n"));
+    assert!(stdout.contains("bad packets:     1\n"));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_exit_codes() -> Result<()> {
+    // Missing sensor address
+    let output = ouster().arg("info").output()?;
+    assert_eq!(output.status.code(), Some(2));
+
+    // Unknown recording format
+    let output = ouster().args(["stats", "frames.bin"]).output()?;
+    assert_eq!(output.status.code(), Some(2));
+
+    // Unknown subcommand
+    let output = ouster().arg("fly").output()?;
+    assert_eq!(output.status.code(), Some(2));
+
+    // Missing file
+    let output = ouster().args(["stats", "missing.pcap"]).output()?;
+    assert_eq!(output.status.code(), Some(1));
+
+    Ok(())
+}
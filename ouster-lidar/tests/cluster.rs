@@ -0,0 +1,84 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    cluster::{EuclideanClustering, RangeImageClustering},
+    frame_converter::FrameConverter,
+    ground::{GroundMethod, GroundSegmenter, RingSlopeParams},
+};
+
+#[test]
+fn cluster_euclidean_blobs() {
+    let mut points = vec![];
+    for center in [[5.0, 0.0, 0.0], [0.0, 8.0, 1.0]] {
+        for dx in 0..5 {
+            for dy in 0..3 {
+                let position = [
+                    center[0] + dx as f64 * 0.1,
+                    center[1] + dy as f64 * 0.1,
+                    center[2],
+                ];
+                points.push(common::make_point(position, points.len() as u32));
+            }
+        }
+    }
+    // An isolated point is left unclustered
+    points.push(common::make_point([-20.0, 0.0, 0.0], 99));
+
+    let clustering = EuclideanClustering {
+        tolerance: 0.15,
+        min_cluster_size: 2,
+        max_cluster_size: 1000,
+    }
+    .cluster(&points);
+
+    assert_eq!(clustering.clusters.len(), 2);
+    assert_eq!(clustering.labels[..15], [Some(0); 15]);
+    assert_eq!(clustering.labels[15..30], [Some(1); 15]);
+    assert_eq!(clustering.labels[30], None);
+
+    let first = &clustering.clusters[0];
+    assert!((first.aabb.min[0] - 5.0).abs() < 1e-9 && (first.aabb.max[0] - 5.4).abs() < 1e-9);
+    assert!((first.obb.half_extents[0] - 0.2).abs() < 1e-9);
+    assert!((first.obb.half_extents[1] - 0.1).abs() < 1e-9);
+    assert!(first.obb.yaw.abs() < 1e-9);
+}
+
+#[test]
+fn cluster_range_image_after_ground_removal() -> Result<()> {
+    let config = common::downward_config();
+    let mut converter = FrameConverter::from_config(config.clone())?;
+    let mut frames = vec![];
+    for packet in common::ground_and_wall_packets(1, 1.8, 512, 3.0) {
+        frames.extend(converter.push_packet(packet)?);
+    }
+    let frame = frames.remove(0);
+
+    let segmentation =
+        GroundSegmenter::new(&config, GroundMethod::RingSlope(RingSlopeParams::default()))?
+            .segment(&frame);
+    let obstacles = segmentation.non_ground_points(&frame.points);
+
+    let params = (0.3, 10, 100_000);
+    let range_image = RangeImageClustering {
+        tolerance: params.0,
+        min_cluster_size: params.1,
+        max_cluster_size: params.2,
+    }
+    .cluster(&obstacles, 1024);
+    let euclidean = EuclideanClustering {
+        tolerance: params.0,
+        min_cluster_size: params.1,
+        max_cluster_size: params.2,
+    }
+    .cluster(&obstacles);
+
+    for clustering in [range_image, euclidean] {
+        assert_eq!(clustering.clusters.len(), 1);
+        assert!(clustering.labels.iter().all(|label| *label == Some(0)));
+        let aabb = clustering.clusters[0].aabb;
+        assert!(aabb.max[2] < 0.0 && aabb.min[2] > -1.8);
+    }
+
+    Ok(())
+}
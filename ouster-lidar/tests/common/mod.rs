@@ -0,0 +1,103 @@
+#![allow(dead_code)]
+
+use measurements::{Angle, Length};
+use noisy_float::types::R64;
+use ouster_lidar::{
+    config::Config, consts::COLUMNS_PER_PACKET, enums::LidarMode, packet::Packet as OusterPacket,
+    pcd_converter::Point,
+};
+use std::time::Duration;
+
+/// Builds the packets of one full 10 Hz revolution. Every pixel
+/// reports the same distance.
+pub fn make_frame_packets(
+    frame_id: u16,
+    columns_per_revolution: u16,
+    start_timestamp: u64,
+    distance_mm: u32,
+) -> Vec<OusterPacket> {
+    let column_period = 100_000_000 / columns_per_revolution as u64;
+    let ticks_per_column = 90112 / columns_per_revolution as u32;
+
+    (0..columns_per_revolution)
+        .step_by(COLUMNS_PER_PACKET)
+        .map(|first_mid| {
+            let mut packet = OusterPacket::from_bytes([0; std::mem::size_of::<OusterPacket>()]);
+            for (offset, column) in packet.columns.iter_mut().enumerate() {
+                let mid = first_mid + offset as u16;
+                column.timestamp = start_timestamp + mid as u64 * column_period;
+                column.measurement_id = mid;
+                column.frame_id = frame_id;
+                column.encoder_ticks = mid as u32 * ticks_per_column;
+                column.raw_valid = 0xffffffff;
+                for pixel in column.pixels.iter_mut() {
+                    pixel.raw_distance = distance_mm;
+                    pixel.reflectivity = 100;
+                    pixel.signal_photons = 50;
+                    pixel.noise_photons = 10;
+                }
+            }
+            packet
+        })
+        .collect()
+}
+
+/// Builds a point at the position in meters.
+pub fn make_point(position: [f64; 3], laser_id: u32) -> Point {
+    let distance = position.iter().map(|v| v * v).sum::<f64>().sqrt();
+    Point {
+        timestamp: Duration::from_nanos(laser_id as u64),
+        azimuth_angle: Angle::from_radians(position[1].atan2(position[0])),
+        distance: Length::from_meters(distance),
+        reflectivity: laser_id as u16,
+        signal_photons: 0,
+        noise_photons: 0,
+        laser_id,
+        measurement_id: 0,
+        point: position.map(Length::from_meters),
+    }
+}
+
+/// Creates a 1024x10 config whose beams look downwards from
+/// -2 to -17 degrees without azimuth corrections.
+pub fn downward_config() -> Config {
+    let altitudes = [(); 16];
+    let mut altitude = -1.0;
+    let altitudes = altitudes.map(|_| {
+        altitude -= 1.0;
+        R64::new(altitude)
+    });
+    Config::new(altitudes, [R64::new(0.0); 16], LidarMode::Mode1024x10)
+}
+
+/// Builds a frame of [downward_config] in which columns below
+/// `wall_from` see flat ground `height` meters below the sensor,
+/// and the other columns see a wall `wall_range` meters away.
+pub fn ground_and_wall_packets(
+    frame_id: u16,
+    height: f64,
+    wall_from: u16,
+    wall_range: f64,
+) -> Vec<OusterPacket> {
+    let config = downward_config();
+    let mut packets = make_frame_packets(frame_id, 1024, 0, 0);
+    for packet in packets.iter_mut() {
+        for column in packet.columns.iter_mut() {
+            let mid = column.measurement_id;
+            for (pixel, altitude) in column
+                .pixels
+                .iter_mut()
+                .zip(config.beam_altitude_angles.iter())
+            {
+                let altitude = altitude.raw().to_radians();
+                let distance = if mid < wall_from {
+                    height / (-altitude).sin()
+                } else {
+                    wall_range / altitude.cos()
+                };
+                pixel.raw_distance = (distance * 1000.0).round() as u32;
+            }
+        }
+    }
+    packets
+}
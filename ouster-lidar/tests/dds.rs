@@ -0,0 +1,213 @@
+#![cfg(feature = "dds")]
+
+mod common;
+
+use anyhow::Result;
+use itertools::izip;
+use ouster_lidar::{
+    config::Config,
+    dds::{
+        ColumnKey, DdsBridge, DdsConfig, DdsPublisher, DdsReliability, DdsSubscriber,
+        DdsSubscriptions, FrameMessage,
+    },
+    filter::{FilterPipeline, PointFilter},
+    frame_converter::FrameConverter,
+    packet::PacketMetaData,
+};
+use rustdds::Keyed;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn dds_keyed_instances() -> Result<()> {
+    let packets = common::make_frame_packets(1, 32, 0, 1000);
+
+    // Every column of every sensor is a distinct instance
+    let keys: HashSet<ColumnKey> = [1, 2]
+        .into_iter()
+        .flat_map(|sensor_id| {
+            packets
+                .iter()
+                .flat_map(move |packet| packet.columns.map(|c| ColumnKey::new(sensor_id, &c)))
+        })
+        .collect();
+    assert_eq!(keys.len(), 2 * 32);
+    let column = &packets[1].columns[5];
+    assert_eq!(
+        PacketMetaData::new(2, column).key(),
+        ColumnKey::new(2, column)
+    );
+
+    // Readers keep the depth per instance, so samples of different
+    // columns and sensors never replace each other. Writers keep it
+    // across instances, so it covers the metadata of one publish.
+    let mut config = DdsConfig {
+        domain_id: 33,
+        ..Default::default()
+    };
+    config.qos.reliability = DdsReliability::Reliable;
+    config.qos.history_depth = Some(32);
+    let mut subscriber = DdsSubscriber::new(&config)?;
+    let publishers = [1, 2]
+        .into_iter()
+        .map(|sensor_id| {
+            DdsPublisher::new(&DdsConfig {
+                sensor_id,
+                ..config.clone()
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut received_packets = HashSet::new();
+    let mut received_metadata = HashSet::new();
+    let deadline = Instant::now() + Duration::from_secs(20);
+    let mut round = 0;
+    while (received_metadata.len() < keys.len() || received_packets.len() < 2 * packets.len())
+        && Instant::now() < deadline
+    {
+        // Samples are lost until writers are matched, so publish again
+        // every second
+        if round % 10 == 0 {
+            for publisher in publishers.iter() {
+                for packet in packets.iter() {
+                    publisher.publish_packet(packet)?;
+                }
+            }
+        }
+        round += 1;
+        std::thread::sleep(Duration::from_millis(100));
+        for message in subscriber.take_packets()? {
+            let expect = packets[message.packet.columns[0].measurement_id as usize / 16];
+            assert_eq!(message.packet, expect);
+            received_packets.insert(message.key());
+        }
+        for metadata in subscriber.take_metadata()? {
+            received_metadata.insert(metadata.key());
+        }
+    }
+
+    assert_eq!(received_metadata, keys);
+    assert_eq!(received_packets.len(), 2 * packets.len());
+
+    Ok(())
+}
+
+#[test]
+fn dds_frame_roundtrip() -> Result<()> {
+    let mut converter = FrameConverter::from_config(Config::os_1_config())?;
+    let mut frames = vec![];
+    for packet in common::make_frame_packets(3, 1024, 1_000_000, 5000) {
+        frames.extend(converter.push_packet(packet)?);
+    }
+    let frame = frames.pop().unwrap();
+
+    let message = FrameMessage::from(&frame);
+    assert_eq!(message.len(), frame.points.len());
+    let decoded = message.to_frame()?;
+    assert_eq!(decoded.frame_id, frame.frame_id);
+    assert_eq!(decoded.skipped_frame_ids, frame.skipped_frame_ids);
+    assert_eq!(decoded.timestamps, frame.timestamps);
+    assert_eq!(decoded.completeness, frame.completeness);
+    for (lhs, rhs) in izip!(&decoded.points, &frame.points) {
+        assert_eq!(lhs.timestamp, rhs.timestamp);
+        assert_eq!(lhs.laser_id, rhs.laser_id);
+        assert_eq!(lhs.measurement_id, rhs.measurement_id);
+        assert_eq!(lhs.reflectivity, rhs.reflectivity);
+        for axis in 0..3 {
+            let diff = lhs.point[axis].as_meters() - rhs.point[axis].as_meters();
+            assert!(diff.abs() < 1e-5);
+        }
+    }
+
+    // A frame missing its first packet stays partial
+    let mut converter = FrameConverter::from_config(Config::os_1_config())?;
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000, 5000);
+    packets.remove(0);
+    let mut frames = vec![];
+    for packet in packets {
+        frames.extend(converter.push_packet(packet)?);
+    }
+    let decoded = FrameMessage::from(&frames[0]).to_frame()?;
+    assert_eq!(decoded.completeness.expected_columns, 1024);
+    assert_eq!(decoded.completeness.missing_columns(), 16);
+    assert!(!decoded.completeness.is_complete());
+
+    let mut message = FrameMessage::from(&frames[0]);
+    message.columns_per_revolution = 512;
+    assert!(message.to_frame().is_err());
+
+    // Keep the payload small since large samples are fragmented
+    // and easily dropped on loopback with default socket buffers.
+    let mut config = DdsConfig {
+        domain_id: 32,
+        ..Default::default()
+    };
+    config.qos.reliability = DdsReliability::Reliable;
+    // Packets the bridge publishes along with frames are not received
+    let mut subscriber = DdsSubscriber::new(&DdsConfig {
+        subscriptions: DdsSubscriptions {
+            packets: false,
+            metadata: false,
+            frames: true,
+        },
+        ..config.clone()
+    })?;
+    let mut bridge = DdsBridge::new(DdsPublisher::new(&config)?, Config::os_1_config())?;
+    bridge
+        .converter_mut()
+        .set_filter(FilterPipeline::new().with(PointFilter::Sector {
+            azimuth: [0.0, 30.0],
+            elevation: [-90.0, 90.0],
+        }));
+
+    let mut received = vec![];
+    let mut num_published = 0;
+    let mut frame_id = 1;
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while received.is_empty() && Instant::now() < deadline {
+        // Frames are lost until the reader is matched
+        for packet in common::make_frame_packets(frame_id, 1024, 0, 5000) {
+            num_published += bridge.push_packet(&packet)?;
+        }
+        frame_id += 1;
+        std::thread::sleep(Duration::from_millis(200));
+        received.extend(subscriber.take_frames()?);
+    }
+
+    assert!(num_published > 0);
+    assert!(!received.is_empty());
+    let frame = &received[0];
+    assert!(frame.frame_id < frame_id);
+    assert_eq!(frame.timestamps.len(), 1024);
+    assert!(!frame.points.is_empty() && frame.points.len() < 1024 * 16 / 10);
+    assert!(subscriber.take_packets()?.is_empty());
+    assert!(subscriber
+        .register_packets(&mio::Poll::new()?, mio::Token(0))
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn dds_bridge_recovers_from_rejected_packets() -> Result<()> {
+    let config = DdsConfig {
+        domain_id: 33,
+        ..Default::default()
+    };
+    let mut bridge = DdsBridge::new(DdsPublisher::new(&config)?, Config::os_1_config())?;
+    let packets = common::make_frame_packets(1, 1024, 0, 5000);
+    for packet in packets[..8].iter() {
+        assert_eq!(bridge.push_packet(packet)?, 0);
+    }
+    // The sensor rewinds to the start of the frame
+    assert!(bridge.push_packet(&packets[0]).is_err());
+
+    let mut num_published = 0;
+    for packet in common::make_frame_packets(2, 1024, 100_000_000, 5000) {
+        num_published += bridge.push_packet(&packet)?;
+    }
+    assert_eq!(num_published, 1);
+    Ok(())
+}
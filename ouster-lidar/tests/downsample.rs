@@ -0,0 +1,103 @@
+mod common;
+
+use ouster_lidar::{
+    downsample::{random_decimate, uniform_decimate, VoxelGrid, VoxelRepresentative},
+    spatial::KdTree,
+};
+
+fn grid_points() -> Vec<ouster_lidar::Point> {
+    let mut points = vec![];
+    for x in 0..10 {
+        for y in 0..10 {
+            for z in 0..4 {
+                let position = [x as f64 * 0.25, y as f64 * 0.25 + 0.01, z as f64 * 0.25];
+                points.push(common::make_point(position, points.len() as u32));
+            }
+        }
+    }
+    points
+}
+
+#[test]
+fn downsample_voxel_grid() {
+    let points = grid_points();
+
+    let centroid = VoxelGrid::new(1.0, VoxelRepresentative::Centroid).downsample(&points);
+    let nearest = VoxelGrid::new(1.0, VoxelRepresentative::NearestToCentroid).downsample(&points);
+    assert_eq!(centroid.len(), 3 * 3);
+    assert_eq!(nearest.len(), centroid.len());
+
+    // The first voxel holds 4x4x4 points with centroid at 0.375
+    let first = &centroid[0];
+    assert!((first.point[0].as_meters() - 0.375).abs() < 1e-9);
+    assert!((first.point[2].as_meters() - 0.375).abs() < 1e-9);
+
+    // Attributes come from an input point
+    for (centroid, nearest) in centroid.iter().zip(nearest.iter()) {
+        let source = &points[nearest.laser_id as usize];
+        assert_eq!(source.point, nearest.point);
+        assert_eq!(centroid.laser_id, nearest.laser_id);
+        assert_eq!(centroid.reflectivity, source.reflectivity);
+        assert_eq!(centroid.timestamp, source.timestamp);
+    }
+}
+
+#[test]
+fn downsample_decimation() {
+    let points = grid_points();
+
+    let uniform = uniform_decimate(&points, 4);
+    assert_eq!(uniform.len(), 100);
+    assert!(uniform.iter().all(|point| point.laser_id % 4 == 0));
+
+    let random = random_decimate(&points, 50, 7);
+    assert_eq!(random.len(), 50);
+    assert!(random
+        .windows(2)
+        .all(|pair| pair[0].laser_id < pair[1].laser_id));
+    let again = random_decimate(&points, 50, 7);
+    assert!(random
+        .iter()
+        .zip(again.iter())
+        .all(|(lhs, rhs)| lhs.laser_id == rhs.laser_id));
+}
+
+#[test]
+fn spatial_kdtree_matches_brute_force() {
+    let points = grid_points();
+    let tree = KdTree::new(&points);
+    assert_eq!(tree.len(), points.len());
+
+    let positions: Vec<[f64; 3]> = points
+        .iter()
+        .map(|point| point.point.map(|v| v.as_meters()))
+        .collect();
+    let distance = |lhs: [f64; 3], rhs: [f64; 3]| {
+        lhs.iter()
+            .zip(rhs.iter())
+            .map(|(lhs, rhs)| (lhs - rhs).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    };
+
+    for query in [[0.3, 0.4, 0.1], [1.2, 2.0, 0.5], [-1.0, -1.0, -1.0]] {
+        let mut found = tree.radius_search(query, 0.6);
+        found.sort_unstable();
+        let expected: Vec<_> = (0..positions.len())
+            .filter(|&index| distance(positions[index], query) <= 0.6)
+            .collect();
+        assert_eq!(found, expected);
+
+        let knn = tree.nearest_neighbors(query, 5);
+        let mut brute: Vec<_> = positions
+            .iter()
+            .map(|&position| distance(position, query))
+            .collect();
+        brute.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).unwrap());
+        assert_eq!(knn.len(), 5);
+        for ((index, dist), expected) in knn.iter().zip(brute.iter()) {
+            assert!((dist - expected).abs() < 1e-9);
+            assert!((distance(positions[*index], query) - dist).abs() < 1e-9);
+        }
+    }
+}
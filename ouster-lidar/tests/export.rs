@@ -0,0 +1,76 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    export::{write_las, write_pcd, write_ply, PointCloudFormat},
+    pcd_converter::Point,
+};
+
+fn make_points() -> Vec<Point> {
+    vec![
+        common::make_point([1.0, 2.0, 3.0], 4),
+        common::make_point([-1.5, 0.5, 0.0], 7),
+    ]
+}
+
+/// Splits the file at the end of the header line.
+fn split_header<'a>(data: &'a [u8], last_line: &str) -> (&'a str, &'a [u8]) {
+    let text = String::from_utf8_lossy(data);
+    let end = text.find(last_line).unwrap() + last_line.len() + 1;
+    (std::str::from_utf8(&data[..end]).unwrap(), &data[end..])
+}
+
+fn check_body(body: &[u8], points: &[Point]) -> Result<()> {
+    assert_eq!(body.len(), points.len() * 26);
+    for (record, point) in body.chunks(26).zip(points.iter()) {
+        for axis in 0..3 {
+            let value = f32::from_le_bytes(record[(axis * 4)..(axis * 4 + 4)].try_into()?);
+            assert!((value as f64 - point.point[axis].as_meters()).abs() < 1e-6);
+        }
+        let ring = u16::from_le_bytes(record[18..20].try_into()?);
+        let range = u32::from_le_bytes(record[22..26].try_into()?);
+        assert_eq!(ring as u32, point.laser_id);
+        assert_eq!(range as f64, point.distance.as_millimeters().round());
+    }
+    Ok(())
+}
+
+#[test]
+fn export_pcd_and_ply() -> Result<()> {
+    let points = make_points();
+
+    let mut pcd = vec![];
+    write_pcd(&mut pcd, &points)?;
+    let (header, body) = split_header(&pcd, "DATA binary");
+    assert!(header.contains("FIELDS x y z intensity reflectivity ring ambient range\n"));
+    assert!(header.contains("POINTS 2\n"));
+    check_body(body, &points)?;
+
+    let mut ply = vec![];
+    write_ply(&mut ply, &points)?;
+    let (header, body) = split_header(&ply, "end_header");
+    assert!(header.starts_with("ply\nformat binary_little_endian 1.0\n"));
+    assert!(header.contains("element vertex 2\n"));
+    check_body(body, &points)?;
+
+    let mut las = vec![];
+    write_las(&mut las, &points)?;
+    assert_eq!(&las[..4], b"LASF");
+    assert_eq!(las.len(), 227 + 2 * 28);
+    assert_eq!(u32::from_le_bytes(las[107..111].try_into()?), 2);
+    let max_x = f64::from_le_bytes(las[179..187].try_into()?);
+    let min_x = f64::from_le_bytes(las[187..195].try_into()?);
+    assert_eq!((min_x, max_x), (-1.5, 1.0));
+    let record = &las[(227 + 28)..];
+    assert_eq!(i32::from_le_bytes(record[0..4].try_into()?), -1500);
+    assert_eq!(record[17], 7);
+
+    assert_eq!("PLY".parse::<PointCloudFormat>()?, PointCloudFormat::Ply);
+    assert_eq!(
+        PointCloudFormat::from_path("frames/000001.pcd")?,
+        PointCloudFormat::Pcd
+    );
+    assert!("xyz".parse::<PointCloudFormat>().is_err());
+
+    Ok(())
+}
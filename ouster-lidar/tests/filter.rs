@@ -0,0 +1,97 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    config::Config,
+    filter::{BoundingBox, FilterPipeline, PointFilter},
+    frame_converter::FrameConverter,
+};
+
+fn convert_frame(distance_mm: u32, filter: Option<FilterPipeline>) -> Result<usize> {
+    let mut converter = FrameConverter::from_config(Config::os_1_config())?;
+    if let Some(filter) = filter {
+        converter.set_filter(filter);
+    }
+
+    let mut frames = vec![];
+    for packet in common::make_frame_packets(1, 1024, 0, distance_mm) {
+        frames.extend(converter.push_packet(packet)?);
+    }
+    assert_eq!(frames.len(), 1);
+    Ok(frames[0].points.len())
+}
+
+#[test]
+fn filter_on_converter() -> Result<()> {
+    assert_eq!(convert_frame(0, None)?, 1024 * 16);
+    assert_eq!(
+        convert_frame(0, Some(FilterPipeline::new().with(PointFilter::ZeroReturn)))?,
+        0
+    );
+
+    let range = FilterPipeline::new().with(PointFilter::Range {
+        min: 2.0,
+        max: 50.0,
+    });
+    assert_eq!(convert_frame(1000, Some(range.clone()))?, 0);
+    assert_eq!(convert_frame(3000, Some(range))?, 1024 * 16);
+
+    let reflectivity = FilterPipeline::new().with(PointFilter::Reflectivity { min: 101 });
+    assert_eq!(convert_frame(3000, Some(reflectivity))?, 0);
+
+    Ok(())
+}
+
+#[test]
+fn filter_on_frame() -> Result<()> {
+    let mut converter = FrameConverter::from_config(Config::os_1_config())?;
+    let mut frames = vec![];
+    for packet in common::make_frame_packets(1, 1024, 0, 10_000) {
+        frames.extend(converter.push_packet(packet)?);
+    }
+    let frame = &frames[0];
+
+    // Keep the front half plane
+    let mut front = frame.clone();
+    FilterPipeline::new()
+        .with(PointFilter::RegionOfInterest(BoundingBox::from_min_max(
+            [0.0, -100.0, -100.0],
+            [100.0, 100.0, 100.0],
+        )))
+        .apply_frame(&mut front);
+    assert!(front
+        .points
+        .iter()
+        .all(|point| point.point[0].as_meters() >= 0.0));
+    assert!(!front.points.is_empty() && front.points.len() < frame.points.len());
+
+    // Crop a box around the sensor covering the whole cloud
+    let mut cropped = frame.clone();
+    FilterPipeline::new()
+        .with(PointFilter::CropBox(BoundingBox {
+            center: [0.0, 0.0, 0.0],
+            half_extents: [20.0, 20.0, 20.0],
+            yaw: 0.5,
+        }))
+        .apply_frame(&mut cropped);
+    assert!(cropped.points.is_empty());
+
+    // A sector that wraps around zero azimuth
+    let mut sector = frame.clone();
+    let filter = PointFilter::Sector {
+        azimuth: [315.0, 45.0],
+        elevation: [-90.0, 90.0],
+    };
+    FilterPipeline::new()
+        .with(filter.clone())
+        .apply_frame(&mut sector);
+    assert!(sector.points.iter().all(|point| filter.keep(point)));
+    assert!(sector.points.iter().all(|point| {
+        let [x, y, _] = point.point.map(|v| v.as_meters());
+        x >= y.abs() - 1e-6
+    }));
+    let ratio = sector.points.len() as f64 / frame.points.len() as f64;
+    assert!((ratio - 0.25).abs() < 0.02);
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{enums::LidarMode, frame_converter::FrameConverter};
+use std::time::Duration;
+
+const START: u64 = 1_000_000_000;
+const COLUMN_PERIOD: u64 = 100_000_000 / 1024;
+
+#[test]
+fn frame_time_offsets() -> Result<()> {
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let packets = common::make_frame_packets(1, 1024, START, 2000);
+    // Drop the first packet so the frame starts at column 16
+    let frames = packets[1..]
+        .iter()
+        .map(|packet| converter.push_packet(packet))
+        .collect::<Result<Vec<_>>>()?;
+    let frame = frames.into_iter().flatten().next().unwrap();
+
+    assert_eq!(
+        frame.start_time(),
+        Duration::from_nanos(START + 16 * COLUMN_PERIOD)
+    );
+    assert_eq!(
+        frame.end_time(),
+        Duration::from_nanos(START + 1023 * COLUMN_PERIOD)
+    );
+    assert_eq!(frame.duration(), Duration::from_nanos(1007 * COLUMN_PERIOD));
+
+    let offsets: Vec<_> = frame.point_time_offsets().collect();
+    assert_eq!(offsets.len(), frame.points.len());
+    for (point, offset) in frame.points.iter().zip(offsets) {
+        let column = point.measurement_id as u64 - 16;
+        assert_eq!(offset, Duration::from_nanos(column * COLUMN_PERIOD));
+    }
+    Ok(())
+}
+
+#[test]
+fn frame_time_interpolates_missing_timestamps() -> Result<()> {
+    assert_eq!(
+        LidarMode::Mode1024x10.frame_period(),
+        Some(Duration::from_millis(100))
+    );
+    assert_eq!(
+        LidarMode::Mode512x20.frame_period(),
+        Some(Duration::from_millis(50))
+    );
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let mut packets = common::make_frame_packets(1, 1024, START, 2000);
+    for column in packets[3].columns.iter_mut() {
+        column.timestamp = 0;
+    }
+    let frame = packets
+        .iter()
+        .map(|packet| converter.push_packet(packet))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .next()
+        .unwrap();
+
+    for &(measurement_id, timestamp) in frame.timestamps.iter() {
+        let expected = START + measurement_id as u64 * COLUMN_PERIOD;
+        assert!(
+            timestamp.abs_diff(expected) < 100,
+            "column {} at {} instead of {}",
+            measurement_id,
+            timestamp,
+            expected
+        );
+    }
+    for point in frame
+        .points
+        .iter()
+        .filter(|point| point.measurement_id == 50)
+    {
+        let expected = START + 50 * COLUMN_PERIOD;
+        assert!((point.timestamp.as_nanos() as u64).abs_diff(expected) < 100);
+    }
+    Ok(())
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    frame_converter::{Frame, FrameConverter},
+    ground::{GroundMethod, GroundSegmenter, RansacParams, RingSlopeParams},
+};
+
+fn ground_and_wall_frame() -> Result<Frame> {
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let mut frames = vec![];
+    for packet in common::ground_and_wall_packets(1, 1.8, 512, 3.0) {
+        frames.extend(converter.push_packet(packet)?);
+    }
+    Ok(frames.remove(0))
+}
+
+#[test]
+fn ground_ring_slope() -> Result<()> {
+    let frame = ground_and_wall_frame()?;
+    let segmenter = GroundSegmenter::new(
+        &common::downward_config(),
+        GroundMethod::RingSlope(RingSlopeParams::default()),
+    )?;
+    let segmentation = segmenter.segment(&frame);
+
+    assert_eq!(segmentation.is_ground.len(), frame.points.len());
+    for (point, ground) in frame.points.iter().zip(segmentation.is_ground.iter()) {
+        assert_eq!(*ground, point.measurement_id < 512);
+    }
+    assert!(segmentation.model.tilt_degrees() < 0.1);
+    assert!((segmentation.model.offset - 1.8).abs() < 0.01);
+    assert_eq!(
+        segmentation.non_ground_points(&frame.points).len(),
+        512 * 16
+    );
+
+    Ok(())
+}
+
+#[test]
+fn ground_ransac() -> Result<()> {
+    let frame = ground_and_wall_frame()?;
+    let segmenter = GroundSegmenter::new(
+        &common::downward_config(),
+        GroundMethod::Ransac(RansacParams::default()),
+    )?;
+    let segmentation = segmenter.segment(&frame);
+
+    let misclassified = frame
+        .points
+        .iter()
+        .zip(segmentation.is_ground.iter())
+        .filter(|(point, ground)| **ground != (point.measurement_id < 512))
+        .count();
+    // Wall points touching the ground plane are inliers as well
+    assert!(misclassified < frame.points.len() / 100);
+    assert!((segmentation.model.height([0.0, 0.0, -1.8])).abs() < 0.01);
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    config::AzimuthWindow,
+    health::{HealthAlarm, HealthConfig, HealthMonitor, HealthThresholds},
+    packet::Packet,
+};
+use std::time::Duration;
+
+const COLUMNS: u16 = 1024;
+const COLUMN_PERIOD: u64 = 100_000_000 / COLUMNS as u64;
+const LATENCY: u64 = 1_000_000;
+
+/// Pushes packets as if each arrived 1 ms after its last column.
+fn push_all(monitor: &mut HealthMonitor, packets: &[Packet]) -> Vec<HealthAlarm> {
+    packets
+        .iter()
+        .flat_map(|packet| {
+            let timestamp = packet.columns[packet.columns.len() - 1].timestamp;
+            monitor.push_packet_at(packet, timestamp + LATENCY)
+        })
+        .collect()
+}
+
+/// Builds consecutive frames with evenly spaced columns.
+fn frames(first_frame_id: u16, count: u16) -> Vec<Packet> {
+    (first_frame_id..first_frame_id + count)
+        .flat_map(|frame_id| {
+            let start = 1_000_000_000 + frame_id as u64 * COLUMNS as u64 * COLUMN_PERIOD;
+            common::make_frame_packets(frame_id, COLUMNS, start, 2000)
+        })
+        .collect()
+}
+
+#[test]
+fn health_clean_stream() -> Result<()> {
+    let mut monitor = HealthMonitor::with_columns(COLUMNS, HealthConfig::default())?;
+    let packets = frames(1, 3);
+    assert!(push_all(&mut monitor, &packets).is_empty());
+
+    let snapshot = monitor.snapshot();
+    assert_eq!(snapshot.packets, packets.len());
+    assert_eq!(snapshot.frames, 2);
+    assert_eq!(snapshot.dropped_frames, 0);
+    assert_eq!(snapshot.missing_columns, 0);
+    assert_eq!(snapshot.invalid_columns, 0);
+    assert_eq!(snapshot.out_of_order, 0);
+    assert_eq!(snapshot.encoder_discontinuities, 0);
+    assert_eq!(snapshot.jitter, Duration::ZERO);
+    assert_eq!(snapshot.mean_latency_ns, LATENCY as i64);
+    assert_eq!(snapshot.max_latency_ns, LATENCY as i64);
+    assert!((snapshot.packet_rate - 640.0).abs() < 10.0);
+    assert!(monitor.latest().is_none());
+    Ok(())
+}
+
+#[test]
+fn health_stream_faults() -> Result<()> {
+    let mut monitor = HealthMonitor::with_columns(COLUMNS, HealthConfig::default())?;
+    let mut packets = frames(1, 2);
+    packets[2].columns[3].raw_valid = 0;
+    packets[4].columns[5].encoder_ticks += 1000;
+    // Drop a packet of the second frame and swap two others
+    packets.remove(70);
+    packets.swap(80, 81);
+    // Skip frame 3
+    packets.extend(frames(4, 1).into_iter().take(1));
+    push_all(&mut monitor, &packets);
+
+    let snapshot = monitor.snapshot();
+    assert_eq!(snapshot.frames, 2);
+    assert_eq!(snapshot.dropped_frames, 1);
+    assert_eq!(snapshot.invalid_columns, 1);
+    // An invalid column in frame 1, a dropped and a late packet in frame 2
+    assert_eq!(snapshot.missing_columns, 1 + 16 + 16);
+    assert_eq!(snapshot.max_missing_columns, 32);
+    assert_eq!(snapshot.out_of_order, 16);
+    // Entering and leaving the shifted column
+    assert_eq!(snapshot.encoder_discontinuities, 2);
+    Ok(())
+}
+
+#[test]
+fn health_alarms_on_window_rollover() -> Result<()> {
+    let config = HealthConfig {
+        window: Duration::from_millis(100),
+        thresholds: HealthThresholds {
+            max_missing_columns: Some(0),
+            max_latency: Some(Duration::from_millis(2)),
+            min_packet_rate: Some(100.0),
+            ..Default::default()
+        },
+    };
+    let mut monitor = HealthMonitor::with_columns(COLUMNS, config)?;
+    let mut packets = frames(1, 2);
+    packets.remove(10);
+
+    let alarms = push_all(&mut monitor, &packets);
+    assert_eq!(
+        alarms,
+        vec![HealthAlarm::MissingColumns { count: 16, max: 0 }]
+    );
+    assert_eq!(
+        alarms[0].to_string(),
+        "16 missing columns in a frame exceed 0"
+    );
+    let latest = monitor.latest().unwrap();
+    assert_eq!(latest.frames, 1);
+    assert_eq!(latest.missing_columns, 16);
+    assert!(monitor.alarms().is_empty());
+    Ok(())
+}
+
+#[test]
+fn health_alarms_when_stream_stops() -> Result<()> {
+    let config = HealthConfig {
+        window: Duration::from_millis(100),
+        thresholds: HealthThresholds {
+            min_packet_rate: Some(100.0),
+            ..Default::default()
+        },
+    };
+    let mut monitor = HealthMonitor::with_columns(COLUMNS, config)?;
+    let packets = frames(1, 2);
+    assert!(push_all(&mut monitor, &packets).is_empty());
+    let last = packets.last().unwrap().columns[15].timestamp + LATENCY;
+
+    // The window with the last packets closes on time, and the next
+    // one is empty
+    assert!(monitor.poll_at(last + 50_000_000).is_empty());
+    assert!(monitor.latest().unwrap().packets > 0);
+    assert!(monitor.poll_at(last + 100_000_000).is_empty());
+    let alarms = monitor.poll_at(last + 160_000_000);
+    assert_eq!(
+        alarms,
+        vec![HealthAlarm::LowPacketRate {
+            rate: 0.0,
+            min: 100.0
+        }]
+    );
+    let latest = monitor.latest().unwrap();
+    assert_eq!(latest.packets, 0);
+    assert_eq!(latest.elapsed, Duration::from_millis(110));
+    assert_eq!(monitor.totals().packets, packets.len());
+    Ok(())
+}
+
+#[test]
+fn health_azimuth_window() -> Result<()> {
+    let mut config = common::downward_config();
+    config.azimuth_window(AzimuthWindow::new(90_000, 180_000));
+    let mut monitor = HealthMonitor::new(&config, HealthConfig::default())?;
+    assert_eq!(monitor.columns_per_frame(), 257);
+
+    // The sensor marks columns out of the window invalid
+    let mut packets = frames(1, 3);
+    for column in packets
+        .iter_mut()
+        .flat_map(|packet| packet.columns.iter_mut())
+    {
+        let measurement_id = column.measurement_id;
+        if !(256..=512).contains(&measurement_id) || (column.frame_id == 2 && measurement_id == 300)
+        {
+            column.raw_valid = 0;
+        }
+    }
+    push_all(&mut monitor, &packets);
+
+    let snapshot = monitor.snapshot();
+    assert_eq!(snapshot.frames, 2);
+    assert_eq!(snapshot.invalid_columns, 1);
+    assert_eq!(snapshot.missing_columns, 1);
+    assert_eq!(snapshot.max_missing_columns, 1);
+    Ok(())
+}
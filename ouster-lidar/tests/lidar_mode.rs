@@ -0,0 +1,72 @@
+use anyhow::Result;
+use ouster_lidar::{
+    config::Config,
+    enums::LidarMode,
+    frame_converter::FrameConverter,
+    health::{HealthConfig, HealthMonitor},
+    pcd_converter::PointCloudConverter,
+    xyz_lut::XyzLut,
+};
+use std::time::Duration;
+
+#[test]
+fn lidar_mode_known_modes() -> Result<()> {
+    let expected = [
+        ("512x10", 512, 10),
+        ("512x20", 512, 20),
+        ("1024x10", 1024, 10),
+        ("1024x20", 1024, 20),
+        ("2048x10", 2048, 10),
+        ("4096x5", 4096, 5),
+    ];
+    for (mode, (name, columns, hz)) in LidarMode::known_modes().into_iter().zip(expected) {
+        assert_eq!(mode.to_string(), name);
+        assert_eq!(name.parse::<LidarMode>()?, mode);
+        assert_eq!(serde_json::to_string(&mode)?, format!("\"{}\"", name));
+        assert_eq!(
+            serde_json::from_str::<LidarMode>(&format!("\"{}\"", name))?,
+            mode
+        );
+        assert_eq!(mode.columns_per_revolution(), Some(columns));
+        assert_eq!(mode.rotation_frequency(), Some(hz));
+        assert_eq!(
+            mode.frame_period(),
+            Some(Duration::from_secs(1) / hz as u32)
+        );
+    }
+
+    let mode = LidarMode::Mode4096x5;
+    assert_eq!(mode.frame_period(), Some(Duration::from_millis(200)));
+    assert_eq!(mode.column_time(), Some(Duration::from_nanos(48_828)));
+    Ok(())
+}
+
+#[test]
+fn lidar_mode_unknown() -> Result<()> {
+    let mode: LidarMode = serde_json::from_str("\"8192x2\"")?;
+    assert_eq!(mode, LidarMode::Unknown("8192x2".into()));
+    assert_eq!(mode.columns_per_revolution(), Some(8192));
+    assert_eq!(mode.rotation_frequency(), Some(2));
+    assert_eq!(mode.frame_period(), Some(Duration::from_millis(500)));
+    assert_eq!(serde_json::to_string(&mode)?, "\"8192x2\"");
+    assert_eq!("8192x2".parse::<LidarMode>()?, mode);
+
+    // Unparsable names survive metadata parsing but not user input
+    let mode: LidarMode = serde_json::from_str("\"MODE_FUTURE\"")?;
+    assert_eq!(mode.to_string(), "MODE_FUTURE");
+    assert_eq!(mode.columns_per_revolution(), None);
+    assert_eq!(mode.frame_period(), None);
+    assert_eq!(mode.column_time(), None);
+    assert!("MODE_FUTURE".parse::<LidarMode>().is_err());
+    assert!("0x10".parse::<LidarMode>().is_err());
+
+    // Converters and monitors reject modes of unknown dimensions
+    let mut config = Config::os_1_config();
+    config.lidar_mode(mode);
+    assert!(PointCloudConverter::from_config(config.clone()).is_err());
+    assert!(FrameConverter::from_config(config.clone()).is_err());
+    assert!(XyzLut::from_config(&config).is_err());
+    assert!(HealthMonitor::new(&config, HealthConfig::default()).is_err());
+    assert!(HealthMonitor::with_columns(0, HealthConfig::default()).is_err());
+    Ok(())
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    client::{Alerts, TimeInfo},
+    health::{HealthConfig, HealthMonitor},
+    metrics::{MetricsFormat, MetricsRegistry, MetricsServer},
+    time_sync::{TimeReference, TimeScale, TimeSyncStatus},
+};
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+const TIME_INFO: &str = r#"{"timestamp": {"time_options": {"ptp_1588": 0, "sync_pulse_in": 1, "internal_osc": 0}, "mode": "TIME_FROM_SYNC_PULSE_IN", "time": 1234.5}, "sync_pulse_in": {"diagnostics": {"count_unfiltered": 11, "last_period_nsec": 1000000000, "count": 10}, "polarity": "ACTIVE_HIGH", "locked": 1}, "multipurpose_io": {"mode": "OFF", "sync_pulse_out": {"frequency_hz": 1, "angle_deg": 360, "pulse_width_ms": 10, "polarity": "ACTIVE_HIGH"}, "nmea": {"polarity": "ACTIVE_HIGH", "baud_rate": "BAUD_9600", "diagnostics": {"io_checks": {"bit_count": 1, "start_char_count": 0, "bit_count_unfilterd": 0, "char_count": 0}, "decoding": {"not_valid_count": 2, "last_read_message": "", "utc_decoded_count": 5, "date_decoded_count": 4}}, "leap_seconds": 0, "ignore_valid_char": 0, "locked": 0}}}"#;
+
+const ALERTS: &str = r#"{"next_cursor": 3, "log": [], "active": [{"realtime": "1596000000000000000", "active": true, "msg_verbose": "", "cursor": 2, "id": "0x01000017", "category": "ETHERNET_LINK_BAD", "level": "WARNING", "msg": "Ethernet link \"bad\""}]}"#;
+
+fn registry() -> Result<MetricsRegistry> {
+    let config = HealthConfig {
+        window: Duration::from_millis(50),
+        ..Default::default()
+    };
+    let mut monitor = HealthMonitor::with_columns(1024, config)?;
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    packets.extend(common::make_frame_packets(2, 1024, 1_100_000_000, 2000));
+    packets.remove(10);
+    for packet in packets.iter() {
+        let timestamp = packet.columns[packet.columns.len() - 1].timestamp;
+        monitor.push_packet_at(packet, timestamp + 2_000_000);
+    }
+
+    let registry = MetricsRegistry::new();
+    registry.update_stream("lidar0", &monitor);
+    registry.update_time_info("lidar0", &serde_json::from_str::<TimeInfo>(TIME_INFO)?);
+    registry.update_alerts("lidar0", &serde_json::from_str::<Alerts>(ALERTS)?);
+    Ok(registry)
+}
+
+#[test]
+fn metrics_render() -> Result<()> {
+    let registry = registry()?;
+
+    let text = registry.render(MetricsFormat::Prometheus);
+    assert!(text.contains("# TYPE ouster_packets_total counter\n"));
+    assert!(text.contains("ouster_packets_total{sensor=\"lidar0\"} 127\n"));
+    assert!(text.contains("ouster_frames_total{sensor=\"lidar0\"} 1\n"));
+    assert!(text.contains("ouster_missing_columns_total{sensor=\"lidar0\"} 16\n"));
+    assert!(text.contains("ouster_frame_completeness_ratio{sensor=\"lidar0\"} "));
+    assert!(text.contains("ouster_latency_seconds{sensor=\"lidar0\"} 0.002\n"));
+    assert!(text.contains("ouster_sync_pulse_in_locked{sensor=\"lidar0\"} 1\n"));
+    assert!(text.contains("ouster_nmea_locked{sensor=\"lidar0\"} 0\n"));
+    assert!(text.contains("ouster_nmea_utc_decoded_total{sensor=\"lidar0\"} 5\n"));
+    assert!(text
+        .contains("ouster_timestamp_mode{sensor=\"lidar0\",mode=\"TIME_FROM_SYNC_PULSE_IN\"} 1\n"));
+    assert!(text.contains("ouster_alerts_logged_total{sensor=\"lidar0\"} 3\n"));
+    assert!(text.contains(
+        "ouster_alert_active{sensor=\"lidar0\",id=\"0x01000017\",category=\"ETHERNET_LINK_BAD\",level=\"WARNING\"} 1\n"
+    ));
+    assert!(!text.contains("ouster_ptp_locked"));
+    assert!(!text.contains("# EOF"));
+
+    let text = registry.render(MetricsFormat::OpenMetrics);
+    assert!(text.contains("# TYPE ouster_packets counter\n"));
+    assert!(text.contains("ouster_packets_total{sensor=\"lidar0\"} 127\n"));
+    assert!(text.ends_with("# EOF\n"));
+
+    registry.remove("lidar0");
+    assert_eq!(registry.render(MetricsFormat::Prometheus), "");
+    Ok(())
+}
+
+#[test]
+fn metrics_stopped_stream() -> Result<()> {
+    let config = HealthConfig {
+        window: Duration::from_millis(50),
+        ..Default::default()
+    };
+    let mut monitor = HealthMonitor::with_columns(1024, config)?;
+    let packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    for packet in packets.iter() {
+        let timestamp = packet.columns[packet.columns.len() - 1].timestamp;
+        monitor.push_packet_at(packet, timestamp + 2_000_000);
+    }
+    let registry = MetricsRegistry::new();
+    registry.update_stream("lidar0", &monitor);
+    assert!(!registry
+        .render(MetricsFormat::Prometheus)
+        .contains("ouster_packet_rate{sensor=\"lidar0\"} 0\n"));
+
+    // Receiving times out after the last packet
+    let last = packets.last().unwrap().columns[15].timestamp + 2_000_000;
+    for delay in [50_000_000, 110_000_000] {
+        monitor.poll_at(last + delay);
+    }
+    registry.update_stream("lidar0", &monitor);
+    let text = registry.render(MetricsFormat::Prometheus);
+    assert!(text.contains("ouster_packet_rate{sensor=\"lidar0\"} 0\n"));
+    assert!(text.contains("ouster_frame_rate{sensor=\"lidar0\"} 0\n"));
+    assert!(text.contains(&format!(
+        "ouster_packets_total{{sensor=\"lidar0\"}} {}\n",
+        packets.len()
+    )));
+    Ok(())
+}
+
+#[test]
+fn metrics_ptp() -> Result<()> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("test_files")
+        .join("time_info_ptp.json");
+    let time_info: TimeInfo = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let ptp = time_info.ptp.as_ref().unwrap();
+    assert!(ptp.locked());
+    assert_eq!(ptp.current_data_set.steps_removed, 1);
+
+    let status = TimeSyncStatus::from_time_info(&time_info);
+    assert!(status.ptp_locked);
+    assert_eq!(status.reference, TimeReference::Absolute(TimeScale::Tai));
+
+    let registry = MetricsRegistry::new();
+    registry.update_time_info("lidar0", &time_info);
+    let text = registry.render(MetricsFormat::Prometheus);
+    assert!(text.contains("ouster_ptp_locked{sensor=\"lidar0\"} 1\n"));
+    assert!(text.contains("ouster_ptp_offset_from_master_seconds{sensor=\"lidar0\"} -0.00000125\n"));
+    assert!(text.contains(
+        "ouster_ptp_info{sensor=\"lidar0\",profile=\"default\",grandmaster=\"001747.fffe.700038\"} 1\n"
+    ));
+
+    // A sensor that lost its master
+    let mut time_info = time_info;
+    time_info.ptp.as_mut().unwrap().port_data_set.port_state = "LISTENING".into();
+    registry.update_time_info("lidar0", &time_info);
+    let text = registry.render(MetricsFormat::Prometheus);
+    assert!(text.contains("ouster_ptp_locked{sensor=\"lidar0\"} 0\n"));
+    Ok(())
+}
+
+#[test]
+fn metrics_server() -> Result<()> {
+    let server = MetricsServer::bind("127.0.0.1:0", registry()?)?;
+
+    let get = |path: &str, accept: &str| -> Result<String> {
+        let mut stream = TcpStream::connect(server.local_addr())?;
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nAccept: {}\r\n\r\n",
+            path, accept
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    };
+
+    let response = get("/metrics", "text/plain")?;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+    assert!(response.contains("ouster_packets_total{sensor=\"lidar0\"} 127\n"));
+
+    let response = get("/metrics", "application/openmetrics-text; version=1.0.0")?;
+    assert!(response.contains("Content-Type: application/openmetrics-text"));
+    assert!(response.ends_with("# EOF\n"));
+
+    let response = get("/", "*/*")?;
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    Ok(())
+}
@@ -40,7 +40,7 @@ fn ouster_create_packet() -> Result<()> {
 fn ouster_pcd_converter() -> Result<()> {
     // Load config
     let config = Config::from_path("test_files/ouster_example.json")?;
-    let pcd_converter = PointCloudConverter::from_config(config);
+    let pcd_converter = PointCloudConverter::from_config(config)?;
 
     // Load pcap file
     let mut cap = Capture::from_file("test_files/ouster_example.pcap")?;
@@ -60,7 +60,7 @@ fn ouster_pcd_converter() -> Result<()> {
 fn ouster_frame_converter() -> Result<()> {
     // Load config
     let config = Config::from_path("test_files/ouster_example.json")?;
-    let mut frame_converter = FrameConverter::from_config(config);
+    let mut frame_converter = FrameConverter::from_config(config)?;
 
     // Load pcap file
     let mut cap = Capture::from_file("test_files/ouster_example.pcap")?;
@@ -0,0 +1,97 @@
+mod common;
+
+use anyhow::{ensure, Result};
+use ouster_lidar::{frame_converter::FrameConverter, parallel};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[test]
+fn parallel_helpers_keep_order() -> Result<()> {
+    let items: Vec<u32> = (0..1000).collect();
+    let squares = parallel::map_ordered(&items, |item| item * item);
+    assert!(squares
+        .iter()
+        .enumerate()
+        .all(|(index, square)| *square == (index * index) as u32));
+
+    let sum = AtomicUsize::new(0);
+    parallel::try_for_each_indexed(&items, |index, item| {
+        ensure!(index as u32 == *item);
+        sum.fetch_add(index, Ordering::Relaxed);
+        Ok(())
+    })?;
+    assert_eq!(sum.into_inner(), 999 * 1000 / 2);
+    assert!(parallel::try_for_each_indexed(&items, |index, _| {
+        ensure!(index != 500);
+        Ok(())
+    })
+    .is_err());
+    Ok(())
+}
+
+#[test]
+fn parallel_push_packets_matches_push_packet() -> Result<()> {
+    let mut packets: Vec<_> = (1..=5)
+        .flat_map(|frame_id| {
+            common::make_frame_packets(frame_id, 1024, frame_id as u64 * 100_000_000, 2000)
+        })
+        .collect();
+    // A missing timestamp, a dropped packet and a skipped frame
+    for column in packets[10].columns.iter_mut() {
+        column.timestamp = 0;
+    }
+    packets.drain(128..192);
+    packets.remove(70);
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let mut expected = vec![];
+    for packet in &packets {
+        expected.extend(converter.push_packet(packet)?);
+    }
+    expected.extend(converter.finish());
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let mut frames = vec![];
+    for batch in packets.chunks(7) {
+        frames.extend(converter.push_packets(batch)?);
+    }
+    frames.extend(converter.finish());
+
+    let frame_ids: Vec<_> = frames.iter().map(|frame| frame.frame_id).collect();
+    assert_eq!(frame_ids, [1, 2, 4, 5]);
+    assert_eq!(frames.len(), expected.len());
+    for (frame, expected) in frames.iter().zip(&expected) {
+        assert_eq!(frame.frame_id, expected.frame_id);
+        assert_eq!(frame.skipped_frame_ids, expected.skipped_frame_ids);
+        assert_eq!(frame.timestamps, expected.timestamps);
+        assert_eq!(frame.completeness, expected.completeness);
+        assert_eq!(frame.points.len(), expected.points.len());
+        for (point, expected) in frame.points.iter().zip(&expected.points) {
+            assert_eq!(point.timestamp, expected.timestamp);
+            assert_eq!(point.measurement_id, expected.measurement_id);
+            assert_eq!(point.laser_id, expected.laser_id);
+            assert_eq!(point.point, expected.point);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn parallel_push_packets_keeps_frames_before_rejected_packet() -> Result<()> {
+    let mut packets = common::make_frame_packets(1, 1024, 100_000_000, 2000);
+    packets.extend(common::make_frame_packets(2, 1024, 200_000_000, 2000));
+    // The sensor rewinds in the middle of the second frame
+    packets.insert(70, packets[64]);
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let error = converter.push_packets(&packets).unwrap_err();
+    assert_eq!(error.index, 70);
+    let frame_ids: Vec<_> = error.frames.iter().map(|frame| frame.frame_id).collect();
+    assert_eq!(frame_ids, [1]);
+
+    // The converter goes on after a reset
+    converter.reset();
+    let frames = converter.push_packets(&packets[71..])?;
+    let frame_ids: Vec<_> = frames.iter().map(|frame| frame.frame_id).collect();
+    assert_eq!(frame_ids, [2]);
+    Ok(())
+}
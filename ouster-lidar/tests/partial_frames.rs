@@ -0,0 +1,120 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    frame_converter::{Frame, FrameConverter, PartialFramePolicy, PartialReason},
+    packet::Packet,
+};
+
+/// Builds a frame of 1024 columns without packet 3 and with the
+/// columns of packet 5 marked invalid.
+fn partial_packets() -> Vec<Packet> {
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    packets.remove(3);
+    for column in packets[4].columns.iter_mut() {
+        column.raw_valid = 0;
+    }
+    packets
+}
+
+fn convert(converter: &mut FrameConverter, packets: &[Packet]) -> Result<Vec<Frame>> {
+    let frames = packets
+        .iter()
+        .map(|packet| converter.push_packet(packet))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(frames.into_iter().flatten().collect())
+}
+
+#[test]
+fn partial_frames_completeness() -> Result<()> {
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    let frames = convert(&mut converter, &packets)?;
+    assert_eq!(frames.len(), 1);
+    let completeness = &frames[0].completeness;
+    assert!(completeness.is_complete());
+    assert_eq!(completeness.valid_columns(), 1024);
+    assert_eq!(completeness.columns.len(), 1024);
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let frames = convert(&mut converter, &partial_packets())?;
+    assert_eq!(frames.len(), 1);
+    let completeness = &frames[0].completeness;
+    assert_eq!(completeness.expected_columns, 1024);
+    assert_eq!(completeness.valid_columns(), 992);
+    assert_eq!(completeness.invalid_columns, 16);
+    assert_eq!(completeness.missing_columns(), 16);
+    assert_eq!(completeness.ratio(), 992.0 / 1024.0);
+    assert_eq!(
+        completeness.partial_reason(),
+        Some(PartialReason::MissingColumns)
+    );
+    assert!(!completeness.columns.contains(48));
+    assert!(!completeness.columns.contains(80));
+    assert!(completeness.columns.contains(96));
+    assert_eq!(frames[0].points.len(), 992 * 16);
+    Ok(())
+}
+
+#[test]
+fn partial_frames_policies() -> Result<()> {
+    assert_eq!(
+        "zero-fill".parse::<PartialFramePolicy>()?,
+        PartialFramePolicy::ZeroFill
+    );
+    assert!("fill".parse::<PartialFramePolicy>().is_err());
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    converter.set_partial_frame_policy(PartialFramePolicy::Drop);
+    assert!(convert(&mut converter, &partial_packets())?.is_empty());
+    assert_eq!(converter.dropped_partial_frames(), 1);
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    converter.set_min_completeness(0.99);
+    assert!(convert(&mut converter, &partial_packets())?.is_empty());
+    assert_eq!(converter.dropped_partial_frames(), 1);
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    converter.set_partial_frame_policy(PartialFramePolicy::ZeroFill);
+    converter.set_min_completeness(0.9);
+    let frames = convert(&mut converter, &partial_packets())?;
+    assert_eq!(frames.len(), 1);
+    let frame = &frames[0];
+    assert_eq!(frame.completeness.filled_columns, 32);
+    assert_eq!(frame.points.len(), 1024 * 16);
+    assert!(frame
+        .points
+        .windows(2)
+        .all(|pair| pair[0].measurement_id <= pair[1].measurement_id));
+    let filled: Vec<_> = frame
+        .points
+        .iter()
+        .filter(|point| point.measurement_id == 50)
+        .collect();
+    assert_eq!(filled.len(), 16);
+    assert!(filled.iter().all(|point| point.distance.as_meters() == 0.0));
+    let expected = 1_000_000_000 + 50 * (100_000_000 / 1024);
+    assert!((filled[0].timestamp.as_nanos() as u64).abs_diff(expected) < 1000);
+    Ok(())
+}
+
+#[test]
+fn partial_frames_finish_reason() -> Result<()> {
+    let packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    assert!(convert(&mut converter, &packets[..32])?.is_empty());
+    let frame = converter.finish().unwrap();
+    assert!(frame.completeness.truncated);
+    assert_eq!(
+        frame.completeness.partial_reason(),
+        Some(PartialReason::EndOfStream)
+    );
+    assert_eq!(frame.completeness.missing_columns(), 512);
+
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    converter.set_partial_frame_policy(PartialFramePolicy::Drop);
+    convert(&mut converter, &packets[..32])?;
+    assert!(converter.finish().is_none());
+    Ok(())
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use anyhow::Result;
+use itertools::izip;
+use ouster_lidar::{
+    consts::{COLUMNS_PER_PACKET, PIXELS_PER_COLUMN},
+    filter::{FilterPipeline, PointFilter},
+    pcd_converter::PointCloudConverter,
+    point_buffer::PointBuffer,
+};
+
+#[test]
+fn point_buffer_matches_points() -> Result<()> {
+    let converter = PointCloudConverter::from_config(common::downward_config())?;
+    let packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    let mut buffer = PointBuffer::with_capacity(COLUMNS_PER_PACKET * PIXELS_PER_COLUMN);
+
+    for packet in packets.iter().take(4) {
+        buffer.clear();
+        let written = converter.convert_into(packet, &mut buffer)?;
+        let points = converter.convert(packet)?;
+        assert_eq!(written, points.len());
+        assert_eq!(buffer.len(), points.len());
+
+        for (point, restored) in points.iter().zip(buffer.points()) {
+            assert_eq!(restored.measurement_id, point.measurement_id);
+            assert_eq!(restored.laser_id, point.laser_id);
+            assert_eq!(restored.timestamp, point.timestamp);
+            assert_eq!(restored.distance.as_millimeters(), 2000.0);
+            for (a, b) in restored.point.iter().zip(point.point.iter()) {
+                assert!((a.as_meters() - b.as_meters()).abs() < 1e-6);
+            }
+            let azimuth = restored.azimuth_angle.as_radians() - point.azimuth_angle.as_radians();
+            assert!(azimuth.abs() < 1e-4 || (azimuth.abs() - std::f64::consts::TAU).abs() < 1e-4);
+        }
+    }
+    assert!(buffer.get(buffer.len()).is_none());
+    Ok(())
+}
+
+#[test]
+fn point_buffer_reuses_memory() -> Result<()> {
+    let mut converter = PointCloudConverter::from_config(common::downward_config())?;
+    converter.set_filter(FilterPipeline::new().with(PointFilter::Range {
+        min: 0.5,
+        max: 10.0,
+    }));
+    let packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    let mut buffer = PointBuffer::with_capacity(COLUMNS_PER_PACKET * PIXELS_PER_COLUMN);
+    let capacity = buffer.capacity();
+    let pointer = buffer.x.as_ptr();
+
+    for packet in &packets {
+        buffer.clear();
+        assert_eq!(converter.convert_into(packet, &mut buffer)?, 256);
+    }
+    assert_eq!(buffer.capacity(), capacity);
+    assert_eq!(buffer.x.as_ptr(), pointer);
+
+    // Columns are appended
+    let mut column = packets[0].columns[0];
+    assert_eq!(converter.column_into(&column, &mut buffer)?, 16);
+    assert_eq!(buffer.len(), 272);
+    column.raw_valid = 0;
+    assert_eq!(converter.column_into(&column, &mut buffer)?, 0);
+
+    converter.set_filter(FilterPipeline::new().with(PointFilter::Range {
+        min: 3.0,
+        max: 10.0,
+    }));
+    buffer.clear();
+    assert_eq!(converter.convert_into(packets[0], &mut buffer)?, 0);
+    Ok(())
+}
+
+#[test]
+fn point_buffer_filters_like_points() -> Result<()> {
+    let mut converter = PointCloudConverter::from_config(common::downward_config())?;
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    for (laser_id, pixel) in packets[0].columns[3].pixels.iter_mut().enumerate() {
+        pixel.raw_distance = laser_id as u32 * 400;
+        pixel.reflectivity = laser_id as u16;
+    }
+    converter.set_filter(
+        FilterPipeline::new()
+            .with(PointFilter::Range { min: 0.5, max: 4.0 })
+            .with(PointFilter::Reflectivity { min: 3 })
+            .with(PointFilter::Sector {
+                azimuth: [0.0, 360.0],
+                elevation: [-90.0, 0.0],
+            }),
+    );
+
+    let mut buffer = PointBuffer::new();
+    let written = converter.convert_into(packets[0], &mut buffer)?;
+    let points = converter.convert(packets[0])?;
+    assert_eq!(written, points.len());
+    assert!(written > 0 && written < 256);
+    assert!(
+        izip!(buffer.points(), points.iter()).all(|(restored, point)| {
+            restored.laser_id == point.laser_id && restored.measurement_id == point.measurement_id
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn point_buffer_keeps_points_on_error() -> Result<()> {
+    let converter = PointCloudConverter::from_config(common::downward_config())?;
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 2000);
+    let mut buffer = PointBuffer::new();
+    converter.convert_into(packets[0], &mut buffer)?;
+
+    // The last column is out of the lidar mode
+    packets[1].columns[15].measurement_id = 1024;
+    assert!(converter.convert_into(packets[1], &mut buffer).is_err());
+    assert_eq!(buffer.len(), 256);
+    assert_eq!(buffer.timestamp.len(), 256);
+    Ok(())
+}
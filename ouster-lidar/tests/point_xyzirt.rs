@@ -0,0 +1,72 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    frame_converter::FrameConverter, pcd_converter::PointCloudConverter, point_xyzirt::PointXYZIRT,
+};
+use std::{mem, time::Duration};
+
+const START: u64 = 1_000_000_000;
+
+#[test]
+fn point_xyzirt_layout() {
+    assert_eq!(mem::size_of::<PointXYZIRT>(), 32);
+    assert_eq!(mem::size_of::<PointXYZIRT<f64>>(), 48);
+    assert_eq!(mem::align_of::<PointXYZIRT>(), 4);
+
+    let points = vec![
+        PointXYZIRT {
+            x: 1.0,
+            ring: 3,
+            ..Default::default()
+        };
+        10
+    ];
+    let bytes: &[u8] = bytemuck::cast_slice(&points);
+    assert_eq!(bytes.len(), 320);
+    assert_eq!(&bytes[0..4], &1.0f32.to_le_bytes());
+    assert_eq!(&bytes[24..26], &3u16.to_le_bytes());
+    let restored: &[PointXYZIRT] = bytemuck::cast_slice(bytes);
+    assert_eq!(restored, &points[..]);
+}
+
+#[test]
+fn point_xyzirt_from_frame() -> Result<()> {
+    let mut converter = FrameConverter::from_config(common::downward_config())?;
+    let packets = common::make_frame_packets(1, 1024, START, 2000);
+    let mut frames = vec![];
+    for packet in &packets {
+        frames.extend(converter.push_packet(packet)?);
+    }
+    let frame = &frames[0];
+
+    let points = frame.to_xyzirt::<f32>();
+    let precise = frame.to_xyzirt::<f64>();
+    assert_eq!(points.len(), frame.points.len());
+    for (point, (compact, precise)) in frame.points.iter().zip(points.iter().zip(&precise)) {
+        for (value, (compact, precise)) in point
+            .point
+            .iter()
+            .zip(compact.position().iter().zip(precise.position()))
+        {
+            assert!((value.as_meters() - *compact as f64).abs() < 1e-6);
+            assert_eq!(value.as_meters(), precise);
+        }
+        assert_eq!(compact.range, 2000);
+        assert_eq!(compact.ring as u32, point.laser_id);
+        assert_eq!(compact.column, point.measurement_id);
+        assert_eq!(compact.intensity, 50.0);
+        assert_eq!(compact.ambient, 10);
+        let offset = point.timestamp - Duration::from_nanos(START);
+        assert_eq!(compact.t as u128, offset.as_nanos());
+    }
+
+    // Packets convert the same way given the frame start
+    let pcd_converter = PointCloudConverter::from_config(common::downward_config())?;
+    let mut converted: Vec<PointXYZIRT> = Vec::with_capacity(256);
+    for packet in &packets {
+        pcd_converter.convert_xyzirt_into(packet, frame.start_time(), &mut converted)?;
+    }
+    assert_eq!(converted, points);
+    Ok(())
+}
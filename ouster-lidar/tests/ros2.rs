@@ -0,0 +1,214 @@
+#![cfg(feature = "dds")]
+
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    config::Config,
+    filter::{FilterPipeline, PointFilter},
+    frame_converter::FrameConverter,
+    packet::ImuPacket,
+    ros2::{
+        from_cdr_bytes, to_cdr_bytes, Image, ImageKind, Imu, PointCloud2, Ros2Config, Ros2Publisher,
+    },
+};
+use rustdds::{DomainParticipant, QosPolicyBuilder, TopicKind};
+use std::time::{Duration, Instant};
+
+fn make_frame() -> Result<ouster_lidar::Frame> {
+    let mut converter = FrameConverter::from_config(Config::os_1_config())?;
+    let mut frames = vec![];
+    for packet in common::make_frame_packets(1, 1024, 1_000_000_000, 3000) {
+        frames.extend(converter.push_packet(packet)?);
+    }
+    Ok(frames.pop().unwrap())
+}
+
+#[test]
+fn ros2_point_cloud_layout() -> Result<()> {
+    let frame = make_frame()?;
+    let cloud = PointCloud2::from_frame_organized(&frame, 1024, "os_lidar");
+    assert_eq!((cloud.width, cloud.height), (1024, 16));
+    assert_eq!(cloud.row_step, 1024 * 48);
+    assert_eq!(cloud.data.len(), 1024 * 16 * 48);
+    assert_eq!(cloud.header.stamp.as_nanos(), 1_000_000_000);
+    let names: Vec<_> = cloud.fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "x",
+            "y",
+            "z",
+            "intensity",
+            "t",
+            "reflectivity",
+            "ring",
+            "ambient",
+            "range"
+        ]
+    );
+
+    // Pixel at row 3, column 10
+    let point = frame
+        .points
+        .iter()
+        .find(|p| p.laser_id == 3 && p.measurement_id == 10)
+        .unwrap();
+    let index = 3 * 1024 + 10;
+    let position = cloud.position(index);
+    for (lhs, rhs) in position.iter().zip(point.point.iter()) {
+        assert!((*lhs as f64 - rhs.as_meters()).abs() < 1e-5);
+    }
+    let offset = index * 48;
+    let field = |start: usize, len: usize| &cloud.data[(offset + start)..(offset + start + len)];
+    let t = u32::from_le_bytes(field(20, 4).try_into()?);
+    let ring = u16::from_le_bytes(field(26, 2).try_into()?);
+    let range = u32::from_le_bytes(field(32, 4).try_into()?);
+    assert_eq!(t as u128, point.timestamp.as_nanos() - 1_000_000_000);
+    assert_eq!(ring, 3);
+    assert_eq!(range, 3000);
+
+    // CDR encapsulation, then header stamp and frame ID string
+    let bytes = to_cdr_bytes(&cloud)?;
+    assert_eq!(bytes[..4], [0x00, 0x01, 0x00, 0x00]);
+    assert_eq!(i32::from_le_bytes(bytes[4..8].try_into()?), 1);
+    assert_eq!(u32::from_le_bytes(bytes[12..16].try_into()?), 9);
+    assert_eq!(&bytes[16..25], b"os_lidar\0");
+    assert_eq!(from_cdr_bytes::<PointCloud2>(&bytes)?, cloud);
+
+    Ok(())
+}
+
+#[test]
+fn ros2_point_cloud_over_dds() -> Result<()> {
+    // Keep the payload small since large samples are fragmented
+    // and easily dropped on loopback with default socket buffers.
+    let mut frame = make_frame()?;
+    FilterPipeline::new()
+        .with(PointFilter::Sector {
+            azimuth: [0.0, 10.0],
+            elevation: [-90.0, 90.0],
+        })
+        .apply_frame(&mut frame);
+    let config = Ros2Config {
+        domain_id: 34,
+        organized: false,
+        ..Default::default()
+    };
+
+    // A plain ROS 2 style reader on the conventional topic
+    let participant = DomainParticipant::new(config.domain_id)?;
+    let qos = QosPolicyBuilder::new().build();
+    let topic = participant.create_topic(
+        "rt/ouster/points".to_string(),
+        "sensor_msgs::msg::dds_::PointCloud2_".to_string(),
+        &qos,
+        TopicKind::NoKey,
+    )?;
+    let mut reader = participant
+        .create_subscriber(&qos)?
+        .create_datareader_no_key_cdr::<PointCloud2>(&topic, None)?;
+
+    let publisher = Ros2Publisher::new(config)?;
+    let mut received = None;
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while received.is_none() && Instant::now() < deadline {
+        publisher.publish_points(&frame, 1024)?;
+        std::thread::sleep(Duration::from_millis(200));
+        received = reader.take_next_sample()?.map(|sample| sample.into_value());
+    }
+
+    let cloud = received.unwrap();
+    assert_eq!(cloud, PointCloud2::from_frame(&frame, "os_lidar"));
+    assert_eq!(cloud.len(), frame.points.len());
+
+    Ok(())
+}
+
+fn make_imu_packet() -> ImuPacket {
+    let mut buffer = [0u8; 48];
+    buffer[0..8].copy_from_slice(&3_000_000_000u64.to_le_bytes());
+    buffer[8..16].copy_from_slice(&2_000_000_000u64.to_le_bytes());
+    buffer[16..24].copy_from_slice(&2_500_000_000u64.to_le_bytes());
+    for (index, value) in [0.0f32, 0.0, 1.0, 90.0, 0.0, -180.0].iter().enumerate() {
+        let offset = 24 + index * 4;
+        buffer[offset..(offset + 4)].copy_from_slice(&value.to_le_bytes());
+    }
+    *ImuPacket::from_slice(&buffer).unwrap()
+}
+
+#[test]
+fn ros2_imu_and_images() -> Result<()> {
+    // IMU
+    let packet = make_imu_packet();
+    let imu = Imu::from_packet(&packet, "os_imu");
+    assert_eq!(imu.header.stamp.as_nanos(), 2_500_000_000);
+    assert_eq!(imu.header.frame_id, "os_imu");
+    assert!((imu.linear_acceleration.z - 9.80665).abs() < 1e-9);
+    assert!((imu.angular_velocity.x - std::f64::consts::FRAC_PI_2).abs() < 1e-6);
+    assert!((imu.angular_velocity.z + std::f64::consts::PI).abs() < 1e-6);
+    assert_eq!(imu.orientation_covariance[0], -1.0);
+    assert_eq!(from_cdr_bytes::<Imu>(&to_cdr_bytes(&imu)?)?, imu);
+
+    // Images
+    let frame = make_frame()?;
+    let point = frame
+        .points
+        .iter()
+        .find(|p| p.laser_id == 5 && p.measurement_id == 100)
+        .unwrap();
+    let offset = 5 * 1024 + 100;
+    for kind in ImageKind::ALL {
+        let image = Image::from_frame(&frame, 1024, kind, "os_lidar");
+        assert_eq!((image.width, image.height), (1024, 16));
+        assert_eq!(image.step, 1024 * kind.pixel_size() as u32);
+        assert_eq!(image.data.len(), 1024 * 16 * kind.pixel_size());
+        let pixel = &image.data[(offset * kind.pixel_size())..((offset + 1) * kind.pixel_size())];
+        match kind {
+            ImageKind::Range => {
+                assert_eq!(image.encoding, "32FC1");
+                assert_eq!(f32::from_le_bytes(pixel.try_into()?), 3.0);
+            }
+            ImageKind::Signal => {
+                assert_eq!(image.encoding, "mono16");
+                assert_eq!(u16::from_le_bytes(pixel.try_into()?), point.signal_photons);
+            }
+            ImageKind::Reflectivity => {
+                assert_eq!(u16::from_le_bytes(pixel.try_into()?), point.reflectivity);
+            }
+            ImageKind::Noise => {
+                assert_eq!(u16::from_le_bytes(pixel.try_into()?), point.noise_photons);
+            }
+        }
+        assert_eq!(from_cdr_bytes::<Image>(&to_cdr_bytes(&image)?)?, image);
+    }
+
+    // IMU over DDS
+    let config = Ros2Config {
+        domain_id: 35,
+        ..Default::default()
+    };
+    let participant = DomainParticipant::new(config.domain_id)?;
+    let qos = QosPolicyBuilder::new().build();
+    let topic = participant.create_topic(
+        "rt/ouster/imu".to_string(),
+        "sensor_msgs::msg::dds_::Imu_".to_string(),
+        &qos,
+        TopicKind::NoKey,
+    )?;
+    let mut reader = participant
+        .create_subscriber(&qos)?
+        .create_datareader_no_key_cdr::<Imu>(&topic, None)?;
+
+    let publisher = Ros2Publisher::new(config)?;
+    let mut received = None;
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while received.is_none() && Instant::now() < deadline {
+        publisher.publish_imu(&packet)?;
+        std::thread::sleep(Duration::from_millis(200));
+        received = reader.take_next_sample()?.map(|sample| sample.into_value());
+    }
+    assert_eq!(received, Some(imu));
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use anyhow::Result;
+use ouster_lidar::{
+    config::Config,
+    rig::{Extrinsic, RigConfig, SensorRig},
+};
+use std::time::Duration;
+
+#[test]
+fn rig_extrinsic_formats_agree() -> Result<()> {
+    let from_toml = Extrinsic::from_toml_str(
+        r#"
+        rotation = [0.7071067811865476, 0.0, 0.0, 0.7071067811865476]
+        translation = [1.0, 2.0, 3.0]
+        "#,
+    )?;
+    let from_json = Extrinsic::from_json_str(
+        r#"{ "matrix": [0, -1, 0, 1,  1, 0, 0, 2,  0, 0, 1, 3,  0, 0, 0, 1] }"#,
+    )?;
+
+    for (lhs, rhs) in from_toml
+        .to_matrix()
+        .iter()
+        .zip(from_json.to_matrix().iter())
+    {
+        assert!((lhs - rhs).abs() < 1e-9, "quaternion and matrix disagree");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn rig_rejects_invalid_extrinsic() -> Result<()> {
+    assert!(Extrinsic::from_toml_str("rotation = [0, 0, 0, 0]\ntranslation = [1, 0, 0]").is_err());
+    assert!(
+        Extrinsic::from_toml_str("rotation = [1, 0, 0, 0]\ntranslation = [nan, 0, 0]").is_err()
+    );
+    assert!(Extrinsic::from_json_str(
+        r#"{ "matrix": [1, 0, 0, 0,  0, 1, 0, 0,  0, 0, 1, 0,  0, 0, 1, 1] }"#
+    )
+    .is_err());
+
+    let mut rig = SensorRig::new(Duration::from_millis(10));
+    let zero = Extrinsic::QuaternionTranslation {
+        rotation: [0.0; 4],
+        translation: [0.0; 3],
+    };
+    assert!(rig
+        .add_sensor("front", Config::os_1_config(), zero)
+        .is_err());
+    assert_eq!(rig.num_sensors(), 0);
+    Ok(())
+}
+
+#[test]
+fn rig_merges_time_aligned_frames() -> Result<()> {
+    let config = Config::os_1_config();
+    let mut rig = SensorRig::new(Duration::from_millis(20));
+    let front = rig.add_sensor(
+        "front",
+        config.clone(),
+        Extrinsic::from_toml_str("rotation = [1, 0, 0, 0]\ntranslation = [10, 0, 0]")?,
+    )?;
+    let rear = rig.add_sensor("rear", config, Extrinsic::identity())?;
+    assert_eq!(rig.sensor_id("rear"), Some(rear));
+
+    let mut merged = vec![];
+    // The rear sensor starts one frame earlier, and that frame has no partner.
+    for packet in common::make_frame_packets(0, 1024, 0, 1000) {
+        merged.extend(rig.push_packet(rear, packet)?);
+    }
+    for frame_id in 1..4 {
+        let start = frame_id as u64 * 100_000_000;
+        for packet in common::make_frame_packets(frame_id, 1024, start + 5_000_000, 1000) {
+            merged.extend(rig.push_packet(front, packet)?);
+        }
+        for packet in common::make_frame_packets(frame_id, 1024, start, 1000) {
+            merged.extend(rig.push_packet(rear, packet)?);
+        }
+    }
+    merged.extend(rig.finish());
+
+    assert_eq!(merged.len(), 3);
+    for (frame_id, frame) in (1..).zip(merged.iter()) {
+        assert_eq!(frame.frame_ids, vec![(front, frame_id), (rear, frame_id)]);
+        assert_eq!(frame.points.len(), 2 * 1024 * 16);
+
+        let front_x = frame
+            .points
+            .iter()
+            .filter(|point| point.sensor_id == front)
+            .map(|point| point.point.point[0].as_meters())
+            .fold(f64::NEG_INFINITY, f64::max);
+        let rear_x = frame
+            .points
+            .iter()
+            .filter(|point| point.sensor_id == rear)
+            .map(|point| point.point.point[0].as_meters())
+            .fold(f64::NEG_INFINITY, f64::max);
+        assert!((front_x - rear_x - 10.0).abs() < 1e-6);
+    }
+
+    let config: RigConfig = serde_json::from_value(serde_json::json!({
+        "sync_tolerance_ms": 20,
+        "sensors": [{
+            "name": "front",
+            "extrinsic": { "rotation": [1, 0, 0, 0], "translation": [0, 0, 0] },
+            "config": Config::os_1_config(),
+        }],
+    }))?;
+    assert_eq!(SensorRig::from_config(config)?.num_sensors(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn rig_bounds_pending_frames() -> Result<()> {
+    let config = Config::os_1_config();
+    let mut rig = SensorRig::new(Duration::from_millis(20));
+    rig.set_max_pending_frames(3);
+    let front = rig.add_sensor("front", config.clone(), Extrinsic::identity())?;
+    let rear = rig.add_sensor("rear", config, Extrinsic::identity())?;
+
+    // The rear sensor is silent, so frames of the front one pile up
+    let mut merged = vec![];
+    for frame_id in 1..=6 {
+        let start = frame_id as u64 * 100_000_000;
+        for packet in common::make_frame_packets(frame_id, 1024, start, 1000) {
+            merged.extend(rig.push_packet(front, packet)?);
+        }
+    }
+    assert!(merged.is_empty());
+    assert_eq!(rig.dropped_frames(front)?, 3);
+    assert_eq!(rig.dropped_frames(rear)?, 0);
+
+    // The newest queued frame still finds its partner
+    for packet in common::make_frame_packets(6, 1024, 600_000_000, 1000) {
+        merged.extend(rig.push_packet(rear, packet)?);
+    }
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].frame_ids, vec![(front, 6), (rear, 6)]);
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use ouster_lidar::{
+    client::TimeInfo,
+    enums::TimestampMode,
+    time_sync::{ClockEstimator, TimeConverter, TimeReference, TimeScale, TimeSyncStatus},
+};
+use std::{path::PathBuf, time::Duration};
+
+/// Builds time info in the mode with the NMEA lock and leap seconds.
+fn time_info(mode: &str, nmea_locked: bool, leap_seconds: u64) -> Result<TimeInfo> {
+    let json = format!(
+        r#"{{"timestamp": {{"time_options": {{"ptp_1588": 0, "sync_pulse_in": 1, "internal_osc": 0}}, "mode": "{mode}", "time": 0.0}}, "sync_pulse_in": {{"diagnostics": {{"count_unfiltered": 0, "last_period_nsec": 0, "count": 0}}, "polarity": "ACTIVE_HIGH", "locked": 1}}, "multipurpose_io": {{"mode": "OFF", "sync_pulse_out": {{"frequency_hz": 1, "angle_deg": 360, "pulse_width_ms": 10, "polarity": "ACTIVE_HIGH"}}, "nmea": {{"polarity": "ACTIVE_HIGH", "baud_rate": "BAUD_9600", "diagnostics": {{"io_checks": {{"bit_count": 0, "start_char_count": 0, "bit_count_unfilterd": 0, "char_count": 0}}, "decoding": {{"not_valid_count": 0, "last_read_message": "", "utc_decoded_count": 0, "date_decoded_count": 0}}}}, "leap_seconds": {leap_seconds}, "ignore_valid_char": 0, "locked": {locked}}}}}}}"#,
+        mode = mode,
+        leap_seconds = leap_seconds,
+        locked = nmea_locked as u8,
+    );
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Loads time info in PTP mode with the port state.
+fn ptp_time_info(port_state: &str) -> Result<TimeInfo> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("test_files")
+        .join("time_info_ptp.json");
+    let mut time_info: TimeInfo = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    time_info.ptp.as_mut().unwrap().port_data_set.port_state = port_state.into();
+    Ok(time_info)
+}
+
+#[test]
+fn time_sync_status() -> Result<()> {
+    let status = TimeSyncStatus::from_time_info(&time_info("TIME_FROM_INTERNAL_OSC", true, 0)?);
+    assert_eq!(status.mode, TimestampMode::TimeFromInternalOsc);
+    assert_eq!(status.reference, TimeReference::SinceBoot);
+    assert!(!status.is_absolute());
+
+    let status = TimeSyncStatus::from_time_info(&time_info("TIME_FROM_SYNC_PULSE_IN", false, 0)?);
+    assert!(status.sync_pulse_in_locked);
+    assert_eq!(status.reference, TimeReference::SinceBoot);
+
+    let status = TimeSyncStatus::from_time_info(&time_info("TIME_FROM_SYNC_PULSE_IN", true, 37)?);
+    assert_eq!(
+        status.reference,
+        TimeReference::Absolute(TimeScale::Utc { leap_seconds: 37 })
+    );
+
+    let status = TimeSyncStatus::from_time_info(&ptp_time_info("SLAVE")?);
+    assert!(status.ptp_locked);
+    assert_eq!(status.reference, TimeReference::Absolute(TimeScale::Tai));
+
+    // Without a PTP master or PTP state the sensor counts from boot
+    let status = TimeSyncStatus::from_time_info(&ptp_time_info("LISTENING")?);
+    assert!(!status.ptp_locked);
+    assert_eq!(status.reference, TimeReference::SinceBoot);
+    let status = TimeSyncStatus::from_time_info(&time_info("TIME_FROM_PTP_1588", false, 0)?);
+    assert!(!status.ptp_locked);
+    assert_eq!(status.reference, TimeReference::SinceBoot);
+    Ok(())
+}
+
+#[test]
+fn time_sync_absolute_conversion() -> Result<()> {
+    // 2020-01-01T00:00:00Z
+    let utc = 1_577_836_800_000_000_000i64;
+
+    let converter = TimeConverter::from_time_info(&time_info("TIME_FROM_SYNC_PULSE_IN", true, 0)?);
+    assert_eq!(converter.utc_nanos(utc as u64), Some(utc));
+    assert_eq!(converter.tai_nanos(utc as u64), Some(utc + 37_000_000_000));
+    assert_eq!(
+        converter
+            .utc_datetime(utc as u64 + 500_000_000)
+            .unwrap()
+            .to_string(),
+        "2020-01-01 00:00:00.500"
+    );
+
+    let converter = TimeConverter::from_time_info(&time_info("TIME_FROM_SYNC_PULSE_IN", true, 37)?);
+    assert_eq!(converter.utc_nanos(utc as u64 + 37_000_000_000), Some(utc));
+
+    let converter = TimeConverter::from_time_info(&ptp_time_info("SLAVE")?);
+    let tai = utc as u64 + 37_000_000_000;
+    assert_eq!(converter.utc_nanos(tai), Some(utc));
+    assert_eq!(converter.tai_nanos(tai), Some(tai as i64));
+    assert_eq!(
+        converter.tai_datetime(tai).unwrap().to_string(),
+        "2020-01-01 00:00:37"
+    );
+    let converter = converter.with_tai_utc_offset(36);
+    assert_eq!(converter.utc_nanos(tai), Some(utc + 1_000_000_000));
+    Ok(())
+}
+
+#[test]
+fn time_sync_clock_estimation() -> Result<()> {
+    let converter = TimeConverter::from_time_info(&time_info("TIME_FROM_INTERNAL_OSC", false, 0)?);
+    assert_eq!(converter.utc_nanos(0), None);
+
+    // Host clock runs 50 ppm fast and packets arrive 0.1 to 1.1 ms late
+    let boot_time = 1_577_836_800_000_000_000u64;
+    let host_time = |sensor_time: u64| boot_time + sensor_time + sensor_time / 20_000;
+    let mut estimator = ClockEstimator::new(Duration::from_secs(1), 60);
+    for index in 0..3000u64 {
+        let sensor_time = index * 10_000_000;
+        let delay = 100_000 + (index * 7919) % 1000 * 1000;
+        estimator.push(sensor_time, host_time(sensor_time) + delay);
+    }
+
+    let estimate = estimator.estimate().unwrap();
+    assert!((estimate.drift - 50e-6).abs() < 1e-6, "{:?}", estimate);
+    for sensor_time in [0, 15_000_000_000, 30_000_000_000] {
+        let error = estimate.to_host(sensor_time) - host_time(sensor_time) as i64;
+        assert!((0..300_000).contains(&error), "{} {:?}", error, estimate);
+    }
+
+    // A packet reordered across a bucket boundary is dropped
+    let before = estimator.estimate().unwrap();
+    estimator.push(30_000_000_000, host_time(30_000_000_000) + 200_000);
+    estimator.push(29_990_000_000, host_time(29_990_000_000) + 20_000_000);
+    let after = estimator.estimate().unwrap();
+    assert!((after.drift - before.drift).abs() < 1e-6, "{:?}", after);
+    assert!((after.to_host(0) - before.to_host(0)).abs() < 100_000);
+
+    // Samples before a reboot are dropped
+    estimator.push(1_000_000, boot_time + 100_000_000_000);
+    let estimate = estimator.estimate().unwrap();
+    assert_eq!(estimate.drift, 0.0);
+    assert_eq!(
+        estimate.to_host(1_000_000),
+        (boot_time + 100_000_000_000) as i64
+    );
+    Ok(())
+}
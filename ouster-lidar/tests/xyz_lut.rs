@@ -0,0 +1,133 @@
+mod common;
+
+use anyhow::Result;
+use noisy_float::types::R64;
+use ouster_lidar::{
+    config::Config, enums::LidarMode, pcd_converter::PointCloudConverter, point_buffer::PointBuffer,
+};
+use std::f64::consts::TAU;
+
+/// Computes a point the way of the sensor manual, by trigonometry on
+/// the beam angles.
+fn reference_xyz(config: &Config, measurement_id: u16, laser_id: usize, range_mm: u32) -> [f64; 3] {
+    if range_mm == 0 {
+        return [0.0; 3];
+    }
+    let columns = config.lidar_mode.columns_per_revolution().unwrap() as f64;
+    let offset = config.lidar_origin_to_beam_origin_mm.raw() / 1000.0;
+    let encoder = TAU * measurement_id as f64 / columns;
+    let azimuth = TAU
+        - (encoder
+            + config.beam_azimuth_angle_corrections[laser_id]
+                .raw()
+                .to_radians());
+    let altitude = config.beam_altitude_angles[laser_id].raw().to_radians();
+    let range = range_mm as f64 / 1000.0 - offset;
+    [
+        range * altitude.cos() * azimuth.cos() + offset * (TAU - encoder).cos(),
+        range * altitude.cos() * azimuth.sin() + offset * (TAU - encoder).sin(),
+        range * altitude.sin(),
+    ]
+}
+
+#[test]
+fn xyz_lut_matches_points() -> Result<()> {
+    let mut config = Config::os_1_config();
+    config.lidar_origin_to_beam_origin_mm(15.806);
+    let converter = PointCloudConverter::from_config(config.clone())?;
+    let lut = converter.xyz_lut();
+    assert_eq!(lut.columns_per_revolution(), 1024);
+    let direction = lut.direction(100, 3);
+    let norm: f32 = direction.iter().map(|value| value * value).sum();
+    assert!((norm - 1.0).abs() < 1e-6);
+
+    let mut packets = common::make_frame_packets(1, 1024, 1_000_000_000, 0);
+    for (index, pixel) in packets
+        .iter_mut()
+        .flat_map(|packet| packet.columns.iter_mut())
+        .flat_map(|column| column.pixels.iter_mut())
+        .enumerate()
+    {
+        pixel.raw_distance = (index as u32 * 7919) % 50_000;
+    }
+
+    let mut buffer = PointBuffer::new();
+    for packet in &packets {
+        buffer.clear();
+        converter.convert_into(packet, &mut buffer)?;
+        let points = converter.convert(packet)?;
+        assert_eq!(buffer.len(), points.len());
+        for (index, point) in points.iter().enumerate() {
+            // Both conversions share the lookup table
+            let xyz = [buffer.x[index], buffer.y[index], buffer.z[index]];
+            assert_eq!(
+                xyz.map(|value| value as f64),
+                point.point.map(|value| value.as_meters())
+            );
+
+            let expected = reference_xyz(
+                &config,
+                point.measurement_id,
+                point.laser_id as usize,
+                buffer.range[index],
+            );
+            for (lut_value, value) in xyz.iter().zip(expected) {
+                assert!(
+                    (*lut_value as f64 - value).abs() < 1e-4,
+                    "{:?} {:?}",
+                    xyz,
+                    expected
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn xyz_lut_beam_origin_offset() -> Result<()> {
+    let mut config = Config::new(
+        [R64::new(0.0); 16],
+        [R64::new(0.0); 16],
+        LidarMode::Mode1024x10,
+    );
+    config.lidar_origin_to_beam_origin_mm(20.0);
+    let converter = PointCloudConverter::from_config(config)?;
+    let lut = converter.xyz_lut();
+
+    // Horizontal beams start 20 mm off the origin towards the column
+    // azimuth, which turns clockwise
+    let mut xyz = [[0.0; 16]; 3];
+    let mut ranges = [0; 16];
+    ranges[15] = 1000;
+    lut.column_xyz(0, &ranges, &mut xyz);
+    assert!((xyz[0][15] - 1.0).abs() < 1e-4, "{:?}", xyz);
+    assert!(xyz[1][15].abs() < 1e-4);
+    assert_eq!([xyz[0][0], xyz[1][0], xyz[2][0]], [0.0; 3]);
+
+    lut.column_xyz(256, &ranges, &mut xyz);
+    assert!(xyz[0][15].abs() < 1e-4, "{:?}", xyz);
+    assert!((xyz[1][15] + 1.0).abs() < 1e-4);
+    // The beam origin lies on the horizontal beam, so there is no offset
+    assert!(lut.offset(256, 15).iter().all(|value| value.abs() < 1e-6));
+    Ok(())
+}
+
+#[test]
+fn xyz_lut_keeps_encoder_azimuth() -> Result<()> {
+    let config = Config::os_1_config();
+    let correction = config.beam_azimuth_angle_corrections[0].raw().to_radians();
+    let converter = PointCloudConverter::from_config(config)?;
+    let mut packet = common::make_frame_packets(1, 1024, 0, 2000)[0];
+    // The encoder lags a quarter column behind the measurement ID
+    packet.columns[1].encoder_ticks -= 22;
+
+    let points = converter.convert(packet)?;
+    let point = points
+        .iter()
+        .find(|point| point.measurement_id == 1 && point.laser_id == 0)
+        .unwrap();
+    let expect = TAU * 66.0 / 90112.0 + correction;
+    assert!((point.azimuth_angle.as_radians() - expect).abs() < 1e-9);
+    Ok(())
+}